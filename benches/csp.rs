@@ -0,0 +1,85 @@
+//! Times the backtracking search on the shared benchmark problems, with the
+//! default options and with the heuristics and propagation of
+//! `SearchOptions`. Run with `cargo bench --bench csp`.
+
+use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::{
+    csp::{Propagation, SearchOptions, VariableOrdering, CSP},
+    map_coloring::australia_csp,
+    queens::queens_csp,
+    sudoku::sudoku_csp,
+};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const SUDOKU: [[u32; 9]; 9] = [
+    [5, 3, 0, 0, 7, 0, 0, 0, 0],
+    [6, 0, 0, 1, 9, 5, 0, 0, 0],
+    [0, 9, 8, 0, 0, 0, 0, 6, 0],
+    [8, 0, 0, 0, 6, 0, 0, 0, 3],
+    [4, 0, 0, 8, 0, 3, 0, 0, 1],
+    [7, 0, 0, 0, 2, 0, 0, 0, 6],
+    [0, 6, 0, 0, 0, 0, 2, 8, 0],
+    [0, 0, 0, 4, 1, 9, 0, 0, 5],
+    [0, 0, 0, 0, 8, 0, 0, 7, 9],
+];
+
+/// The option sets every problem is solved with.
+fn variants() -> [(&'static str, SearchOptions); 3] {
+    [
+        ("default", SearchOptions::default()),
+        (
+            "MRV + forward checking",
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+        ),
+        (
+            "MRV + MAC",
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                propagation: Propagation::Mac,
+                ..SearchOptions::default()
+            },
+        ),
+    ]
+}
+
+/// The mean time of `iterations` searches of `csp` with `options`.
+fn time_search<V, D>(csp: &CSP<V, D>, options: SearchOptions, iterations: u32) -> Duration
+where
+    V: Eq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        let solution = csp.backtracking_search_with_options(HashMap::new(), options);
+        assert!(black_box(solution).is_some());
+    }
+
+    start.elapsed() / iterations
+}
+
+fn bench<V, D>(name: &str, csp: &CSP<V, D>, iterations: u32, variants: &[(&str, SearchOptions)])
+where
+    V: Eq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    for &(variant, options) in variants {
+        let name = format!("{} ({}):", name, variant);
+        println!("{:<36} {:?}", name, time_search(csp, options, iterations));
+    }
+}
+
+fn main() {
+    bench("Australia", &australia_csp(), 20_000, &variants());
+    bench("8 queens", &queens_csp(8), 200, &variants());
+    // Without propagation, the Sudoku takes minutes.
+    bench("Sudoku", &sudoku_csp(SUDOKU), 20, &variants()[1..]);
+}
@@ -0,0 +1,73 @@
+//! Times the Australia map coloring with the default SipHash maps and with
+//! Fx hashing. Run with `cargo bench --bench hashers`.
+
+use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::{
+    csp::{NotEqualConstraint, CSP},
+    fx_hash::FxBuildHasher,
+};
+use std::{
+    collections::HashMap,
+    hash::BuildHasher,
+    hint::black_box,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 20_000;
+
+const REGIONS: [&str; 7] = [
+    "Western Australia",
+    "Northern Territory",
+    "South Australia",
+    "Queensland",
+    "New South Wales",
+    "Victoria",
+    "Tasmania",
+];
+
+const BORDERS: [(&str, &str); 10] = [
+    ("Western Australia", "Northern Territory"),
+    ("Western Australia", "South Australia"),
+    ("Northern Territory", "South Australia"),
+    ("Northern Territory", "Queensland"),
+    ("South Australia", "Queensland"),
+    ("South Australia", "New South Wales"),
+    ("South Australia", "Victoria"),
+    ("Queensland", "New South Wales"),
+    ("New South Wales", "Victoria"),
+    ("Victoria", "Tasmania"),
+];
+
+fn australia<S: BuildHasher + Default + Clone>() -> CSP<&'static str, &'static str, S> {
+    let domains: HashMap<&str, Vec<&str>, S> = REGIONS
+        .iter()
+        .map(|region| (*region, vec!["red", "green", "blue"]))
+        .collect();
+
+    let mut csp = CSP::with_hasher(REGIONS.to_vec(), domains);
+
+    for (region1, region2) in BORDERS {
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![region1, region2])));
+    }
+
+    csp
+}
+
+fn time_solves<S: BuildHasher + Default + Clone>() -> Duration {
+    let csp = australia::<S>();
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        black_box(csp.backtracking_search(HashMap::new()));
+    }
+
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!(
+        "SipHash: {:?} per solve",
+        time_solves::<std::hash::RandomState>()
+    );
+    println!("FxHash:  {:?} per solve", time_solves::<FxBuildHasher>());
+}
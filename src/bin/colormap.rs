@@ -0,0 +1,81 @@
+//! Colors the map in an adjacency file (see `map_coloring::load_adjacency`)
+//! and prints each region's color, one `region: color` line per region.
+//!
+//! ```text
+//! colormap --colors red,green,blue australia.txt
+//! ```
+//!
+//! Exits with status 1, after printing how many colors the map needs, if the
+//! palette is too small, and with status 2 on bad arguments or an unreadable
+//! or malformed file.
+
+use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::map_coloring::{
+    color_map, load_adjacency,
+};
+use std::{env, path::PathBuf, process::ExitCode};
+
+const USAGE: &str = "usage: colormap --colors <color>,<color>,... <adjacency file>";
+
+/// The palette and the path given on the command line.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(Vec<String>, PathBuf), String> {
+    let mut colors: Option<Vec<String>> = None;
+    let mut path: Option<PathBuf> = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--colors" {
+            let list = args.next().ok_or("--colors needs a value")?;
+            colors = Some(
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|color| !color.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            );
+        } else if path.is_none() {
+            path = Some(PathBuf::from(arg));
+        } else {
+            return Err(format!("unexpected argument '{}'", arg));
+        }
+    }
+
+    Ok((
+        colors.ok_or("missing --colors")?,
+        path.ok_or("missing adjacency file")?,
+    ))
+}
+
+fn main() -> ExitCode {
+    let (colors, path) = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let adjacency = match load_adjacency(&path) {
+        Ok(adjacency) => adjacency,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+
+    let palette: Vec<&str> = colors.iter().map(String::as_str).collect();
+    match color_map(&adjacency, &palette) {
+        Ok(coloring) => {
+            let mut regions: Vec<(&String, &String)> = coloring.iter().collect();
+            regions.sort();
+
+            for (region, color) in regions {
+                println!("{}: {}", region, color);
+            }
+
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
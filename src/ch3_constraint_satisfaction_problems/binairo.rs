@@ -0,0 +1,225 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{Constraint, Propagation, SearchOptions, VariableOrdering, CSP},
+    grid::{grid_variables, Cell},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Three consecutive cells of a row or column must not all hold the same
+/// digit.
+#[derive(Clone, Debug)]
+pub struct NoThreeInARowConstraint {
+    cells: Vec<Cell>,
+}
+
+impl NoThreeInARowConstraint {
+    pub fn new(cell1: Cell, cell2: Cell, cell3: Cell) -> Self {
+        Self {
+            cells: vec![cell1, cell2, cell3],
+        }
+    }
+}
+
+impl Constraint<Cell, u8> for NoThreeInARowConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, u8>) -> bool {
+        let digits: Vec<u8> = self
+            .cells
+            .iter()
+            .filter_map(|cell| assignment.get(cell).copied())
+            .collect();
+
+        digits.len() < 3 || digits[0] != digits[1] || digits[1] != digits[2]
+    }
+}
+
+/// A row or column must hold as many 0s as 1s. Lines being filled in fail
+/// as soon as either digit fills more than half of them.
+#[derive(Clone, Debug)]
+pub struct BalancedCountConstraint {
+    cells: Vec<Cell>,
+}
+
+impl BalancedCountConstraint {
+    pub fn new(cells: Vec<Cell>) -> Self {
+        Self { cells }
+    }
+}
+
+impl Constraint<Cell, u8> for BalancedCountConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, u8>) -> bool {
+        let half = self.cells.len() / 2;
+        let digits = self.cells.iter().filter_map(|cell| assignment.get(cell));
+        let ones = digits.clone().filter(|digit| **digit == 1).count();
+        let zeros = digits.count() - ones;
+
+        ones <= half && zeros <= half
+    }
+}
+
+/// Two rows, or two columns, must not be filled in the same way. They are
+/// only compared once both are complete.
+#[derive(Clone, Debug)]
+pub struct DistinctLinesConstraint {
+    cells: Vec<Cell>,
+}
+
+impl DistinctLinesConstraint {
+    pub fn new(line1: Vec<Cell>, line2: Vec<Cell>) -> Self {
+        Self {
+            cells: line1.into_iter().chain(line2).collect(),
+        }
+    }
+}
+
+impl Constraint<Cell, u8> for DistinctLinesConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, u8>) -> bool {
+        let Some(digits) = self
+            .cells
+            .iter()
+            .map(|cell| assignment.get(cell))
+            .collect::<Option<Vec<&u8>>>()
+        else {
+            return true;
+        };
+        let (line1, line2) = digits.split_at(digits.len() / 2);
+
+        line1 != line2
+    }
+}
+
+/// Fills an `n`×`n` Binairo grid with 0s and 1s from `givens`, each a
+/// `(row, column, digit)` triple: every row and column is balanced, holds
+/// no three equal digits in a row and differs from all the others.
+///
+/// Returns `None` if the givens can't be completed, or if `n` is odd and no
+/// line can be balanced.
+pub fn solve(n: usize, givens: &[(usize, usize, u8)]) -> Option<Vec<Vec<u8>>> {
+    if n % 2 == 1 {
+        return None;
+    }
+
+    let cells = grid_variables(n, n);
+    let mut domains: HashMap<Cell, Vec<u8>> =
+        cells.iter().map(|cell| (*cell, vec![0, 1])).collect();
+
+    for &(row, column, digit) in givens {
+        domains.insert((row, column), vec![digit]);
+    }
+
+    let mut csp = CSP::new(cells, domains);
+    let rows: Vec<Vec<Cell>> = (0..n)
+        .map(|row| (0..n).map(|column| (row, column)).collect())
+        .collect();
+    let columns: Vec<Vec<Cell>> = (0..n)
+        .map(|column| (0..n).map(|row| (row, column)).collect())
+        .collect();
+
+    for lines in [&rows, &columns] {
+        for (i, line) in lines.iter().enumerate() {
+            csp.add_constraint(Arc::new(BalancedCountConstraint::new(line.clone())));
+            for window in line.windows(3) {
+                csp.add_constraint(Arc::new(NoThreeInARowConstraint::new(
+                    window[0], window[1], window[2],
+                )));
+            }
+            for other in &lines[i + 1..] {
+                csp.add_constraint(Arc::new(DistinctLinesConstraint::new(
+                    line.clone(),
+                    other.clone(),
+                )));
+            }
+        }
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    Some(
+        rows.iter()
+            .map(|row| row.iter().map(|cell| solution[cell]).collect())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_valid(grid: &[Vec<u8>]) {
+        let n = grid.len();
+        let columns: Vec<Vec<u8>> = (0..n)
+            .map(|column| grid.iter().map(|row| row[column]).collect())
+            .collect();
+
+        for lines in [grid, &columns[..]] {
+            for (i, line) in lines.iter().enumerate() {
+                assert_eq!(line.len(), n);
+                assert_eq!(line.iter().filter(|digit| **digit == 1).count(), n / 2);
+                assert!(line
+                    .windows(3)
+                    .all(|run| run[0] != run[1] || run[1] != run[2]));
+                assert!(lines[i + 1..].iter().all(|other| other != line));
+            }
+        }
+    }
+
+    #[test]
+    fn test_six_by_six_binairo() {
+        let givens = [
+            (0, 1, 1),
+            (0, 4, 0),
+            (1, 0, 0),
+            (1, 3, 0),
+            (2, 2, 1),
+            (3, 1, 0),
+            (3, 5, 1),
+            (4, 4, 1),
+            (5, 0, 1),
+            (5, 3, 1),
+        ];
+
+        let grid = solve(6, &givens).unwrap();
+
+        assert_valid(&grid);
+        for (row, column, digit) in givens {
+            assert_eq!(grid[row][column], digit);
+        }
+    }
+
+    #[test]
+    fn test_three_equal_givens_in_a_row_are_unsolvable() {
+        assert_eq!(solve(4, &[(0, 0, 1), (0, 1, 1), (0, 2, 1)]), None);
+    }
+
+    #[test]
+    fn test_odd_size_is_unsolvable() {
+        assert_eq!(solve(3, &[]), None);
+    }
+
+    #[test]
+    fn test_distinct_lines_waits_for_complete_lines() {
+        let constraint = DistinctLinesConstraint::new(vec![(0, 0), (0, 1)], vec![(1, 0), (1, 1)]);
+        let mut assignment: HashMap<Cell, u8> = [((0, 0), 0), ((0, 1), 1), ((1, 0), 0)]
+            .into_iter()
+            .collect();
+
+        assert!(constraint.satisfied(&assignment));
+        assignment.insert((1, 1), 1);
+        assert!(!constraint.satisfied(&assignment));
+    }
+}
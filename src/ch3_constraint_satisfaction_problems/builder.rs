@@ -0,0 +1,156 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{CspError, SharedConstraint, CSP};
+use std::{collections::HashMap, hash::Hash};
+
+/// Collects the variables and constraints of a CSP one at a time, checking
+/// them all together in `build`.
+///
+/// ```
+/// # use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::{
+/// #     builder::CSPBuilder, csp::NotEqualConstraint,
+/// # };
+/// # use std::sync::Arc;
+/// let csp = CSPBuilder::new()
+///     .variable("A", vec![1, 2])
+///     .variable("B", vec![1, 2])
+///     .constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(csp.count_solutions(), 2);
+/// ```
+pub struct CSPBuilder<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> {
+    variables: Vec<(V, Vec<D>)>,
+    constraints: Vec<SharedConstraint<V, D>>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> CSPBuilder<V, D> {
+    pub fn new() -> Self {
+        CSPBuilder {
+            variables: vec![],
+            constraints: vec![],
+        }
+    }
+
+    /// Adds `variable`, which takes its values from `domain`. Variables are
+    /// searched in the order they are added.
+    pub fn variable(mut self, variable: V, domain: Vec<D>) -> Self {
+        self.variables.push((variable, domain));
+        self
+    }
+
+    pub fn constraint(mut self, constraint: SharedConstraint<V, D>) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    /// Builds the CSP, failing with `CspError::DuplicateVariable` if a
+    /// variable was added twice and with `CspError::UnknownVariable` if a
+    /// constraint names a variable that wasn't added. Like `CSP::try_new`,
+    /// it accepts an empty domain, which the searches turn down before
+    /// exploring anything.
+    pub fn build(self) -> Result<CSP<V, D>, CspError<V>> {
+        let mut csp = CSP::new(vec![], HashMap::new());
+
+        for (variable, domain) in self.variables {
+            csp.add_variable(variable, domain)?;
+        }
+
+        for constraint in self.constraints {
+            csp.try_add_constraint(constraint)?;
+        }
+
+        Ok(csp)
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> Default for CSPBuilder<V, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch3_constraint_satisfaction_problems::csp::NotEqualConstraint;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_builder_solves_australia() {
+        let places = [
+            "Western Australia",
+            "Northern Territory",
+            "South Australia",
+            "Queensland",
+            "New South Wales",
+            "Victoria",
+            "Tasmania",
+        ];
+        let borders = [
+            ("Western Australia", "Northern Territory"),
+            ("Western Australia", "South Australia"),
+            ("Northern Territory", "South Australia"),
+            ("Northern Territory", "Queensland"),
+            ("South Australia", "Queensland"),
+            ("South Australia", "New South Wales"),
+            ("South Australia", "Victoria"),
+            ("Queensland", "New South Wales"),
+            ("New South Wales", "Victoria"),
+        ];
+
+        let builder = places.iter().fold(CSPBuilder::new(), |builder, place| {
+            builder.variable(*place, vec!["red", "green", "blue"])
+        });
+        let csp = borders
+            .iter()
+            .fold(builder, |builder, (place1, place2)| {
+                builder.constraint(Arc::new(NotEqualConstraint::new(vec![*place1, *place2])))
+            })
+            .build()
+            .unwrap();
+
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+
+        assert_eq!(csp.variables(), places);
+        for (place1, place2) in borders {
+            assert_ne!(solution[place1], solution[place2]);
+        }
+    }
+
+    #[test]
+    fn test_build_rejects_duplicate_variables() {
+        let result = CSPBuilder::<_, i32>::new()
+            .variable("A", vec![1])
+            .variable("A", vec![2])
+            .build();
+
+        assert_eq!(result.err(), Some(CspError::DuplicateVariable("A")));
+    }
+
+    #[test]
+    fn test_build_accepts_empty_domains_like_try_new() {
+        let csp = CSPBuilder::<_, i32>::new()
+            .variable("A", vec![1])
+            .variable("B", vec![])
+            .build()
+            .unwrap();
+        let domains = HashMap::from([("A", vec![1]), ("B", vec![])]);
+
+        assert!(CSP::try_new(vec!["A", "B"], domains).is_ok());
+        assert_eq!(csp.domain(&"B"), Some(&[][..]));
+
+        let (solution, stats) = csp.backtracking_search_with_stats(HashMap::new());
+        assert_eq!(solution, None);
+        assert_eq!(stats.nodes_visited, 0);
+    }
+
+    #[test]
+    fn test_build_rejects_constraints_on_unknown_variables() {
+        let result = CSPBuilder::new()
+            .variable("A", vec![1, 2])
+            .constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])))
+            .build();
+
+        assert_eq!(result.err(), Some(CspError::UnknownVariable("B")));
+    }
+}
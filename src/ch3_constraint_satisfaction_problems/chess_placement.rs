@@ -0,0 +1,151 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{Constraint, OrderingConstraint, Propagation, SearchOptions, VariableOrdering, CSP},
+    grid::{grid_variables, Cell},
+};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    Rook,
+    Bishop,
+    Knight,
+}
+
+impl PieceKind {
+    /// Whether a piece of this kind on `from` attacks `to`, on an empty
+    /// board. A square is taken to attack itself, so that no two pieces can
+    /// share one.
+    fn attacks(self) -> fn(Cell, Cell) -> bool {
+        match self {
+            PieceKind::Rook => |from, to| from.0 == to.0 || from.1 == to.1,
+            PieceKind::Bishop => |from, to| from.0.abs_diff(to.0) == from.1.abs_diff(to.1),
+            PieceKind::Knight => |from, to| {
+                let distance = (from.0.abs_diff(to.0), from.1.abs_diff(to.1));
+                from == to || distance == (1, 2) || distance == (2, 1)
+            },
+        }
+    }
+}
+
+/// Two pieces, whose squares are the values of the variables, must not
+/// attack each other by `attacks`, checked both ways.
+#[derive(Clone, Debug)]
+pub struct AttackConstraint {
+    pieces: Vec<usize>,
+    attacks: fn(Cell, Cell) -> bool,
+}
+
+impl AttackConstraint {
+    pub fn new(piece1: usize, piece2: usize, attacks: fn(Cell, Cell) -> bool) -> Self {
+        Self {
+            pieces: vec![piece1, piece2],
+            attacks,
+        }
+    }
+}
+
+impl Constraint<usize, Cell> for AttackConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.pieces
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, Cell>) -> bool {
+        match (
+            assignment.get(&self.pieces[0]),
+            assignment.get(&self.pieces[1]),
+        ) {
+            (Some(&square1), Some(&square2)) => {
+                !(self.attacks)(square1, square2) && !(self.attacks)(square2, square1)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Places `count` pieces of the given kind on an `n_board`×`n_board` board
+/// so that none attacks another, returning their squares as
+/// `(row, column)` in increasing order.
+pub fn place(piece: PieceKind, n_board: usize, count: usize) -> Option<Vec<Cell>> {
+    let squares = grid_variables(n_board, n_board);
+    let pieces: Vec<usize> = (0..count).collect();
+    let domains = pieces
+        .iter()
+        .map(|piece| (*piece, squares.clone()))
+        .collect();
+
+    let mut csp = CSP::new(pieces.clone(), domains);
+
+    for piece1 in 0..count {
+        for piece2 in piece1 + 1..count {
+            csp.add_constraint(Arc::new(AttackConstraint::new(
+                piece1,
+                piece2,
+                piece.attacks(),
+            )));
+        }
+    }
+    // The pieces are interchangeable: only keep them in square order.
+    csp.add_constraint(Arc::new(OrderingConstraint::new(pieces)));
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::FirstUnassigned,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    Some((0..count).map(|piece| solution[&piece]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_no_attacks(piece: PieceKind, squares: &[Cell]) {
+        for (i, square1) in squares.iter().enumerate() {
+            for square2 in &squares[i + 1..] {
+                assert!(!piece.attacks()(*square1, *square2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_eight_rooks_on_eight_by_eight_board() {
+        let squares = place(PieceKind::Rook, 8, 8).unwrap();
+
+        assert_eq!(squares.len(), 8);
+        assert_no_attacks(PieceKind::Rook, &squares);
+    }
+
+    #[test]
+    fn test_four_rooks_do_not_fit_on_three_by_three_board() {
+        assert_eq!(place(PieceKind::Rook, 3, 4), None);
+    }
+
+    #[test]
+    fn test_thirteen_knights_on_five_by_five_board() {
+        let squares = place(PieceKind::Knight, 5, 13).unwrap();
+
+        assert_eq!(squares.len(), 13);
+        assert_no_attacks(PieceKind::Knight, &squares);
+    }
+
+    #[test]
+    fn test_six_bishops_on_four_by_four_board() {
+        let squares = place(PieceKind::Bishop, 4, 6).unwrap();
+
+        assert_no_attacks(PieceKind::Bishop, &squares);
+    }
+
+    #[test]
+    fn test_attack_constraint() {
+        let constraint = AttackConstraint::new(0, 1, PieceKind::Knight.attacks());
+        let assignment =
+            |square1, square2| -> HashMap<usize, Cell> { [(0, square1), (1, square2)].into() };
+
+        assert!(!constraint.satisfied(&assignment((0, 0), (1, 2))));
+        assert!(!constraint.satisfied(&assignment((0, 0), (0, 0))));
+        assert!(constraint.satisfied(&assignment((0, 0), (1, 1))));
+        assert!(constraint.satisfied(&HashMap::from([(0, (0, 0))])));
+    }
+}
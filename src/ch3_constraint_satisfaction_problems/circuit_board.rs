@@ -0,0 +1,188 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{Constraint, CSP};
+use std::{collections::HashMap, sync::Arc};
+
+/// Where a chip goes on the board: the cell of its top-left corner, and
+/// whether it is turned a quarter so that its rows and columns swap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub row: usize,
+    pub column: usize,
+    pub rotated: bool,
+}
+
+impl Placement {
+    /// The rows and columns a chip of `size` covers when placed like this.
+    pub fn footprint(&self, size: (usize, usize)) -> (usize, usize) {
+        let (rows, columns) = size;
+
+        if self.rotated {
+            (columns, rows)
+        } else {
+            (rows, columns)
+        }
+    }
+
+    /// The cells covered by a chip of `size`, row by row.
+    pub fn cells(&self, size: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        let Placement { row, column, .. } = *self;
+        let (rows, columns) = self.footprint(size);
+
+        (row..row + rows)
+            .flat_map(move |row| (column..column + columns).map(move |column| (row, column)))
+    }
+
+    /// Every placement that keeps a chip of `size` on a board of `board`
+    /// rows and columns. A square chip is never rotated, as that would
+    /// only repeat its placements.
+    fn all(board: (usize, usize), size: (usize, usize)) -> Vec<Placement> {
+        let (board_rows, board_columns) = board;
+        let turns: &[bool] = if size.0 == size.1 {
+            &[false]
+        } else {
+            &[false, true]
+        };
+        let mut placements = vec![];
+
+        for &rotated in turns {
+            for row in 0..board_rows {
+                for column in 0..board_columns {
+                    let placement = Placement {
+                        row,
+                        column,
+                        rotated,
+                    };
+                    let (rows, columns) = placement.footprint(size);
+
+                    if row + rows <= board_rows && column + columns <= board_columns {
+                        placements.push(placement);
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+}
+
+/// Two chips, of the given sizes, must not cover a common cell. The
+/// variables are indices into the list of chips.
+#[derive(Clone, Debug)]
+pub struct NonOverlapConstraint {
+    chips: Vec<usize>,
+    sizes: [(usize, usize); 2],
+}
+
+impl NonOverlapConstraint {
+    pub fn new(chip1: usize, size1: (usize, usize), chip2: usize, size2: (usize, usize)) -> Self {
+        Self {
+            chips: vec![chip1, chip2],
+            sizes: [size1, size2],
+        }
+    }
+}
+
+impl Constraint<usize, Placement> for NonOverlapConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.chips
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, Placement>) -> bool {
+        match (
+            assignment.get(&self.chips[0]),
+            assignment.get(&self.chips[1]),
+        ) {
+            (Some(placement1), Some(placement2)) => {
+                let (rows1, columns1) = placement1.footprint(self.sizes[0]);
+                let (rows2, columns2) = placement2.footprint(self.sizes[1]);
+
+                // Two rectangles are disjoint when one lies entirely above,
+                // below, left or right of the other.
+                placement1.row + rows1 <= placement2.row
+                    || placement2.row + rows2 <= placement1.row
+                    || placement1.column + columns1 <= placement2.column
+                    || placement2.column + columns2 <= placement1.column
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Lays out `chips`, each given as `(rows, columns)`, on a board of
+/// `(rows, columns)` cells without any two of them overlapping. Chips may be
+/// rotated. The solution maps each chip's index in `chips` to its placement.
+pub fn layout(
+    board: (usize, usize),
+    chips: &[(usize, usize)],
+) -> Option<HashMap<usize, Placement>> {
+    let variables: Vec<usize> = (0..chips.len()).collect();
+    let domains = variables
+        .iter()
+        .map(|chip| (*chip, Placement::all(board, chips[*chip])))
+        .collect();
+
+    let mut csp = CSP::new(variables, domains);
+
+    for chip1 in 0..chips.len() {
+        for chip2 in chip1 + 1..chips.len() {
+            csp.add_constraint(Arc::new(NonOverlapConstraint::new(
+                chip1,
+                chips[chip1],
+                chip2,
+                chips[chip2],
+            )));
+        }
+    }
+
+    csp.backtracking_search(HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_three_chips_on_nine_by_nine_board() {
+        let chips = [(6, 1), (4, 4), (3, 3)];
+
+        let placements = layout((9, 9), &chips).unwrap();
+        let mut covered: HashMap<(usize, usize), usize> = HashMap::new();
+
+        assert_eq!(placements.len(), 3);
+        for (chip, placement) in &placements {
+            for cell in placement.cells(chips[*chip]) {
+                assert!(cell.0 < 9 && cell.1 < 9);
+                assert_eq!(covered.insert(cell, *chip), None);
+            }
+        }
+        assert_eq!(covered.len(), 6 + 16 + 9);
+    }
+
+    #[test]
+    fn test_rotation_lets_a_long_chip_fit() {
+        let placements = layout((1, 3), &[(3, 1)]).unwrap();
+
+        assert!(placements[&0].rotated);
+    }
+
+    #[test]
+    fn test_chips_that_cannot_all_fit() {
+        assert_eq!(layout((3, 3), &[(2, 2), (2, 2)]), None);
+    }
+
+    #[test]
+    fn test_non_overlap_constraint() {
+        let constraint = NonOverlapConstraint::new(0, (2, 2), 1, (1, 3));
+        let at = |row, column| Placement {
+            row,
+            column,
+            rotated: false,
+        };
+
+        let touching: HashMap<usize, Placement> = [(0, at(0, 0)), (1, at(2, 0))].into();
+        let overlapping: HashMap<usize, Placement> = [(0, at(0, 0)), (1, at(1, 1))].into();
+
+        assert!(constraint.satisfied(&touching));
+        assert!(!constraint.satisfied(&overlapping));
+        assert!(constraint.satisfied(&HashMap::from([(0, at(0, 0))])));
+    }
+}
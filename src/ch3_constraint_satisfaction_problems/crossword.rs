@@ -0,0 +1,239 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Constraint, Propagation, SearchOptions, VariableOrdering, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// What a template marks blocked cells with; any other character is a cell
+/// to fill.
+const BLOCK: char = '#';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Across,
+    Down,
+}
+
+/// A run of two or more open cells that takes one word, starting at
+/// `(row, column)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Slot {
+    pub row: usize,
+    pub column: usize,
+    pub direction: Direction,
+    pub length: usize,
+}
+
+impl Slot {
+    /// The cells of the slot, in reading order.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let slot = *self;
+
+        (0..self.length).map(move |i| slot.cell(i))
+    }
+
+    /// The cell of the slot's `i`-th letter.
+    fn cell(&self, i: usize) -> (usize, usize) {
+        match self.direction {
+            Direction::Across => (self.row, self.column + i),
+            Direction::Down => (self.row + i, self.column),
+        }
+    }
+}
+
+/// Finds the slots of `template`, whose lines are the rows of the grid.
+fn slots(template: &[&str]) -> Vec<Slot> {
+    let cells: Vec<Vec<bool>> = template
+        .iter()
+        .map(|line| line.chars().map(|cell| cell != BLOCK).collect())
+        .collect();
+    let open = |(row, column): (usize, usize)| {
+        cells
+            .get(row)
+            .and_then(|line| line.get(column))
+            .copied()
+            .unwrap_or(false)
+    };
+    let mut slots = vec![];
+
+    for (row, line) in cells.iter().enumerate() {
+        for column in 0..line.len() {
+            if !open((row, column)) {
+                continue;
+            }
+
+            for direction in [Direction::Across, Direction::Down] {
+                let continues_run = match direction {
+                    Direction::Across => column > 0 && open((row, column - 1)),
+                    Direction::Down => row > 0 && open((row - 1, column)),
+                };
+                if continues_run {
+                    continue;
+                }
+
+                let mut slot = Slot {
+                    row,
+                    column,
+                    direction,
+                    length: 0,
+                };
+                while open(slot.cell(slot.length)) {
+                    slot.length += 1;
+                }
+
+                if slot.length >= 2 {
+                    slots.push(slot);
+                }
+            }
+        }
+    }
+
+    slots
+}
+
+/// Two crossing slots must have the same letter in their shared cell,
+/// which is letter `positions.0` of the first and `positions.1` of the
+/// second.
+#[derive(Clone, Debug)]
+pub struct IntersectionConstraint {
+    slots: Vec<Slot>,
+    positions: (usize, usize),
+}
+
+impl IntersectionConstraint {
+    pub fn new(slot1: Slot, slot2: Slot, positions: (usize, usize)) -> Self {
+        Self {
+            slots: vec![slot1, slot2],
+            positions,
+        }
+    }
+}
+
+impl Constraint<Slot, String> for IntersectionConstraint {
+    fn get_variables(&self) -> &Vec<Slot> {
+        &self.slots
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Slot, String>) -> bool {
+        match (
+            assignment.get(&self.slots[0]),
+            assignment.get(&self.slots[1]),
+        ) {
+            (Some(word1), Some(word2)) => {
+                word1.chars().nth(self.positions.0) == word2.chars().nth(self.positions.1)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Fills the slots of `template`, one line per row with `#` for blocked
+/// cells, with words from `word_list`, each used at most once, so that
+/// crossing words agree on their shared letters.
+pub fn fill(template: &[&str], word_list: &[&str]) -> Option<HashMap<Slot, String>> {
+    let slots = slots(template);
+    let domains = slots
+        .iter()
+        .map(|slot| {
+            let words = word_list
+                .iter()
+                .filter(|word| word.chars().count() == slot.length)
+                .map(|word| word.to_string())
+                .collect();
+            (*slot, words)
+        })
+        .collect();
+
+    let mut csp = CSP::new(slots.clone(), domains);
+    csp.add_constraint(Arc::new(AllDifferentConstraint::new(slots.clone())));
+
+    for (i, slot1) in slots.iter().enumerate() {
+        for slot2 in &slots[i + 1..] {
+            for (position1, cell) in slot1.cells().enumerate() {
+                if let Some(position2) = slot2.cells().position(|other| other == cell) {
+                    csp.add_constraint(Arc::new(IntersectionConstraint::new(
+                        *slot1,
+                        *slot2,
+                        (position1, position2),
+                    )));
+                }
+            }
+        }
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    csp.backtracking_search_with_options(HashMap::new(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: [&str; 5] = [".....", ".#.#.", ".....", ".#.#.", "....."];
+    const WORDS: [&str; 9] = [
+        "CRANE", "ALERT", "RESIN", "CHAIR", "AREAS", "EATEN", "ABOUT", "RIPEN", "TIGER",
+    ];
+
+    #[test]
+    fn test_slots_of_template() {
+        let slots = slots(&TEMPLATE);
+
+        assert_eq!(slots.len(), 6);
+        assert!(slots.iter().all(|slot| slot.length == 5));
+        assert!(slots.contains(&Slot {
+            row: 0,
+            column: 2,
+            direction: Direction::Down,
+            length: 5,
+        }));
+    }
+
+    #[test]
+    fn test_fill_five_by_five_template() {
+        let solution = fill(&TEMPLATE, &WORDS).unwrap();
+        let mut letters: HashMap<(usize, usize), char> = HashMap::new();
+
+        assert_eq!(solution.len(), 6);
+        for (slot, word) in &solution {
+            assert!(WORDS.contains(&word.as_str()));
+            for (cell, letter) in slot.cells().zip(word.chars()) {
+                assert_eq!(*letters.entry(cell).or_insert(letter), letter);
+            }
+        }
+        assert_eq!(letters.len(), 21);
+    }
+
+    #[test]
+    fn test_fill_fails_without_a_fitting_word() {
+        let words: Vec<&str> = WORDS.into_iter().filter(|word| *word != "EATEN").collect();
+
+        assert_eq!(fill(&TEMPLATE, &words), None);
+    }
+
+    #[test]
+    fn test_intersection_constraint_waits_for_both_slots() {
+        let across = Slot {
+            row: 0,
+            column: 0,
+            direction: Direction::Across,
+            length: 3,
+        };
+        let down = Slot {
+            row: 0,
+            column: 2,
+            direction: Direction::Down,
+            length: 3,
+        };
+        let constraint = IntersectionConstraint::new(across, down, (2, 0));
+        let assignment = |word1: &str, word2: &str| -> HashMap<Slot, String> {
+            [(across, word1.to_string()), (down, word2.to_string())].into()
+        };
+
+        assert!(constraint.satisfied(&assignment("CAT", "TOE")));
+        assert!(!constraint.satisfied(&assignment("CAT", "DOE")));
+        assert!(constraint.satisfied(&HashMap::from([(across, "CAT".to_string())])));
+    }
+}
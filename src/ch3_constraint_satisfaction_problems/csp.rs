@@ -1,46 +1,472 @@
-use std::{collections::HashMap, hash::Hash, sync::Arc};
+use crate::ch3_constraint_satisfaction_problems::finite_domain::{self, FiniteDomain};
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::RandomState, HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt::{self, Debug, Display},
+    hash::{BuildHasher, Hash},
+    iter::Sum,
+    marker::PhantomData,
+    ops::{ControlFlow, RangeInclusive, Sub},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(feature = "parallel")]
+use std::{
+    sync::{atomic::AtomicUsize, OnceLock},
+    thread,
+};
 
-pub trait Constraint<V: Eq + PartialEq + Hash, D: Clone + PartialEq> {
+/// A rule over some of a CSP's variables.
+pub trait Constraint<V: Eq + PartialEq + Hash, D: PartialEq> {
     fn get_variables(&self) -> &Vec<V>;
     fn satisfied(&self, assignment: &HashMap<V, D>) -> bool;
+
+    /// Whether `a = va, b = vb` satisfies the constraint when no other
+    /// variable is assigned. This is the check arc consistency and forward
+    /// checking repeat for every pair of values, so constraints that can
+    /// answer it without building an assignment should override it.
+    fn supports(&self, a: &V, va: &D, b: &V, vb: &D) -> bool
+    where
+        V: Clone,
+        D: Clone,
+    {
+        let assignment: HashMap<V, D> = [(a.clone(), va.clone()), (b.clone(), vb.clone())]
+            .into_iter()
+            .collect();
+
+        self.satisfied(&assignment)
+    }
+
+    /// Where the constraint stands when a variable's constraints are
+    /// checked: those with a higher priority go first, ties in the order the
+    /// constraints were added. Cheap or often violated constraints can ask
+    /// for a high one so that failing assignments are turned down sooner.
+    fn priority(&self) -> i32 {
+        0
+    }
+}
+
+/// Requires the assigned variables among `variables` to take pairwise
+/// distinct values. Unassigned variables are ignored.
+#[derive(Clone, Debug)]
+pub struct AllDifferentConstraint<V: Eq + PartialEq + Hash + Clone> {
+    variables: Vec<V>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone> AllDifferentConstraint<V> {
+    pub fn new(variables: Vec<V>) -> Self {
+        Self { variables }
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> Constraint<V, D>
+    for AllDifferentConstraint<V>
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.variables.iter().enumerate().all(|(i, variable1)| {
+            assignment.get(variable1).is_none_or(|value1| {
+                self.variables[i + 1..]
+                    .iter()
+                    .all(|variable2| assignment.get(variable2) != Some(value1))
+            })
+        })
+    }
+
+    fn supports(&self, a: &V, va: &D, b: &V, vb: &D) -> bool {
+        va != vb || a == b || !self.variables.contains(a) || !self.variables.contains(b)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CspError<V> {
+    /// A variable was given without a domain.
+    MissingDomain(V),
+    /// A constraint refers to a variable that isn't part of the problem.
+    UnknownVariable(V),
+    /// A variable was registered more than once.
+    DuplicateVariable(V),
+}
+
+impl<V: Debug> Display for CspError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CspError::MissingDomain(variable) => {
+                write!(f, "variable {:?} has no domain assigned to it", variable)
+            }
+            CspError::UnknownVariable(variable) => {
+                write!(f, "variable {:?} is not part of the CSP", variable)
+            }
+            CspError::DuplicateVariable(variable) => {
+                write!(f, "variable {:?} is already part of the CSP", variable)
+            }
+        }
+    }
 }
 
-#[allow(dead_code)]
-pub struct CSP<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> {
+impl<V: Debug> Error for CspError<V> {}
+
+/// How a CSP holds its constraints, each shared by all of its variables.
+/// The `parallel` feature shares the whole CSP between threads, so there
+/// the constraints have to be `Send + Sync` as well.
+#[cfg(not(feature = "parallel"))]
+pub type SharedConstraint<V, D> = Arc<dyn Constraint<V, D>>;
+#[cfg(feature = "parallel")]
+pub type SharedConstraint<V, D> = Arc<dyn Constraint<V, D> + Send + Sync>;
+
+/// `Send + Sync` with the `parallel` feature, and no requirement without
+/// it: the bound generic code needs to put its constraints in a
+/// `SharedConstraint` either way.
+#[cfg(not(feature = "parallel"))]
+pub trait ThreadSafe {}
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> ThreadSafe for T {}
+#[cfg(feature = "parallel")]
+pub trait ThreadSafe: Send + Sync {}
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync + ?Sized> ThreadSafe for T {}
+
+type ConstraintList<V, D> = Vec<SharedConstraint<V, D>>;
+
+/// What is left of each variable's domain during a search, as indices into
+/// the variable's declared domain.
+type Domains<V, S> = HashMap<V, FiniteDomain, S>;
+
+/// A constraint satisfaction problem over variables `V` taking values `D`.
+///
+/// `S` is the hasher of the maps kept per variable; the default SipHash is
+/// DoS-resistant but slow for the small keys of typical puzzles, so a faster
+/// one such as [`FxBuildHasher`](super::fx_hash::FxBuildHasher) can be plugged in with `with_hasher`.
+pub struct CSP<V: Eq + PartialEq + Hash + Clone, D: PartialEq, S = RandomState> {
     variables: Vec<V>,
-    domains: HashMap<V, Vec<D>>,
-    constraints: HashMap<V, Vec<Arc<dyn Constraint<V, D>>>>,
+    domains: HashMap<V, Vec<D>, S>,
+    constraints: HashMap<V, ConstraintList<V, D>, S>,
+    /// The variables sharing a constraint with each variable, kept up to
+    /// date by `try_add_constraint` for the searches to look up. They are
+    /// listed in the order the constraints bring them in, so that searches
+    /// visit them in a predictable order.
+    neighbors: HashMap<V, Vec<V>, S>,
+    /// Constraints checked once per assignment as a whole rather than kept
+    /// under each of their variables, see `add_global_constraint`.
+    global_constraints: ConstraintList<V, D>,
+    soft_constraints: Vec<WeightedConstraint<V, D>>,
+}
+
+/// Shares the constraints with the original, which is cheap, while the
+/// variables and domains are copied and can be changed independently.
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq, S: Clone> Clone for CSP<V, D, S> {
+    fn clone(&self) -> Self {
+        CSP {
+            variables: self.variables.clone(),
+            domains: self.domains.clone(),
+            constraints: self.constraints.clone(),
+            neighbors: self.neighbors.clone(),
+            global_constraints: self.global_constraints.clone(),
+            soft_constraints: self.soft_constraints.clone(),
+        }
+    }
+}
+
+/// Shows the variables with the sizes of their domains and how many
+/// constraints there are, as the constraints themselves can't be printed.
+impl<V, D, S> Debug for CSP<V, D, S>
+where
+    V: Eq + PartialEq + Hash + Clone + Debug,
+    D: PartialEq,
+    S: BuildHasher + Default + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let domain_sizes: Vec<(&V, usize)> = self
+            .variables
+            .iter()
+            .map(|variable| (variable, self.values(variable).len()))
+            .collect();
+
+        f.debug_struct("CSP")
+            .field("domain_sizes", &domain_sizes)
+            .field("constraints", &self.all_constraints().len())
+            .field("soft_constraints", &self.soft_constraints.len())
+            .finish()
+    }
 }
 
-#[allow(dead_code)]
-impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> CSP<V, D> {
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> CSP<V, D> {
+    /// Panics where `try_new` would fail.
     pub fn new(variables: Vec<V>, domains: HashMap<V, Vec<D>>) -> CSP<V, D> {
+        Self::with_hasher(variables, domains)
+    }
+
+    /// Fails with `CspError::MissingDomain` if a variable has no domain and
+    /// with `CspError::DuplicateVariable` if a variable is listed twice.
+    pub fn try_new(
+        variables: Vec<V>,
+        domains: HashMap<V, Vec<D>>,
+    ) -> Result<CSP<V, D>, CspError<V>> {
+        Self::try_with_hasher(variables, domains)
+    }
+}
+
+/// The parts of the API that never copy a value, so `D` doesn't have to be
+/// `Clone`: building the CSP and checking assignments against it.
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq, S: BuildHasher + Default + Clone>
+    CSP<V, D, S>
+{
+    /// Like `new`, with the CSP's maps hashed by `S`, the hasher of `domains`.
+    pub fn with_hasher(variables: Vec<V>, domains: HashMap<V, Vec<D>, S>) -> CSP<V, D, S> {
+        Self::try_with_hasher(variables, domains).unwrap_or_else(|error| match error {
+            CspError::DuplicateVariable(_) => panic!("Every variable should be listed once."),
+            _ => panic!("Every variable should have a domain assigned to it."),
+        })
+    }
+
+    pub fn try_with_hasher(
+        variables: Vec<V>,
+        domains: HashMap<V, Vec<D>, S>,
+    ) -> Result<CSP<V, D, S>, CspError<V>> {
+        let mut seen: HashSet<&V> = HashSet::new();
+
         for variable in &variables {
             if !domains.contains_key(variable) {
-                panic!("Every variable should have a domain assigned to it.")
+                return Err(CspError::MissingDomain(variable.clone()));
+            }
+
+            if !seen.insert(variable) {
+                return Err(CspError::DuplicateVariable(variable.clone()));
             }
         }
 
-        CSP {
+        Ok(CSP {
             variables,
             domains,
-            constraints: HashMap::new(),
+            constraints: HashMap::default(),
+            neighbors: HashMap::default(),
+            global_constraints: vec![],
+            soft_constraints: vec![],
+        })
+    }
+
+    pub fn variables(&self) -> &[V] {
+        &self.variables
+    }
+
+    pub fn domain(&self, variable: &V) -> Option<&[D]> {
+        self.domains.get(variable).map(Vec::as_slice)
+    }
+
+    /// Registers a new variable with its domain; the searches pick it up
+    /// like any variable given to `new`.
+    pub fn add_variable(&mut self, variable: V, domain: Vec<D>) -> Result<(), CspError<V>> {
+        if self.variables.contains(&variable) {
+            return Err(CspError::DuplicateVariable(variable));
+        }
+
+        self.domains.insert(variable.clone(), domain);
+        self.variables.push(variable);
+
+        Ok(())
+    }
+
+    pub fn set_domain(&mut self, variable: &V, domain: Vec<D>) -> Result<(), CspError<V>> {
+        match self.domains.get_mut(variable) {
+            Some(current) => {
+                *current = domain;
+                Ok(())
+            }
+            None => Err(CspError::UnknownVariable(variable.clone())),
+        }
+    }
+
+    /// Keeps only the values of `variable`'s domain that are in `allowed`.
+    pub fn restrict_domain(&mut self, variable: &V, allowed: &[D]) -> Result<(), CspError<V>> {
+        match self.domains.get_mut(variable) {
+            Some(current) => {
+                current.retain(|value| allowed.contains(value));
+                Ok(())
+            }
+            None => Err(CspError::UnknownVariable(variable.clone())),
+        }
+    }
+
+    /// Makes the searches try `variable`'s values in the given order, by
+    /// reordering its domain: values missing from `order` come after the
+    /// ones in it, in the order they had, and values outside the domain are
+    /// ignored. `ValueOrdering::LeastConstraining` and `ValueOrdering::Random`
+    /// still impose their own order.
+    pub fn set_value_order(&mut self, variable: &V, order: Vec<D>) -> Result<(), CspError<V>> {
+        match self.domains.get_mut(variable) {
+            Some(current) => {
+                current.sort_by_key(|value| {
+                    order
+                        .iter()
+                        .position(|preferred| preferred == value)
+                        .unwrap_or(order.len())
+                });
+                Ok(())
+            }
+            None => Err(CspError::UnknownVariable(variable.clone())),
         }
     }
 
-    pub fn add_constraint(&mut self, constraint: Arc<dyn Constraint<V, D>>) {
+    /// The constraints that involve `variable`, in the order they were added.
+    pub fn constraints_for(&self, variable: &V) -> &[SharedConstraint<V, D>] {
+        self.constraints.get(variable).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `assignment`, complete or partial, satisfies every (hard)
+    /// constraint of the CSP. Soft constraints are not checked.
+    ///
+    /// Values are only compared, so they don't need to be `Clone`:
+    ///
+    /// ```
+    /// # use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::csp::{
+    /// #     NotEqualConstraint, CSP,
+    /// # };
+    /// # use std::{collections::HashMap, sync::Arc};
+    /// #[derive(PartialEq)]
+    /// struct Seat(u32);
+    ///
+    /// let domains = HashMap::from([("Ann", vec![Seat(1), Seat(2)]), ("Bob", vec![Seat(1)])]);
+    /// let mut csp = CSP::new(vec!["Ann", "Bob"], domains);
+    /// csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["Ann", "Bob"])));
+    ///
+    /// assert!(csp.is_satisfied(&HashMap::from([("Ann", Seat(2)), ("Bob", Seat(1))])));
+    /// assert!(!csp.is_satisfied(&HashMap::from([("Ann", Seat(1)), ("Bob", Seat(1))])));
+    /// ```
+    pub fn is_satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.all_constraints()
+            .iter()
+            .all(|constraint| constraint.satisfied(assignment))
+    }
+
+    /// Whether `assignment` satisfies the constraints on `variable`, and the
+    /// global ones: the check the searches make after assigning `variable`,
+    /// for validating a single move without re-checking every constraint.
+    pub fn is_consistent(&self, variable: &V, assignment: &HashMap<V, D>) -> bool {
+        self.consistent(variable.clone(), assignment)
+    }
+
+    pub fn add_constraint(&mut self, constraint: SharedConstraint<V, D>) {
+        self.try_add_constraint(constraint)
+            .unwrap_or_else(|_| panic!("Variable in constraint not in CSP"))
+    }
+
+    /// Registers `constraint` under each of its variables, leaving the CSP
+    /// untouched if any of them is unknown. Each variable's constraints are
+    /// kept by decreasing `priority`.
+    pub fn try_add_constraint(
+        &mut self,
+        constraint: SharedConstraint<V, D>,
+    ) -> Result<(), CspError<V>> {
+        self.check_known(constraint.get_variables())?;
+
         for variable in constraint.get_variables() {
-            if !self.variables.contains(variable) {
-                panic!("Variable in constraint not in CSP")
-            } else {
-                let constraints_for_variable = self
-                    .constraints
-                    .entry((*variable).clone())
-                    .or_insert(vec![]);
+            let constraints_for_variable = self
+                .constraints
+                .entry((*variable).clone())
+                .or_insert(vec![]);
+
+            let position = constraints_for_variable
+                .partition_point(|known| known.priority() >= constraint.priority());
+            constraints_for_variable.insert(position, constraint.clone());
 
-                constraints_for_variable.push(constraint.clone());
+            let neighbors = self.neighbors.entry(variable.clone()).or_default();
+            for other in constraint.get_variables() {
+                if other != variable && !neighbors.contains(other) {
+                    neighbors.push(other.clone());
+                }
             }
         }
+
+        Ok(())
+    }
+
+    /// Declares `values` interchangeable: swapping them around in any
+    /// solution gives another one, as with the colors of a map. Only the
+    /// solution where they first appear in the order given, scanning the
+    /// variables declared so far in order, is kept.
+    ///
+    /// This cuts the solutions that `all_solutions` returns down to one per
+    /// class of symmetric ones, and spares the searches from exploring them
+    /// all.
+    pub fn break_value_symmetry(&mut self, values: &[D])
+    where
+        V: ThreadSafe + 'static,
+        D: Clone + ThreadSafe + 'static,
+    {
+        self.add_constraint(Arc::new(ValuePrecedenceConstraint::new(
+            self.variables.clone(),
+            values.to_vec(),
+        )));
+    }
+
+    /// Declares `variables` interchangeable: permuting their values in any
+    /// solution gives another one. Only the solutions where their values are
+    /// in non-decreasing order, as listed, are kept.
+    pub fn break_variable_symmetry(&mut self, variables: &[V])
+    where
+        V: ThreadSafe + 'static,
+        D: Clone + Ord + ThreadSafe + 'static,
+    {
+        self.add_constraint(Arc::new(OrderingConstraint::non_strict(variables.to_vec())));
+    }
+
+    pub fn add_global_constraint(&mut self, constraint: SharedConstraint<V, D>) {
+        self.try_add_global_constraint(constraint)
+            .unwrap_or_else(|_| panic!("Variable in constraint not in CSP"))
+    }
+
+    /// Registers a constraint on the assignment as a whole, such as the
+    /// connectivity of the chosen cells, that no pair of variables decides.
+    ///
+    /// Registered with `add_constraint`, a constraint reading every variable
+    /// makes each variable a neighbor of all the others, so propagation and
+    /// the degree heuristics would revisit it for every pair. A global
+    /// constraint links no variables: it is checked after the constraints
+    /// of the variable at hand wherever the searches check consistency,
+    /// including on the values forward checking tries for a neighbor, but
+    /// never makes propagation visit a variable of its own accord.
+    pub fn try_add_global_constraint(
+        &mut self,
+        constraint: SharedConstraint<V, D>,
+    ) -> Result<(), CspError<V>> {
+        self.check_known(constraint.get_variables())?;
+        self.global_constraints.push(constraint);
+
+        Ok(())
+    }
+
+    pub fn add_soft_constraint(&mut self, constraint: WeightedConstraint<V, D>) {
+        self.try_add_soft_constraint(constraint)
+            .unwrap_or_else(|_| panic!("Variable in constraint not in CSP"))
+    }
+
+    /// Registers a soft constraint for `branch_and_bound`. Soft constraints
+    /// are ignored by every other search.
+    pub fn try_add_soft_constraint(
+        &mut self,
+        constraint: WeightedConstraint<V, D>,
+    ) -> Result<(), CspError<V>> {
+        self.check_known(constraint.constraint.get_variables())?;
+        self.soft_constraints.push(constraint);
+
+        Ok(())
+    }
+
+    fn check_known(&self, variables: &[V]) -> Result<(), CspError<V>> {
+        match variables
+            .iter()
+            .find(|variable| !self.variables.contains(variable))
+        {
+            Some(unknown) => Err(CspError::UnknownVariable(unknown.clone())),
+            None => Ok(()),
+        }
     }
 
     fn consistent(&self, variable: V, assignment: &HashMap<V, D>) -> bool {
@@ -54,124 +480,5574 @@ impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> CSP<V, D> {
             }
         }
 
-        return true;
+        self.global_constraints
+            .iter()
+            .all(|constraint| constraint.satisfied(assignment))
     }
 
-    pub fn backtracking_search<'a>(&self, assignment: HashMap<V, D>) -> Option<HashMap<V, D>> {
-        if assignment.len() == self.variables.len() {
-            return Some(assignment);
-        }
+    /// The values `variable` may take. Every variable is given a domain when
+    /// it is added, but a missing one reads as empty rather than panicking
+    /// in the middle of a search.
+    fn values(&self, variable: &V) -> &[D] {
+        self.domains.get(variable).map_or(&[], Vec::as_slice)
+    }
+
+    /// The first variable left unassigned by `assignment` whose domain is
+    /// empty, which rules out any solution extending `assignment`.
+    fn empty_domain(&self, assignment: &HashMap<V, D>) -> Option<&V> {
+        self.variables
+            .iter()
+            .find(|variable| !assignment.contains_key(variable) && self.values(variable).is_empty())
+    }
+
+    /// Whether no solution can extend `assignment`, the seed of a search,
+    /// because it assigns something other than a value of a variable's
+    /// domain, already violates a constraint or leaves a variable without
+    /// values.
+    fn dead_end(&self, assignment: &HashMap<V, D>) -> bool {
+        // An unknown variable has no values, so it is caught here too, before
+        // it can pass for one of the variables the search counts.
+        assignment
+            .iter()
+            .any(|(variable, value)| !self.values(variable).contains(value))
+            || self.empty_domain(assignment).is_some()
+            || !self.is_satisfied(assignment)
+    }
+
+    /// How many of `variable`'s constraints involve another variable that
+    /// `assignment` leaves unassigned.
+    fn degree(&self, variable: &V, assignment: &HashMap<V, D>) -> usize {
+        self.constraints_for(variable)
+            .iter()
+            .filter(|constraint| {
+                constraint
+                    .get_variables()
+                    .iter()
+                    .any(|other| other != variable && !assignment.contains_key(other))
+            })
+            .count()
+    }
 
-        let mut unassigned: Vec<V> = vec![];
+    /// Each constraint once, although it is registered under every one of
+    /// its variables.
+    fn all_constraints(&self) -> ConstraintList<V, D> {
+        // Sized for every registration, so neither grows along the way.
+        let registered: usize = self.constraints.values().map(Vec::len).sum();
+        let mut constraints: ConstraintList<V, D> =
+            Vec::with_capacity(registered + self.global_constraints.len());
+        let mut seen: HashSet<usize> = HashSet::with_capacity(registered);
 
-        for v in &self.variables {
-            if !assignment.contains_key(&v) {
-                unassigned.push(v.clone());
+        for variable in &self.variables {
+            for constraint in self.constraints_for(variable) {
+                if seen.insert(constraint_key(constraint)) {
+                    constraints.push(constraint.clone());
+                }
             }
         }
+        constraints.extend(self.global_constraints.iter().cloned());
+
+        constraints
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq, S: BuildHasher + Default + Clone>
+    CSP<V, D, S>
+{
+    /// The same variables and domains, under `constraints` alone.
+    fn with_constraints(
+        &self,
+        constraints: impl IntoIterator<Item = SharedConstraint<V, D>>,
+    ) -> CSP<V, D, S> {
+        let mut csp = CSP {
+            variables: self.variables.clone(),
+            domains: self.domains.clone(),
+            constraints: HashMap::default(),
+            neighbors: HashMap::default(),
+            global_constraints: vec![],
+            soft_constraints: vec![],
+        };
+
+        for constraint in constraints {
+            csp.add_constraint(constraint);
+        }
+
+        csp
+    }
+
+    /// Every variable's whole domain, in the representation used by the searches.
+    fn full_domains(&self) -> Domains<V, S> {
+        self.variables
+            .iter()
+            .map(|variable| {
+                let size = self.values(variable).len();
+                (variable.clone(), FiniteDomain::full(size))
+            })
+            .collect()
+    }
+
+    /// The variables that share at least one constraint with `variable`.
+    fn neighbors(&self, variable: &V) -> &[V] {
+        self.neighbors.get(variable).map_or(&[], Vec::as_slice)
+    }
 
-        let first_option = unassigned.first();
+    /// Counts the constraints on `variable`, global ones included, that
+    /// `assignment` violates.
+    fn conflicts(&self, variable: &V, assignment: &HashMap<V, D>) -> usize {
+        self.constraints_for(variable)
+            .iter()
+            .chain(
+                self.global_constraints
+                    .iter()
+                    .filter(|constraint| constraint.get_variables().contains(variable)),
+            )
+            .filter(|constraint| !constraint.satisfied(assignment))
+            .count()
+    }
+
+    /// Whether `a = value_a, b = value_b` satisfies every constraint that
+    /// involves both variables, judged on that two-variable assignment alone.
+    fn pair_consistent(&self, a: &V, value_a: &D, b: &V, value_b: &D) -> bool {
+        self.constraints.get(a).is_none_or(|constraints| {
+            constraints
+                .iter()
+                .filter(|constraint| constraint.get_variables().contains(b))
+                .all(|constraint| constraint.supports(a, value_a, b, value_b))
+        })
+    }
+
+    /// Removes the values of `a` that have no supporting value in the domain
+    /// of `b`, returning whether anything was removed.
+    fn revise(&self, domains: &mut Domains<V, S>, a: &V, b: &V) -> bool {
+        let values_a = self.values(a);
+        let values_b = self.values(b);
+        let domain_b = domains[b].clone();
+        let domain_a = domains.get_mut(a).unwrap();
+        let before = domain_a.len();
+
+        domain_a.retain(|index_a| {
+            domain_b
+                .iter()
+                .any(|index_b| self.pair_consistent(a, &values_a[index_a], b, &values_b[index_b]))
+        });
 
-        if let Some(first) = first_option {
-            for value in &self.domains[first] {
-                let mut local_assignment = assignment.clone();
-                local_assignment.insert(first.clone(), value.clone());
+        domain_a.len() != before
+    }
+
+    /// Enforces arc consistency on `self.domains` with the AC-3 algorithm.
+    ///
+    /// Every arc `(a, b)` between variables sharing a constraint is checked
+    /// until each value of `a` has a supporting value of `b`. Returns `false`
+    /// if a domain gets wiped out, which proves the problem unsatisfiable.
+    pub fn ac3(&mut self) -> bool {
+        let mut domains = self.full_domains();
+        let mut queue: VecDeque<(V, V)> = VecDeque::new();
+
+        for variable in &self.variables {
+            for neighbor in self.neighbors(variable) {
+                queue.push_back((variable.clone(), neighbor.clone()));
+            }
+        }
+
+        let mut consistent = true;
 
-                if self.consistent(first.clone(), &local_assignment) {
-                    let result = self.backtracking_search(local_assignment);
+        while let Some((a, b)) = queue.pop_front() {
+            if self.revise(&mut domains, &a, &b) {
+                if domains[&a].is_empty() {
+                    consistent = false;
+                    break;
+                }
 
-                    if result.is_some() {
-                        return result;
+                for neighbor in self.neighbors(&a) {
+                    if *neighbor != b {
+                        queue.push_back((neighbor.clone(), a.clone()));
                     }
                 }
             }
         }
 
-        None
+        for (variable, domain) in domains {
+            let Some(values) = self.domains.get_mut(&variable) else {
+                continue;
+            };
+            let mut index = 0;
+
+            values.retain(|_| {
+                let keep = domain.contains(index);
+                index += 1;
+                keep
+            });
+        }
+
+        consistent
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[allow(dead_code)]
-    #[derive(Clone)]
-    struct NotEqualConstraint<V: Eq + PartialEq + Hash + Clone> {
-        variables: Vec<V>,
+    /// Extends `assignment`, which may already fix some of the variables, to
+    /// a solution. A seed that violates a constraint on its own yields
+    /// `None` without searching.
+    pub fn backtracking_search(&self, assignment: HashMap<V, D>) -> Option<HashMap<V, D>> {
+        self.backtracking_search_with_options(assignment, SearchOptions::default())
+    }
+
+    pub fn backtracking_search_with_options(
+        &self,
+        assignment: HashMap<V, D>,
+        options: SearchOptions,
+    ) -> Option<HashMap<V, D>> {
+        Search::new(self, options).solve(assignment)
+    }
+
+    /// Searches like `backtracking_search`, but gives up with
+    /// `Completeness::Unknown` once more than `max_nodes` nodes have
+    /// been visited.
+    pub fn backtracking_search_bounded(
+        &self,
+        assignment: HashMap<V, D>,
+        max_nodes: usize,
+    ) -> Completeness<V, D> {
+        let mut search = Search::new(self, SearchOptions::default()).with_max_nodes(max_nodes);
+        let result = search.solve(assignment);
+
+        search.outcome(result)
+    }
+
+    /// Searches like `backtracking_search`, but gives up with
+    /// `Completeness::Unknown` once `timeout` has elapsed. The clock is
+    /// only read every few hundred nodes, so the search may overrun slightly.
+    pub fn backtracking_search_timeout(
+        &self,
+        assignment: HashMap<V, D>,
+        timeout: Duration,
+    ) -> Completeness<V, D> {
+        let mut search = Search::new(self, SearchOptions::default()).with_timeout(timeout);
+        let result = search.solve(assignment);
+
+        search.outcome(result)
     }
-    #[allow(dead_code)]
-    impl<V: Eq + PartialEq + Hash + Clone> NotEqualConstraint<V> {
-        fn new(variables: Vec<V>) -> Self {
-            Self { variables }
+
+    pub fn backtracking_search_with_stats(
+        &self,
+        assignment: HashMap<V, D>,
+    ) -> (Option<HashMap<V, D>>, SearchStats) {
+        self.backtracking_search_with_options_and_stats(assignment, SearchOptions::default())
+    }
+
+    /// Like `backtracking_search_with_options`, also reporting how much work
+    /// the search did, so that orderings and pruning can be compared.
+    pub fn backtracking_search_with_options_and_stats(
+        &self,
+        assignment: HashMap<V, D>,
+        options: SearchOptions,
+    ) -> (Option<HashMap<V, D>>, SearchStats) {
+        let mut search = Search::new(self, options);
+        let result = search.solve(assignment);
+
+        (result, search.stats)
+    }
+
+    /// Searches like `backtracking_search`, but keeps its own stack of
+    /// choice points instead of recursing, so problems with many thousands
+    /// of variables can't overflow the thread's stack.
+    pub fn backtracking_search_iter(&self, assignment: HashMap<V, D>) -> Option<HashMap<V, D>> {
+        Search::new(self, SearchOptions::default()).solve_iter(assignment)
+    }
+
+    /// Explains why the CSP has no solution, returning a set of its
+    /// constraints that already can't be satisfied together, or `None` if
+    /// the CSP is satisfiable.
+    ///
+    /// Starting from every constraint, each one is dropped in turn and left
+    /// out if the rest still admit no solution. The result is minimal in
+    /// that dropping any one of its constraints makes it satisfiable, though
+    /// a smaller unsatisfiable set may exist elsewhere. This runs a full search per
+    /// constraint, so it is meant for diagnosing a failure, not for the hot
+    /// path. A variable with an empty domain is explained by no constraints.
+    pub fn explain_unsat(&self) -> Option<Vec<SharedConstraint<V, D>>> {
+        if self.backtracking_search(HashMap::new()).is_some() {
+            return None;
         }
+
+        let mut core = self.all_constraints();
+        let mut i = 0;
+
+        while i < core.len() {
+            let candidate = self.with_constraints(
+                core.iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, constraint)| constraint.clone()),
+            );
+
+            if candidate.backtracking_search(HashMap::new()).is_none() {
+                core.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        Some(core)
     }
 
-    impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> Constraint<V, D>
-        for NotEqualConstraint<V>
-    {
-        fn get_variables(&self) -> &Vec<V> {
-            &self.variables
+    /// Local search with the min-conflicts heuristic.
+    ///
+    /// Starts from a random complete assignment and, for up to `max_steps`
+    /// steps, picks a random conflicted variable and moves it to the value
+    /// that violates the fewest of its constraints. The same `seed` always
+    /// produces the same run. Returns `None` if no solution was reached.
+    pub fn min_conflicts(&self, max_steps: usize, seed: u64) -> Option<HashMap<V, D>> {
+        self.min_conflicts_from(max_steps, &mut Rng::new(seed))
+    }
+
+    /// Runs [`CSP::min_conflicts`] again from a fresh random assignment
+    /// whenever `max_steps` pass without reaching a solution, up to
+    /// `max_restarts` times after the first attempt.
+    pub fn solve_with_restarts(
+        &self,
+        max_steps: usize,
+        max_restarts: usize,
+        seed: u64,
+    ) -> Option<HashMap<V, D>> {
+        self.solve_with_restarts_and_stats(max_steps, max_restarts, seed)
+            .0
+    }
+
+    /// Like [`CSP::solve_with_restarts`], also reporting how many restarts
+    /// were needed.
+    pub fn solve_with_restarts_and_stats(
+        &self,
+        max_steps: usize,
+        max_restarts: usize,
+        seed: u64,
+    ) -> (Option<HashMap<V, D>>, RestartStats) {
+        let mut rng = Rng::new(seed);
+        let mut stats = RestartStats::default();
+
+        loop {
+            let solution = self.min_conflicts_from(max_steps, &mut rng);
+
+            if solution.is_some() || stats.restarts == max_restarts {
+                return (solution, stats);
+            }
+
+            stats.restarts += 1;
+        }
+    }
+
+    /// A complete assignment giving each variable a random value of its
+    /// domain, or `None` if some domain is empty.
+    fn random_assignment(&self, rng: &mut Rng) -> Option<HashMap<V, D>> {
+        let mut assignment: HashMap<V, D> = HashMap::new();
+
+        for variable in &self.variables {
+            let domain = self.values(variable);
+
+            if domain.is_empty() {
+                return None;
+            }
+
+            assignment.insert(variable.clone(), rng.choose(domain).clone());
         }
 
-        fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
-            let values: Vec<&D> = self
+        Some(assignment)
+    }
+
+    fn min_conflicts_from(&self, max_steps: usize, rng: &mut Rng) -> Option<HashMap<V, D>> {
+        let mut assignment = self.random_assignment(rng)?;
+
+        for _ in 0..max_steps {
+            let conflicted: Vec<&V> = self
                 .variables
                 .iter()
-                .filter(|variable| assignment.get(variable).is_some())
-                .map(|variable| assignment.get(variable).unwrap())
+                .filter(|variable| self.conflicts(variable, &assignment) > 0)
                 .collect();
 
-            for (i, value1) in values.iter().enumerate() {
-                for (j, value2) in values.iter().enumerate() {
-                    if i != j && **value1 == **value2 {
-                        return false;
-                    }
+            if conflicted.is_empty() {
+                return Some(assignment);
+            }
+
+            let variable = rng.choose(&conflicted);
+            let mut best_values: Vec<&D> = vec![];
+            let mut fewest_conflicts = usize::MAX;
+
+            for value in self.values(*variable) {
+                assignment.insert((*variable).clone(), value.clone());
+                let conflicts = self.conflicts(variable, &assignment);
+
+                if conflicts < fewest_conflicts {
+                    fewest_conflicts = conflicts;
+                    best_values.clear();
+                }
+
+                if conflicts == fewest_conflicts {
+                    best_values.push(value);
                 }
             }
 
-            true
+            assignment.insert((*variable).clone(), (*rng.choose(&best_values)).clone());
         }
+
+        self.variables
+            .iter()
+            .all(|variable| self.conflicts(variable, &assignment) == 0)
+            .then_some(assignment)
     }
 
-    #[test]
-    fn test_csp() {
-        let variables = vec!["A", "B", "C"];
-        let domains: HashMap<&str, Vec<i32>> = [
-            ("A", vec![1, 2, 3]),
-            ("B", vec![1, 2, 3]),
-            ("C", vec![1, 2, 3]),
-        ]
-        .iter()
-        .cloned()
-        .collect();
+    /// Local search with a tabu list.
+    ///
+    /// Starts from a random complete assignment and, for up to `max_iters`
+    /// iterations, makes the move of a conflicted variable to another value
+    /// that leaves the fewest violated constraints, even when that is more
+    /// than before. A variable can't go back to a value it just left for
+    /// `tabu_tenure` iterations, unless that beats the best assignment seen,
+    /// which keeps the search from cycling between the same few assignments.
+    ///
+    /// Returns the assignment with the fewest violated constraints found,
+    /// with that number, which is 0 for a solution. The same `seed` always
+    /// produces the same run. Returns `None` only if some domain is empty.
+    pub fn tabu_search(
+        &self,
+        max_iters: usize,
+        tabu_tenure: usize,
+        seed: u64,
+    ) -> Option<(HashMap<V, D>, usize)> {
+        let mut rng = Rng::new(seed);
+        let mut assignment = self.random_assignment(&mut rng)?;
+        let constraints = self.all_constraints();
+        let mut violated = constraints
+            .iter()
+            .filter(|constraint| !constraint.satisfied(&assignment))
+            .count();
+        let mut best = (assignment.clone(), violated);
+        // The values variables may not take back, with the iteration from
+        // which they may again.
+        let mut tabu: Vec<(V, D, usize)> = vec![];
 
-        let mut csp = CSP::new(variables, domains);
+        for iteration in 0..max_iters {
+            if best.1 == 0 {
+                break;
+            }
 
-        let a_b_not_equal = Arc::new(NotEqualConstraint {
-            variables: vec!["A", "B"],
-        });
+            let mut best_moves: Vec<(&V, &D)> = vec![];
+            let mut fewest_violated = usize::MAX;
 
-        let b_c_not_equal = Arc::new(NotEqualConstraint {
-            variables: vec!["B", "C"],
-        });
+            for variable in &self.variables {
+                let before = self.conflicts(variable, &assignment);
 
-        let a_c_not_equal = Arc::new(NotEqualConstraint {
-            variables: vec!["A", "C"],
-        });
+                if before == 0 {
+                    continue;
+                }
 
-        csp.add_constraint(a_b_not_equal);
-        csp.add_constraint(b_c_not_equal);
-        csp.add_constraint(a_c_not_equal);
+                let current = assignment[variable].clone();
 
-        let result = csp.backtracking_search(HashMap::<&str, i32>::new());
+                for value in self.values(variable) {
+                    if *value == current {
+                        continue;
+                    }
 
-        assert!(result.is_some());
+                    assignment.insert(variable.clone(), value.clone());
+                    let after = violated + self.conflicts(variable, &assignment) - before;
+                    let is_tabu = tabu.iter().any(|(tabu_variable, tabu_value, until)| {
+                        tabu_variable == variable && tabu_value == value && *until > iteration
+                    });
 
-        let mut expected: HashMap<&str, i32> = HashMap::new();
-        expected.insert("A", 1);
-        expected.insert("B", 2);
-        expected.insert("C", 3);
+                    if is_tabu && after >= best.1 {
+                        continue;
+                    }
 
-        assert_eq!(result.unwrap(), expected);
+                    if after < fewest_violated {
+                        fewest_violated = after;
+                        best_moves.clear();
+                    }
+
+                    if after == fewest_violated {
+                        best_moves.push((variable, value));
+                    }
+                }
+
+                assignment.insert(variable.clone(), current);
+            }
+
+            if best_moves.is_empty() {
+                continue;
+            }
+
+            let (variable, value) = *rng.choose(&best_moves);
+            let previous = assignment.insert(variable.clone(), value.clone()).unwrap();
+
+            tabu.retain(|(_, _, until)| *until > iteration);
+            tabu.push((variable.clone(), previous, iteration + 1 + tabu_tenure));
+            violated = fewest_violated;
+
+            if violated < best.1 {
+                best = (assignment.clone(), violated);
+            }
+        }
+
+        Some(best)
+    }
+
+    /// Local search by simulated annealing.
+    ///
+    /// Starts from a random complete assignment and, at each step, moves a
+    /// random conflicted variable to a random other value. A move violating
+    /// `delta` more constraints than before is still made with probability
+    /// `exp(-delta / temperature)`, so the search roams freely while hot
+    /// and settles into a low-conflict assignment as it cools along
+    /// `schedule`.
+    ///
+    /// Returns the assignment with the fewest violated constraints found,
+    /// with that number, which is 0 for a solution. The same `seed` always
+    /// produces the same run. Returns `None` only if some domain is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `schedule` has a positive `min_temperature` and a
+    /// `cooling_rate` strictly between 0 and 1, as the search would never
+    /// end otherwise.
+    pub fn simulated_annealing(
+        &self,
+        schedule: CoolingSchedule,
+        seed: u64,
+    ) -> Option<(HashMap<V, D>, usize)> {
+        assert!(
+            schedule.min_temperature > 0.0
+                && schedule.cooling_rate > 0.0
+                && schedule.cooling_rate < 1.0,
+            "the cooling schedule has to reach its minimum temperature"
+        );
+
+        let mut rng = Rng::new(seed);
+        let mut assignment = self.random_assignment(&mut rng)?;
+        let mut violated = self
+            .all_constraints()
+            .iter()
+            .filter(|constraint| !constraint.satisfied(&assignment))
+            .count();
+        let mut best = (assignment.clone(), violated);
+        let mut temperature = schedule.initial_temperature;
+
+        while violated > 0 && temperature >= schedule.min_temperature {
+            let conflicted: Vec<&V> = self
+                .variables
+                .iter()
+                .filter(|variable| self.conflicts(variable, &assignment) > 0)
+                .collect();
+            // What is left violated doesn't depend on any variable, so no
+            // move can do better than the current assignment.
+            if conflicted.is_empty() {
+                return Some((assignment, violated));
+            }
+            let variable = *rng.choose(&conflicted);
+            let current = assignment[variable].clone();
+            let others: Vec<&D> = self
+                .values(variable)
+                .iter()
+                .filter(|value| **value != current)
+                .collect();
+            temperature *= schedule.cooling_rate;
+
+            if others.is_empty() {
+                continue;
+            }
+
+            let before = self.conflicts(variable, &assignment);
+            assignment.insert(variable.clone(), (*rng.choose(&others)).clone());
+            let after = self.conflicts(variable, &assignment);
+
+            let accepted = after <= before
+                || rng.next_f64() < (-((after - before) as f64) / temperature).exp();
+
+            if accepted {
+                violated = violated + after - before;
+
+                if violated < best.1 {
+                    best = (assignment.clone(), violated);
+                }
+            } else {
+                assignment.insert(variable.clone(), current);
+            }
+        }
+
+        Some(best)
+    }
+
+    /// Finds the complete assignment extending `assignment` that satisfies
+    /// every (hard) constraint and minimizes the summed weight of the
+    /// violated soft constraints, together with that penalty.
+    ///
+    /// A soft constraint is only judged once all of its variables are
+    /// assigned, so the penalty of a partial assignment never decreases as
+    /// it grows; branches that already cost as much as the best complete
+    /// assignment found so far are cut. Like `backtracking_search`, it
+    /// returns `None` for a seed `assignment` that already breaks a hard
+    /// constraint.
+    pub fn branch_and_bound(&self, assignment: HashMap<V, D>) -> Option<(HashMap<V, D>, f64)> {
+        if self.dead_end(&assignment) {
+            return None;
+        }
+
+        let penalty = self
+            .soft_constraints
+            .iter()
+            .filter(|soft| {
+                soft.constraint
+                    .get_variables()
+                    .iter()
+                    .all(|variable| assignment.contains_key(variable))
+                    && !soft.constraint.satisfied(&assignment)
+            })
+            .map(|soft| soft.weight)
+            .sum();
+
+        let mut best = None;
+        self.branch_and_bound_from(&mut assignment.clone(), penalty, &mut best);
+
+        best
+    }
+
+    fn branch_and_bound_from(
+        &self,
+        assignment: &mut HashMap<V, D>,
+        penalty: f64,
+        best: &mut Option<(HashMap<V, D>, f64)>,
+    ) {
+        if best
+            .as_ref()
+            .is_some_and(|(_, best_penalty)| penalty >= *best_penalty)
+        {
+            return;
+        }
+
+        let Some(variable) = self
+            .variables
+            .iter()
+            .find(|variable| !assignment.contains_key(variable))
+            .cloned()
+        else {
+            *best = Some((assignment.clone(), penalty));
+            return;
+        };
+
+        for value in self.values(&variable) {
+            assignment.insert(variable.clone(), value.clone());
+
+            if self.consistent(variable.clone(), assignment) {
+                // Soft constraints on `variable` that are now fully assigned
+                // became so just now, so each is counted exactly once.
+                let added: f64 = self
+                    .soft_constraints
+                    .iter()
+                    .filter(|soft| {
+                        let variables = soft.constraint.get_variables();
+
+                        variables.contains(&variable)
+                            && variables.iter().all(|other| assignment.contains_key(other))
+                            && !soft.constraint.satisfied(assignment)
+                    })
+                    .map(|soft| soft.weight)
+                    .sum();
+
+                self.branch_and_bound_from(assignment, penalty + added, best);
+            }
+        }
+
+        assignment.remove(&variable);
+    }
+
+    /// Finds the solution with the best `objective` value according to
+    /// `goal`. The objective is only evaluated on complete assignments, so
+    /// every solution is visited; see `optimize_with_bound` to prune.
+    pub fn optimize(
+        &self,
+        objective: impl Fn(&HashMap<V, D>) -> i64,
+        goal: Goal,
+    ) -> Option<HashMap<V, D>> {
+        let unbounded = match goal {
+            Goal::Maximize => i64::MAX,
+            Goal::Minimize => i64::MIN,
+        };
+
+        self.optimize_with_bound(objective, |_| unbounded, goal)
+    }
+
+    /// Branch and bound over the solutions: `bound` must be admissible, i.e.
+    /// given a partial assignment it returns a value no complete extension
+    /// of it can beat (an upper bound when maximizing, a lower bound when
+    /// minimizing). Branches whose bound doesn't beat the best solution
+    /// found so far are cut.
+    pub fn optimize_with_bound(
+        &self,
+        objective: impl Fn(&HashMap<V, D>) -> i64,
+        bound: impl Fn(&HashMap<V, D>) -> i64,
+        goal: Goal,
+    ) -> Option<HashMap<V, D>> {
+        let mut best = None;
+        self.optimize_from(&mut HashMap::new(), &objective, &bound, goal, &mut best);
+
+        best.map(|(solution, _)| solution)
+    }
+
+    fn optimize_from(
+        &self,
+        assignment: &mut HashMap<V, D>,
+        objective: &impl Fn(&HashMap<V, D>) -> i64,
+        bound: &impl Fn(&HashMap<V, D>) -> i64,
+        goal: Goal,
+        best: &mut Option<(HashMap<V, D>, i64)>,
+    ) {
+        let Some(variable) = self
+            .variables
+            .iter()
+            .find(|variable| !assignment.contains_key(variable))
+            .cloned()
+        else {
+            let value = objective(assignment);
+
+            if best
+                .as_ref()
+                .is_none_or(|(_, incumbent)| goal.improves(value, *incumbent))
+            {
+                *best = Some((assignment.clone(), value));
+            }
+
+            return;
+        };
+
+        if best
+            .as_ref()
+            .is_some_and(|(_, incumbent)| !goal.improves(bound(assignment), *incumbent))
+        {
+            return;
+        }
+
+        for value in self.values(&variable) {
+            assignment.insert(variable.clone(), value.clone());
+
+            if self.consistent(variable.clone(), assignment) {
+                self.optimize_from(assignment, objective, bound, goal, best);
+            }
+        }
+
+        assignment.remove(&variable);
+    }
+
+    /// Lazily enumerates every solution; each call to `next` resumes the
+    /// backtracking search where the previous solution was found.
+    pub fn solutions(&self) -> SolutionIter<'_, V, D, S> {
+        SolutionIter {
+            csp: self,
+            assignment: HashMap::new(),
+            stack: vec![],
+            started: false,
+        }
+    }
+
+    pub fn all_solutions(&self) -> Vec<HashMap<V, D>> {
+        self.solutions().collect()
+    }
+
+    /// Like `all_solutions`, but leaves out each solution that one of
+    /// `symmetries`, or a combination of them, maps an earlier solution to.
+    ///
+    /// Unlike `break_value_symmetry` and `break_variable_symmetry`, this
+    /// doesn't shrink the search: every solution is still found, then
+    /// compared against the equivalents of those kept so far.
+    pub fn all_solutions_canonical(&self, symmetries: &[Symmetry<V, D>]) -> Vec<HashMap<V, D>> {
+        let mut equivalents: Vec<HashMap<V, D>> = vec![];
+        let mut canonical = vec![];
+
+        for solution in self.solutions() {
+            if equivalents.contains(&solution) {
+                continue;
+            }
+
+            let orbit_start = equivalents.len();
+            equivalents.push(solution.clone());
+            let mut i = orbit_start;
+            while i < equivalents.len() {
+                for symmetry in symmetries {
+                    let image = symmetry.apply(&equivalents[i]);
+                    if !equivalents[orbit_start..].contains(&image) {
+                        equivalents.push(image);
+                    }
+                }
+                i += 1;
+            }
+
+            canonical.push(solution);
+        }
+
+        canonical
+    }
+
+    /// Counts the solutions without keeping any of them around: a single
+    /// assignment is updated in place and each complete one bumps a counter.
+    pub fn count_solutions(&self) -> usize {
+        let mut count = 0;
+
+        self.backtracking_search_for_each(|_| {
+            count += 1;
+            ControlFlow::Continue(())
+        });
+
+        count
+    }
+
+    /// Counts the solutions like `count_solutions`, calling `on_progress`
+    /// with the running count each time another `every` solutions have been
+    /// found, so a long count can show it is still making headway.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    pub fn count_solutions_with_progress(
+        &self,
+        every: usize,
+        mut on_progress: impl FnMut(usize),
+    ) -> usize {
+        assert!(
+            every > 0,
+            "progress needs to be reported every 1 or more solutions"
+        );
+
+        let mut count = 0;
+
+        self.backtracking_search_for_each(|_| {
+            count += 1;
+            if count % every == 0 {
+                on_progress(count);
+            }
+            ControlFlow::Continue(())
+        });
+
+        count
+    }
+
+    /// Calls `visit` with every solution, in the order `solutions` yields
+    /// them, until it returns `ControlFlow::Break`. Nothing is allocated per
+    /// solution: `visit` sees the one assignment the search updates in place,
+    /// so it has to clone whatever it wants to keep.
+    pub fn backtracking_search_for_each(
+        &self,
+        mut visit: impl FnMut(&HashMap<V, D>) -> ControlFlow<()>,
+    ) {
+        if self.dead_end(&HashMap::new()) {
+            return;
+        }
+
+        let _ = self.for_each_from(0, &mut HashMap::new(), &mut visit);
+    }
+
+    fn for_each_from(
+        &self,
+        depth: usize,
+        assignment: &mut HashMap<V, D>,
+        visit: &mut impl FnMut(&HashMap<V, D>) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let Some(variable) = self.variables.get(depth) else {
+            return visit(assignment);
+        };
+
+        for value in self.values(variable) {
+            assignment.insert(variable.clone(), value.clone());
+
+            if self.consistent(variable.clone(), assignment) {
+                self.for_each_from(depth + 1, assignment, visit)?;
+            }
+        }
+
+        assignment.remove(variable);
+
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<V, D, S> CSP<V, D, S>
+where
+    V: Eq + PartialEq + Hash + Clone + Send + Sync,
+    D: Clone + PartialEq + Send + Sync,
+    S: BuildHasher + Default + Clone + Sync,
+{
+    /// Searches like `backtracking_search` from an empty assignment, solving
+    /// the subtree below each value of the first variable on its own thread.
+    ///
+    /// Returns the first solution any thread finds and stops the others, so
+    /// which solution comes back may differ from the sequential search when
+    /// there are several. Needs the `parallel` feature, and `V` and `D` to be
+    /// `Send + Sync` as assignments are built on the worker threads; the
+    /// feature makes every `SharedConstraint` `Send + Sync` too.
+    pub fn par_backtracking_search(&self) -> Option<HashMap<V, D>> {
+        let Some(first) = self.variables.first() else {
+            return Some(HashMap::new());
+        };
+
+        let values = self.values(first);
+        let next_value = AtomicUsize::new(0);
+        let cancelled = AtomicBool::new(false);
+        let solution = OnceLock::new();
+        let workers = thread::available_parallelism()
+            .map_or(1, |workers| workers.get())
+            .min(values.len());
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let index = next_value.fetch_add(1, Ordering::Relaxed);
+                    if index >= values.len() || cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let assignment: HashMap<V, D> = [(first.clone(), values[index].clone())]
+                        .into_iter()
+                        .collect();
+                    if !self.consistent(first.clone(), &assignment) {
+                        continue;
+                    }
+
+                    let mut search =
+                        Search::new(self, SearchOptions::default()).with_cancellation(&cancelled);
+                    if let Some(found) = search.solve(assignment) {
+                        // Only the first thread to get here wins.
+                        let _ = solution.set(found);
+                        cancelled.store(true, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+
+        solution.into_inner()
+    }
+}
+
+/// Iterator over the solutions of a [`CSP`], returned by [`CSP::solutions`].
+///
+/// The search state lives in an explicit stack rather than on the call stack,
+/// so the iterator can stop between solutions and be dropped at any time.
+pub struct SolutionIter<'a, V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq, S = RandomState>
+{
+    csp: &'a CSP<V, D, S>,
+    assignment: HashMap<V, D>,
+    /// One entry per variable currently being assigned, in variable order,
+    /// holding the index of the next domain value to try for it.
+    stack: Vec<usize>,
+    started: bool,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq, S: BuildHasher + Default + Clone>
+    Iterator for SolutionIter<'_, V, D, S>
+{
+    type Item = HashMap<V, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let csp = self.csp;
+
+        if !self.started {
+            self.started = true;
+
+            if csp.variables.is_empty() {
+                return Some(HashMap::new());
+            }
+
+            self.stack.push(0);
+        }
+
+        while !self.stack.is_empty() {
+            let depth = self.stack.len() - 1;
+            let variable = &csp.variables[depth];
+            let domain = csp.values(variable);
+            let next_value = &mut self.stack[depth];
+
+            if *next_value == domain.len() {
+                self.assignment.remove(variable);
+                self.stack.pop();
+                continue;
+            }
+
+            let value = domain[*next_value].clone();
+            *next_value += 1;
+
+            self.assignment.insert(variable.clone(), value);
+
+            if !csp.consistent(variable.clone(), &self.assignment) {
+                continue;
+            }
+
+            if self.stack.len() == csp.variables.len() {
+                return Some(self.assignment.clone());
+            }
+
+            self.stack.push(0);
+        }
+
+        None
+    }
+}
+
+/// SplitMix64, a small seedable generator that keeps the randomized solvers
+/// reproducible without an external dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed index below `bound`, which must be non-zero.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// A uniformly distributed float in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// Puts `items` in a uniformly random order (Fisher-Yates).
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Requires its variables, once assigned, to differ pairwise. This is the
+/// same relation as [`AllDifferentConstraint`]; the name reads better when
+/// stating that two variables differ, as in map coloring.
+pub type NotEqualConstraint<V> = AllDifferentConstraint<V>;
+
+/// Requires two variables to take the same value, once both are assigned.
+#[derive(Clone, Debug)]
+pub struct EqualConstraint<V: Eq + PartialEq + Hash + Clone> {
+    variables: Vec<V>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone> EqualConstraint<V> {
+    pub fn new(a: V, b: V) -> Self {
+        Self {
+            variables: vec![a, b],
+        }
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> Constraint<V, D> for EqualConstraint<V> {
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        match (
+            assignment.get(&self.variables[0]),
+            assignment.get(&self.variables[1]),
+        ) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+    }
+
+    fn supports(&self, a: &V, va: &D, b: &V, vb: &D) -> bool {
+        va == vb || a == b || !self.variables.contains(a) || !self.variables.contains(b)
+    }
+}
+
+/// Requires the value of the first variable to be smaller than the value of
+/// the second, once both are assigned.
+#[derive(Clone, Debug)]
+pub struct LessThanConstraint<V: Eq + PartialEq + Hash + Clone> {
+    variables: Vec<V>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone> LessThanConstraint<V> {
+    pub fn new(smaller: V, larger: V) -> Self {
+        Self {
+            variables: vec![smaller, larger],
+        }
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialOrd> Constraint<V, D>
+    for LessThanConstraint<V>
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        match (
+            assignment.get(&self.variables[0]),
+            assignment.get(&self.variables[1]),
+        ) {
+            (Some(smaller), Some(larger)) => smaller < larger,
+            _ => true,
+        }
+    }
+}
+
+/// Requires the values of two variables to be exactly `distance` apart, in
+/// either direction, once both are assigned.
+#[derive(Clone, Debug)]
+pub struct DistanceConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    distance: D,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> DistanceConstraint<V, D> {
+    pub fn new(a: V, b: V, distance: D) -> Self {
+        Self {
+            variables: vec![a, b],
+            distance,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for DistanceConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Copy + PartialOrd + Sub<Output = D>,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        match (
+            assignment.get(&self.variables[0]),
+            assignment.get(&self.variables[1]),
+        ) {
+            (Some(&a), Some(&b)) => {
+                let difference = if a < b { b - a } else { a - b };
+                difference == self.distance
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Requires two variables to take neighboring values, such as the positions
+/// of adjacent houses or seats: a `DistanceConstraint` of 1.
+#[derive(Clone, Debug)]
+pub struct NextToConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    distance: DistanceConstraint<V, D>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: From<u8>> NextToConstraint<V, D> {
+    pub fn new(a: V, b: V) -> Self {
+        Self {
+            distance: DistanceConstraint::new(a, b, D::from(1)),
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for NextToConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Copy + PartialOrd + Sub<Output = D>,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        self.distance.get_variables()
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.distance.satisfied(assignment)
+    }
+}
+
+/// Requires the values of its variables to increase strictly, in the order
+/// the variables are given. Only neighbors in that order that are both
+/// assigned are compared.
+///
+/// Besides sequencing, this breaks symmetries: ordering two variables that
+/// are interchangeable in every solution keeps one of each mirrored pair.
+#[derive(Clone, Debug)]
+pub struct OrderingConstraint<V: Eq + PartialEq + Hash + Clone> {
+    variables: Vec<V>,
+    strict: bool,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone> OrderingConstraint<V> {
+    pub fn new(variables: Vec<V>) -> Self {
+        Self {
+            variables,
+            strict: true,
+        }
+    }
+
+    /// Like `new`, but lets neighbors take equal values.
+    pub fn non_strict(variables: Vec<V>) -> Self {
+        Self {
+            variables,
+            strict: false,
+        }
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + Ord> Constraint<V, D> for OrderingConstraint<V> {
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.variables.windows(2).all(|pair| {
+            match (assignment.get(&pair[0]), assignment.get(&pair[1])) {
+                (Some(first), Some(second)) if self.strict => first < second,
+                (Some(first), Some(second)) => first <= second,
+                _ => true,
+            }
+        })
+    }
+}
+
+/// Requires each of `values` after the first to be used only once the value
+/// before it has been, scanning `variables` in order. When the values are
+/// interchangeable, this keeps one solution out of each set of solutions
+/// that only differ by how the values are permuted.
+///
+/// An unassigned variable could still take any value, so it excuses every
+/// later variable until it is assigned.
+#[derive(Clone, Debug)]
+pub struct ValuePrecedenceConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    values: Vec<D>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> ValuePrecedenceConstraint<V, D> {
+    pub fn new(variables: Vec<V>, values: Vec<D>) -> Self {
+        Self { variables, values }
+    }
+}
+
+impl<V, D> Constraint<V, D> for ValuePrecedenceConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        // How many of `values` have been used, in order, before an
+        // unassigned variable is met.
+        let mut used = 0;
+
+        for variable in &self.variables {
+            let Some(value) = assignment.get(variable) else {
+                return true;
+            };
+
+            match self.values.iter().position(|v| v == value) {
+                Some(index) if index > used => return false,
+                Some(index) if index == used => used += 1,
+                _ => {}
+            }
+        }
+
+        true
+    }
+}
+
+/// Requires the values of its variables to add up to `target`. Partial
+/// assignments are always accepted, as the missing values could be anything.
+#[derive(Clone, Debug)]
+pub struct SumConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    target: D,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> SumConstraint<V, D> {
+    pub fn new(variables: Vec<V>, target: D) -> Self {
+        Self { variables, target }
+    }
+}
+
+impl<V, D> Constraint<V, D> for SumConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq + Sum<D>,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        let values: Option<Vec<D>> = self
+            .variables
+            .iter()
+            .map(|variable| assignment.get(variable).cloned())
+            .collect();
+
+        values.is_none_or(|values| values.into_iter().sum::<D>() == self.target)
+    }
+}
+
+/// How the weighted sum of a `LinearConstraint` compares to its target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    LessOrEqual,
+    GreaterOrEqual,
+    Equal,
+}
+
+/// Requires `sum(coefficient * value)` over its `(coefficient, variable)`
+/// terms to relate to `target` by `relation`.
+///
+/// Every variable is taken to range over `values`, which bounds what the
+/// unassigned terms can still add: a partial assignment is only rejected
+/// when no completion within those bounds could satisfy the relation.
+#[derive(Clone, Debug)]
+pub struct LinearConstraint<V: Eq + PartialEq + Hash + Clone> {
+    variables: Vec<V>,
+    coefficients: Vec<i64>,
+    relation: Relation,
+    target: i64,
+    values: RangeInclusive<i64>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone> LinearConstraint<V> {
+    pub fn new(
+        terms: Vec<(i64, V)>,
+        relation: Relation,
+        target: i64,
+        values: RangeInclusive<i64>,
+    ) -> Self {
+        let (coefficients, variables) = terms.into_iter().unzip();
+
+        Self {
+            variables,
+            coefficients,
+            relation,
+            target,
+            values,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for LinearConstraint<V>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Copy + PartialEq + Into<i64>,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        let range = (*self.values.start(), *self.values.end());
+
+        self.feasible_within(|variable| match assignment.get(variable) {
+            Some(value) => ((*value).into(), (*value).into()),
+            None => range,
+        })
+    }
+}
+
+impl<V: Eq + PartialEq + Hash + Clone> LinearConstraint<V> {
+    /// Whether the relation could hold with each variable somewhere in the
+    /// inclusive `(min, max)` that `bounds` gives for it.
+    ///
+    /// The sums are taken in `i128`, so bounds anywhere in `i64` can't
+    /// overflow them.
+    pub(crate) fn feasible_within(&self, bounds: impl Fn(&V) -> (i64, i64)) -> bool {
+        let (mut min, mut max) = (0i128, 0i128);
+
+        for (variable, coefficient) in self.variables.iter().zip(&self.coefficients) {
+            let (lowest, highest) = bounds(variable);
+            let coefficient = i128::from(*coefficient);
+            let (low, high) = (
+                coefficient * i128::from(lowest),
+                coefficient * i128::from(highest),
+            );
+            min += low.min(high);
+            max += low.max(high);
+        }
+
+        let target = i128::from(self.target);
+        match self.relation {
+            Relation::LessOrEqual => min <= target,
+            Relation::GreaterOrEqual => max >= target,
+            Relation::Equal => min <= target && target <= max,
+        }
+    }
+}
+
+/// Restricts its variables to the combinations of values listed in
+/// `allowed`, each giving the values of the variables in order.
+///
+/// A partial assignment is accepted as long as some allowed tuple agrees
+/// with it on every assigned variable, so a full one has to match a tuple
+/// exactly.
+#[derive(Clone, Debug)]
+pub struct TableConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    allowed: Vec<Vec<D>>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> TableConstraint<V, D> {
+    /// # Panics
+    ///
+    /// Panics if a tuple of `allowed` doesn't have one value per variable.
+    pub fn new(variables: Vec<V>, allowed: Vec<Vec<D>>) -> Self {
+        assert!(
+            allowed.iter().all(|tuple| tuple.len() == variables.len()),
+            "every allowed tuple needs one value per variable"
+        );
+
+        Self { variables, allowed }
+    }
+}
+
+impl<V, D> Constraint<V, D> for TableConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.allowed.iter().any(|tuple| {
+            self.variables
+                .iter()
+                .zip(tuple)
+                .all(|(variable, value)| assignment.get(variable).is_none_or(|v| v == value))
+        })
+    }
+}
+
+/// Requires `value_var` to equal the entry of `array` at the position given
+/// by `index_var`, once both are assigned. An index that is negative or past
+/// the end of `array` satisfies nothing.
+#[derive(Clone, Debug)]
+pub struct ElementConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    array: Vec<D>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> ElementConstraint<V, D> {
+    pub fn new(index_var: V, array: Vec<D>, value_var: V) -> Self {
+        Self {
+            variables: vec![index_var, value_var],
+            array,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for ElementConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq + TryInto<usize>,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        match (
+            assignment.get(&self.variables[0]),
+            assignment.get(&self.variables[1]),
+        ) {
+            (Some(index), Some(value)) => index
+                .clone()
+                .try_into()
+                .ok()
+                .and_then(|index| self.array.get(index))
+                .is_some_and(|entry| entry == value),
+            _ => true,
+        }
+    }
+}
+
+/// Counts, among `variables`, how many are assigned `value` and how many are
+/// still unassigned.
+fn count_value<V: Eq + Hash, D: PartialEq>(
+    variables: &[V],
+    value: &D,
+    assignment: &HashMap<V, D>,
+) -> (usize, usize) {
+    variables.iter().fold(
+        (0, 0),
+        |(matching, unassigned), variable| match assignment.get(variable) {
+            Some(assigned) if assigned == value => (matching + 1, unassigned),
+            Some(_) => (matching, unassigned),
+            None => (matching, unassigned + 1),
+        },
+    )
+}
+
+/// At most `k` of its variables may take the value `target`.
+///
+/// Assigning more variables can only raise the count, so a partial
+/// assignment is rejected as soon as more than `k` already have `target`.
+#[derive(Clone, Debug)]
+pub struct AtMostKConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    target: D,
+    k: usize,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> AtMostKConstraint<V, D> {
+    pub fn new(variables: Vec<V>, target: D, k: usize) -> Self {
+        Self {
+            variables,
+            target,
+            k,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for AtMostKConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        let (matching, _) = count_value(&self.variables, &self.target, assignment);
+
+        matching <= self.k
+    }
+}
+
+/// At least `k` of its variables must take the value `target`.
+///
+/// Partial assignments are judged optimistically, as if every unassigned
+/// variable could still take `target`: they are only rejected once too few
+/// variables are left to reach `k`.
+#[derive(Clone, Debug)]
+pub struct AtLeastKConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    target: D,
+    k: usize,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> AtLeastKConstraint<V, D> {
+    pub fn new(variables: Vec<V>, target: D, k: usize) -> Self {
+        Self {
+            variables,
+            target,
+            k,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for AtLeastKConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        let (matching, unassigned) = count_value(&self.variables, &self.target, assignment);
+
+        matching + unassigned >= self.k
+    }
+}
+
+/// Exactly `k` of its variables must take the value `target`.
+///
+/// Partial assignments are rejected once more than `k` variables have
+/// `target`, or once too few are left unassigned to still reach `k`; a full
+/// assignment therefore has to hit `k` exactly.
+#[derive(Clone, Debug)]
+pub struct ExactlyKConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    target: D,
+    k: usize,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> ExactlyKConstraint<V, D> {
+    pub fn new(variables: Vec<V>, target: D, k: usize) -> Self {
+        Self {
+            variables,
+            target,
+            k,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for ExactlyKConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        let (matching, unassigned) = count_value(&self.variables, &self.target, assignment);
+
+        matching <= self.k && matching + unassigned >= self.k
+    }
+}
+
+/// Bounds how often each value may be used: for each `(value, min, max)` of
+/// `bounds`, between `min` and `max` of its variables must take `value`.
+/// Values without bounds can be used any number of times.
+///
+/// Like `AtLeastKConstraint`, partial assignments count every unassigned
+/// variable towards each lower bound, so they are only rejected once an
+/// upper bound is exceeded or a lower bound is out of reach.
+#[derive(Clone, Debug)]
+pub struct GlobalCardinalityConstraint<V: Eq + PartialEq + Hash + Clone, D> {
+    variables: Vec<V>,
+    bounds: Vec<(D, usize, usize)>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D> GlobalCardinalityConstraint<V, D> {
+    pub fn new(variables: Vec<V>, bounds: Vec<(D, usize, usize)>) -> Self {
+        Self { variables, bounds }
+    }
+}
+
+impl<V, D> Constraint<V, D> for GlobalCardinalityConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.bounds.iter().all(|(value, min, max)| {
+            let (matching, unassigned) = count_value(&self.variables, value, assignment);
+
+            matching <= *max && matching + unassigned >= *min
+        })
+    }
+}
+
+/// A soft constraint for [`CSP::branch_and_bound`]: assignments violating
+/// `constraint` are allowed but cost `weight`.
+#[derive(Clone)]
+pub struct WeightedConstraint<V: Eq + PartialEq + Hash + Clone, D: PartialEq> {
+    pub constraint: SharedConstraint<V, D>,
+    pub weight: f64,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> WeightedConstraint<V, D> {
+    pub fn new(constraint: SharedConstraint<V, D>, weight: f64) -> Self {
+        Self { constraint, weight }
+    }
+}
+
+/// A constraint whose `satisfied` is the closure `predicate`, for one-off
+/// rules that don't deserve a type of their own.
+///
+/// `add_constraint` takes a `SharedConstraint<V, D>`, which is implicitly
+/// `'static`, so the closure must own whatever it captures (use `move`).
+/// Like any constraint, it is also evaluated on partial assignments and
+/// should treat missing variables as satisfied.
+///
+/// ```
+/// # use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::csp::{
+/// #     FunctionConstraint, CSP,
+/// # };
+/// # use std::{collections::HashMap, sync::Arc};
+/// let domains = [("A", vec![1, 2]), ("B", vec![1, 2])].into_iter().collect();
+/// let mut csp = CSP::new(vec!["A", "B"], domains);
+///
+/// csp.add_constraint(Arc::new(FunctionConstraint::new(
+///     vec!["A", "B"],
+///     |assignment: &HashMap<&str, i32>| match (assignment.get("A"), assignment.get("B")) {
+///         (Some(a), Some(b)) => a != b,
+///         _ => true,
+///     },
+/// )));
+///
+/// assert_eq!(csp.count_solutions(), 2);
+/// ```
+pub struct FunctionConstraint<V, D, F>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+    F: Fn(&HashMap<V, D>) -> bool,
+{
+    variables: Vec<V>,
+    predicate: F,
+    domain: PhantomData<fn() -> D>,
+}
+
+impl<V, D, F> FunctionConstraint<V, D, F>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+    F: Fn(&HashMap<V, D>) -> bool,
+{
+    pub fn new(variables: Vec<V>, predicate: F) -> Self {
+        Self {
+            variables,
+            predicate,
+            domain: PhantomData,
+        }
+    }
+}
+
+impl<V, D, F> Constraint<V, D> for FunctionConstraint<V, D, F>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+    F: Fn(&HashMap<V, D>) -> bool,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        (self.predicate)(assignment)
+    }
+}
+
+/// Requires `inner` not to hold.
+///
+/// Constraints accept partial assignments they can't judge yet, so the
+/// negation of one would wrongly reject them. `NotConstraint` therefore
+/// only judges, and flips `inner`'s verdict, once all of its variables are
+/// assigned; until then it is satisfied.
+#[derive(Clone, Debug)]
+pub struct NotConstraint<C> {
+    inner: C,
+}
+
+impl<C> NotConstraint<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<V, D, C> Constraint<V, D> for NotConstraint<C>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: PartialEq,
+    C: Constraint<V, D>,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        self.inner.get_variables()
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        !all_assigned(self.get_variables(), assignment) || !self.inner.satisfied(assignment)
+    }
+}
+
+fn all_assigned<V: Eq + Hash, D>(variables: &[V], assignment: &HashMap<V, D>) -> bool {
+    variables
+        .iter()
+        .all(|variable| assignment.contains_key(variable))
+}
+
+/// The variables of all of `constraints`, each once, in order of appearance.
+fn union_of_variables<V: Eq + PartialEq + Hash + Clone, D: PartialEq>(
+    constraints: &[SharedConstraint<V, D>],
+) -> Vec<V> {
+    let mut variables: Vec<V> = vec![];
+
+    for constraint in constraints {
+        for variable in constraint.get_variables() {
+            if !variables.contains(variable) {
+                variables.push(variable.clone());
+            }
+        }
+    }
+
+    variables
+}
+
+/// Requires every one of `constraints` to hold. Its variables are those of
+/// all of them.
+#[derive(Clone)]
+pub struct AndConstraint<V: Eq + PartialEq + Hash + Clone, D: PartialEq> {
+    variables: Vec<V>,
+    constraints: ConstraintList<V, D>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> AndConstraint<V, D> {
+    pub fn new(constraints: Vec<SharedConstraint<V, D>>) -> Self {
+        Self {
+            variables: union_of_variables(&constraints),
+            constraints,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for AndConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.constraints
+            .iter()
+            .all(|constraint| constraint.satisfied(assignment))
+    }
+}
+
+/// Requires at least one of `constraints` to hold. Its variables are those
+/// of all of them.
+///
+/// A disjunct that is violated while some of its variables are unassigned
+/// may still come to hold, as in a `FunctionConstraint` that isn't written
+/// to accept partial assignments. The disjunction is thus only violated
+/// once every disjunct is fully assigned and fails.
+#[derive(Clone)]
+pub struct OrConstraint<V: Eq + PartialEq + Hash + Clone, D: PartialEq> {
+    variables: Vec<V>,
+    constraints: ConstraintList<V, D>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> OrConstraint<V, D> {
+    pub fn new(constraints: Vec<SharedConstraint<V, D>>) -> Self {
+        Self {
+            variables: union_of_variables(&constraints),
+            constraints,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for OrConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        self.constraints.iter().any(|constraint| {
+            !all_assigned(constraint.get_variables(), assignment)
+                || constraint.satisfied(assignment)
+        })
+    }
+}
+
+/// Requires `consequence` to hold whenever `condition` does, as in "if
+/// option A is chosen then B must be X". Its variables are those of both.
+///
+/// `condition` only counts as holding once all of its variables are
+/// assigned: until then the implication is satisfied, whatever the
+/// consequence. Once it holds, `consequence` is judged as it judges
+/// itself, partial assignments included.
+#[derive(Clone)]
+pub struct ImplicationConstraint<V: Eq + PartialEq + Hash + Clone, D: PartialEq> {
+    variables: Vec<V>,
+    condition: SharedConstraint<V, D>,
+    consequence: SharedConstraint<V, D>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: PartialEq> ImplicationConstraint<V, D> {
+    pub fn new(condition: SharedConstraint<V, D>, consequence: SharedConstraint<V, D>) -> Self {
+        Self {
+            variables: union_of_variables(&[condition.clone(), consequence.clone()]),
+            condition,
+            consequence,
+        }
+    }
+}
+
+impl<V, D> Constraint<V, D> for ImplicationConstraint<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: PartialEq,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<V, D>) -> bool {
+        let condition_holds = all_assigned(self.condition.get_variables(), assignment)
+            && self.condition.satisfied(assignment);
+
+        !condition_holds || self.consequence.satisfied(assignment)
+    }
+}
+
+/// Adds constraints to a CSP from a short list of rules, sparing an
+/// `add_constraint(Arc::new(...))` per constraint:
+///
+/// - `a != b` adds a [`NotEqualConstraint`],
+/// - `a == b` adds an [`EqualConstraint`],
+/// - `all_diff(a, b, c)` adds an [`AllDifferentConstraint`].
+///
+/// Each side of `!=` and `==` has to be a single token, such as a name or
+/// literal, or be put in parentheses. Like `add_constraint`, it panics on a
+/// variable that isn't part of the CSP.
+///
+/// ```
+/// # use classic_computer_science_propblems_in_rust::{
+/// #     ch3_constraint_satisfaction_problems::csp::CSP, constraints,
+/// # };
+/// let domains = ["A", "B", "C"]
+///     .into_iter()
+///     .map(|variable| (variable, vec![1, 2, 3]))
+///     .collect();
+/// let mut csp = CSP::new(vec!["A", "B", "C"], domains);
+///
+/// constraints!(csp; "A" == "B", all_diff("B", "C"));
+///
+/// assert_eq!(csp.count_solutions(), 6);
+/// ```
+#[macro_export]
+macro_rules! constraints {
+    ($csp:expr; $($rules:tt)*) => {{
+        let csp = &mut $csp;
+        $crate::constraints!(@rules csp; $($rules)*);
+    }};
+    (@rules $csp:ident;) => {};
+    (@rules $csp:ident; all_diff($($variable:expr),+ $(,)?) $(, $($rest:tt)*)?) => {
+        $csp.add_constraint(::std::sync::Arc::new(
+            $crate::ch3_constraint_satisfaction_problems::csp::AllDifferentConstraint::new(
+                vec![$($variable),+],
+            ),
+        ));
+        $crate::constraints!(@rules $csp; $($($rest)*)?);
+    };
+    (@rules $csp:ident; $a:tt != $b:tt $(, $($rest:tt)*)?) => {
+        $csp.add_constraint(::std::sync::Arc::new(
+            $crate::ch3_constraint_satisfaction_problems::csp::NotEqualConstraint::new(
+                vec![$a, $b],
+            ),
+        ));
+        $crate::constraints!(@rules $csp; $($($rest)*)?);
+    };
+    (@rules $csp:ident; $a:tt == $b:tt $(, $($rest:tt)*)?) => {
+        $csp.add_constraint(::std::sync::Arc::new(
+            $crate::ch3_constraint_satisfaction_problems::csp::EqualConstraint::new($a, $b),
+        ));
+        $crate::constraints!(@rules $csp; $($($rest)*)?);
+    };
+}
+
+/// A relabelling that turns every solution into another one, for
+/// [`CSP::all_solutions_canonical`]: each `(from, to)` pair of `variables`
+/// moves the value of `from` to `to`, and each of `values` replaces `from`
+/// with `to`. Both have to be permutations, each variable or value showing
+/// up at most once on either side; the ones left out stay as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symmetry<V, D> {
+    pub variables: Vec<(V, V)>,
+    pub values: Vec<(D, D)>,
+}
+
+impl<V: Eq + Hash + Clone, D: Clone + PartialEq> Symmetry<V, D> {
+    /// The symmetry exchanging the values of `a` and `b`.
+    pub fn swap_variables(a: V, b: V) -> Self {
+        Self {
+            variables: vec![(a.clone(), b.clone()), (b, a)],
+            values: vec![],
+        }
+    }
+
+    /// The symmetry exchanging `a` and `b` wherever they are assigned.
+    pub fn swap_values(a: D, b: D) -> Self {
+        Self {
+            variables: vec![],
+            values: vec![(a.clone(), b.clone()), (b, a)],
+        }
+    }
+
+    pub fn apply(&self, solution: &HashMap<V, D>) -> HashMap<V, D> {
+        solution
+            .iter()
+            .map(|(variable, value)| {
+                let variable = self
+                    .variables
+                    .iter()
+                    .find(|(from, _)| from == variable)
+                    .map_or(variable, |(_, to)| to);
+                let value = self
+                    .values
+                    .iter()
+                    .find(|(from, _)| from == value)
+                    .map_or(value, |(_, to)| to);
+
+                (variable.clone(), value.clone())
+            })
+            .collect()
+    }
+}
+
+/// Whether `CSP::optimize` looks for the largest or the smallest objective.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Maximize,
+    Minimize,
+}
+
+impl Goal {
+    fn improves(self, candidate: i64, incumbent: i64) -> bool {
+        match self {
+            Goal::Maximize => candidate > incumbent,
+            Goal::Minimize => candidate < incumbent,
+        }
+    }
+}
+
+/// How `backtracking_search_with_options` picks the next variable to assign.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VariableOrdering {
+    /// The first unassigned variable, in the order the variables were given.
+    #[default]
+    FirstUnassigned,
+    /// Minimum Remaining Values: the unassigned variable with the fewest
+    /// values still consistent with the current assignment.
+    MRV,
+    /// MRV, breaking ties with the degree heuristic: among the variables
+    /// with the fewest remaining values, the one sharing the most
+    /// constraints with other unassigned variables.
+    MrvThenDegree,
+    /// The unassigned variable referenced by the most constraints, ties
+    /// going to the first declared. The order is worked out once per search,
+    /// which makes it much cheaper than MRV.
+    StaticDegree,
+    /// dom/wdeg: the unassigned variable with the fewest remaining values
+    /// per unit of weighted degree. Every constraint starts with weight 1,
+    /// plus 1 each time it wipes out a domain or rejects a value during the
+    /// search, and a variable's weighted degree sums the weights of its
+    /// constraints on other unassigned variables. The search thus learns
+    /// where the problem is hard and turns to those variables first.
+    DomWdeg,
+}
+
+/// The order in which `backtracking_search_with_options` tries the values of
+/// the chosen variable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValueOrdering {
+    /// The order the values were given in the variable's domain, or the one
+    /// set with `CSP::set_value_order`.
+    #[default]
+    Declaration,
+    /// Least Constraining Value: values that rule out the fewest values of
+    /// unassigned neighbors are tried first.
+    LeastConstraining,
+    /// A random order, drawn afresh for every variable the search assigns.
+    /// The given seed fixes the whole sequence, so a search with the same
+    /// seed always explores the same nodes.
+    Random(u64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub variable_ordering: VariableOrdering,
+    pub value_ordering: ValueOrdering,
+    pub propagation: Propagation,
+    /// On a dead end, jump straight back to the most recently assigned
+    /// variable implicated in the conflict instead of the previous one
+    /// (conflict-directed backjumping). Combined with `Propagation::Mac`
+    /// every assigned variable counts as implicated in a wipeout, so the
+    /// search only jumps over values ruled out by a violated constraint.
+    pub backjumping: bool,
+    /// Check every constraint of the CSP after each assignment, instead of
+    /// only those on the variable just assigned.
+    ///
+    /// Every constraint is registered under each of its variables, so the
+    /// incremental check already sees any constraint a new value can break;
+    /// the full check costs time proportional to the whole constraint set at
+    /// every node for no pruning in return. It only matters when the initial
+    /// assignment handed to the search may already violate a constraint.
+    pub check_all_constraints: bool,
+}
+
+/// How a backtracking search prunes the domains of the unassigned variables
+/// after each assignment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Propagation {
+    /// Values are only checked when they are tried.
+    #[default]
+    None,
+    /// Prune the values of unassigned neighbors that became inconsistent,
+    /// and backtrack as soon as a domain is emptied.
+    ForwardChecking,
+    /// Maintaining Arc Consistency: forward checking, then AC-3 over the
+    /// arcs between unassigned variables until no more values can be pruned.
+    Mac,
+}
+
+/// Counters describing the work done by a backtracking search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Recursive calls of the search, the root included.
+    pub nodes_visited: usize,
+    /// Nodes whose every branch failed.
+    pub backtracks: usize,
+    /// Times a variable's constraints were checked against an assignment.
+    pub consistency_checks: usize,
+    pub elapsed: Duration,
+}
+
+/// How [`CSP::simulated_annealing`] cools: the temperature starts at
+/// `initial_temperature`, is multiplied by `cooling_rate` after every step
+/// and the search ends once it drops below `min_temperature`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoolingSchedule {
+    pub initial_temperature: f64,
+    pub cooling_rate: f64,
+    pub min_temperature: f64,
+}
+
+/// Counters describing the work done by [`CSP::solve_with_restarts_and_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RestartStats {
+    /// Fresh random assignments tried after the first one.
+    pub restarts: usize,
+}
+
+/// The result of a search that may give up before it is finished, telling
+/// a proof that there is no solution apart from a search cut short, which
+/// a plain `Option` would both report as `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Completeness<V: Eq + Hash, D> {
+    Solved(HashMap<V, D>),
+    /// The whole search space was explored: there is no solution.
+    Unsatisfiable,
+    /// The search ran out of budget before finding a solution or proving
+    /// there is none.
+    Unknown,
+}
+
+/// How many nodes a timed search visits between two looks at the clock.
+const DEADLINE_CHECK_INTERVAL: usize = 256;
+
+struct Search<'a, V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq, S> {
+    csp: &'a CSP<V, D, S>,
+    options: SearchOptions,
+    stats: SearchStats,
+    max_nodes: Option<usize>,
+    deadline: Option<Instant>,
+    cancelled: Option<&'a AtomicBool>,
+    limit_reached: bool,
+    rng: Rng,
+    /// For each variable, the assigned variables to blame for the values
+    /// pruned from its domain, each paired with the variable whose
+    /// assignment did the pruning. Only kept when backjumping.
+    eliminated_by: HashMap<V, Vec<(V, V)>>,
+    /// The variables by decreasing number of constraints, for
+    /// `VariableOrdering::StaticDegree`; empty with the other orderings.
+    static_order: Vec<V>,
+    /// How many failures each constraint caused, keyed by the address of
+    /// the constraint, for `VariableOrdering::DomWdeg`.
+    failures: HashMap<usize, usize>,
+}
+
+impl<
+        'a,
+        V: Eq + PartialEq + Hash + Clone,
+        D: Clone + PartialEq,
+        S: BuildHasher + Default + Clone,
+    > Search<'a, V, D, S>
+{
+    fn new(csp: &'a CSP<V, D, S>, options: SearchOptions) -> Self {
+        Search {
+            csp,
+            options,
+            stats: SearchStats::default(),
+            max_nodes: None,
+            deadline: None,
+            cancelled: None,
+            limit_reached: false,
+            rng: Rng::new(match options.value_ordering {
+                ValueOrdering::Random(seed) => seed,
+                _ => 0,
+            }),
+            eliminated_by: HashMap::new(),
+            static_order: match options.variable_ordering {
+                VariableOrdering::StaticDegree => {
+                    let mut variables = csp.variables.clone();
+                    variables.sort_by_key(|variable| Reverse(csp.constraints_for(variable).len()));
+                    variables
+                }
+                _ => vec![],
+            },
+            failures: HashMap::new(),
+        }
+    }
+
+    fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// Makes the search give up as soon as `cancelled` is set, e.g. by
+    /// another thread that already found a solution.
+    #[cfg(feature = "parallel")]
+    fn with_cancellation(mut self, cancelled: &'a AtomicBool) -> Self {
+        self.cancelled = Some(cancelled);
+        self
+    }
+
+    fn outcome(&self, result: Option<HashMap<V, D>>) -> Completeness<V, D> {
+        match result {
+            Some(solution) => Completeness::Solved(solution),
+            None if self.limit_reached => Completeness::Unknown,
+            None => Completeness::Unsatisfiable,
+        }
+    }
+
+    fn over_budget(&mut self) -> bool {
+        if self
+            .max_nodes
+            .is_some_and(|max_nodes| self.stats.nodes_visited > max_nodes)
+        {
+            self.limit_reached = true;
+        }
+
+        if self
+            .stats
+            .nodes_visited
+            .is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.limit_reached = true;
+        }
+
+        if self
+            .cancelled
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+        {
+            self.limit_reached = true;
+        }
+
+        self.limit_reached
+    }
+
+    fn solve(&mut self, assignment: HashMap<V, D>) -> Option<HashMap<V, D>> {
+        if self.csp.dead_end(&assignment) {
+            return None;
+        }
+
+        let start = Instant::now();
+        let domains = self.csp.full_domains();
+        let mut assignment = assignment;
+
+        let found = if self.options.backjumping {
+            self.backjump(&mut assignment, &domains).is_ok()
+        } else {
+            self.backtrack(&mut assignment, &domains)
+        };
+        self.stats.elapsed = start.elapsed();
+
+        found.then_some(assignment)
+    }
+
+    /// Explores the same nodes in the same order as `solve`, with an explicit
+    /// `Frame` per assigned variable in place of the recursion. Backjumping
+    /// is not supported: the search always backtracks chronologically.
+    fn solve_iter(&mut self, assignment: HashMap<V, D>) -> Option<HashMap<V, D>> {
+        if self.csp.dead_end(&assignment) {
+            return None;
+        }
+
+        let start = Instant::now();
+        let csp = self.csp;
+        let mut assignment = assignment;
+        let mut frames: Vec<Frame<V>> = Vec::new();
+        // The domains each frame chooses from. Propagation pushes the pruned
+        // domains of every child; otherwise they never change.
+        let mut domains = vec![csp.full_domains()];
+
+        let found = 'search: loop {
+            // Entering a node, as a call to `backtrack` would.
+            self.stats.nodes_visited += 1;
+
+            if self.over_budget() {
+                break false;
+            }
+
+            if assignment.len() == csp.variables.len() {
+                break true;
+            }
+
+            let current = domains.last().unwrap();
+            match self.select_unassigned_variable(&mut assignment, current) {
+                Some(variable) => {
+                    let values = self
+                        .order_domain_values(&variable, &mut assignment, current)
+                        .collect::<Vec<usize>>()
+                        .into_iter();
+
+                    frames.push(Frame { variable, values });
+                }
+                None => {
+                    self.stats.backtracks += 1;
+
+                    if self.options.propagation != Propagation::None && !frames.is_empty() {
+                        domains.pop();
+                    }
+                }
+            }
+
+            // Tries the next value of the innermost frame until one leads to
+            // a child node, unwinding the frames that run out of values.
+            loop {
+                let Some(frame) = frames.last_mut() else {
+                    break 'search false;
+                };
+
+                let Some(index) = frame.values.next() else {
+                    assignment.remove(&frame.variable);
+                    frames.pop();
+                    self.stats.backtracks += 1;
+
+                    if self.options.propagation != Propagation::None && !frames.is_empty() {
+                        domains.pop();
+                    }
+
+                    continue;
+                };
+
+                let variable = frame.variable.clone();
+                assignment.insert(variable.clone(), csp.values(&variable)[index].clone());
+
+                if !self.consistent(&variable, &assignment) {
+                    self.record_conflict(&variable, &assignment);
+                    continue;
+                }
+
+                if self.options.propagation == Propagation::None {
+                    continue 'search;
+                }
+
+                let current = domains.last().unwrap();
+                if let Ok(pruned) = self.propagate(&variable, &mut assignment, current) {
+                    domains.push(pruned);
+                    continue 'search;
+                }
+            }
+        };
+        self.stats.elapsed = start.elapsed();
+
+        found.then_some(assignment)
+    }
+
+    /// Extends `assignment` in place, returning whether it could be completed;
+    /// on failure it is handed back as it came in.
+    ///
+    /// `domains` holds, as indices into `self.csp.domains`, what is left of
+    /// every variable's domain; it only shrinks when propagation is enabled.
+    fn backtrack(&mut self, assignment: &mut HashMap<V, D>, domains: &Domains<V, S>) -> bool {
+        self.stats.nodes_visited += 1;
+
+        if self.over_budget() {
+            return false;
+        }
+
+        if assignment.len() == self.csp.variables.len() {
+            return true;
+        }
+
+        if let Some(first) = self.select_unassigned_variable(assignment, domains) {
+            let csp = self.csp;
+
+            for index in self.order_domain_values(&first, assignment, domains) {
+                assignment.insert(first.clone(), csp.values(&first)[index].clone());
+
+                if self.consistent(&first, assignment) {
+                    let found = if self.options.propagation != Propagation::None {
+                        match self.propagate(&first, assignment, domains) {
+                            Ok(pruned) => self.backtrack(assignment, &pruned),
+                            Err(_) => false,
+                        }
+                    } else {
+                        self.backtrack(assignment, domains)
+                    };
+
+                    if found {
+                        return true;
+                    }
+
+                    if self.limit_reached {
+                        assignment.remove(&first);
+                        return false;
+                    }
+                } else {
+                    self.record_conflict(&first, assignment);
+                }
+            }
+
+            assignment.remove(&first);
+        }
+
+        self.stats.backtracks += 1;
+
+        false
+    }
+
+    /// Like `backtrack`, but on failure returns the conflict set: the assigned
+    /// variables that together leave no value for some variable below. A
+    /// node whose own variable isn't in the conflict set of its child can't
+    /// fix the conflict with another value, so it passes the set straight up.
+    fn backjump(
+        &mut self,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+    ) -> Result<(), HashSet<V>> {
+        self.stats.nodes_visited += 1;
+
+        if self.over_budget() {
+            return Err(HashSet::new());
+        }
+
+        if assignment.len() == self.csp.variables.len() {
+            return Ok(());
+        }
+
+        let Some(variable) = self.select_unassigned_variable(assignment, domains) else {
+            return Err(HashSet::new());
+        };
+        let csp = self.csp;
+
+        // The values pruned before this node are blamed on whoever pruned them.
+        let mut conflicts: HashSet<V> = self
+            .eliminated_by
+            .get(&variable)
+            .into_iter()
+            .flatten()
+            .map(|(_, culprit)| culprit.clone())
+            .collect();
+
+        for index in self.order_domain_values(&variable, assignment, domains) {
+            assignment.insert(variable.clone(), csp.values(&variable)[index].clone());
+
+            if !self.consistent(&variable, assignment) {
+                self.record_conflict(&variable, assignment);
+                conflicts.extend(self.violated_by(&variable, assignment));
+                continue;
+            }
+
+            let pruned = if self.options.propagation == Propagation::None {
+                domains.clone()
+            } else {
+                match self.propagate(&variable, assignment, domains) {
+                    Ok(pruned) => pruned,
+                    Err(wiped_out) => {
+                        conflicts.extend(
+                            self.wipeout_culprits(&variable, &wiped_out, assignment, domains),
+                        );
+                        continue;
+                    }
+                }
+            };
+
+            self.record_eliminations(&variable, assignment, domains, &pruned);
+            let result = self.backjump(assignment, &pruned);
+            self.forget_eliminations(&variable);
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(child_conflicts) if !child_conflicts.contains(&variable) => {
+                    assignment.remove(&variable);
+                    return Err(child_conflicts);
+                }
+                Err(child_conflicts) => conflicts.extend(child_conflicts),
+            }
+        }
+
+        assignment.remove(&variable);
+        conflicts.remove(&variable);
+        self.stats.backtracks += 1;
+
+        Err(conflicts)
+    }
+
+    /// The other assigned variables of the constraints on `variable`, and of
+    /// the global constraints, that `assignment` violates.
+    fn violated_by(&self, variable: &V, assignment: &HashMap<V, D>) -> Vec<V> {
+        self.csp
+            .constraints_for(variable)
+            .iter()
+            .chain(&self.csp.global_constraints)
+            .filter(|constraint| !constraint.satisfied(assignment))
+            .flat_map(|constraint| constraint.get_variables())
+            .filter(|other| *other != variable && assignment.contains_key(*other))
+            .cloned()
+            .collect()
+    }
+
+    /// The assigned variables of the constraints that reject each of the
+    /// values at `indices` for the unassigned `variable`.
+    fn rejecting(
+        &self,
+        variable: &V,
+        indices: impl Iterator<Item = usize>,
+        assignment: &mut HashMap<V, D>,
+    ) -> HashSet<V> {
+        let mut culprits = HashSet::new();
+
+        for index in indices {
+            assignment.insert(variable.clone(), self.csp.values(variable)[index].clone());
+            culprits.extend(self.violated_by(variable, assignment));
+            assignment.remove(variable);
+        }
+
+        culprits
+    }
+
+    /// The assigned variables to blame for propagating `variable`'s value
+    /// wiping out the domain of `wiped_out`, which held `domains` before.
+    fn wipeout_culprits(
+        &self,
+        variable: &V,
+        wiped_out: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+    ) -> Vec<V> {
+        // Arc consistency removes values for lack of support in other
+        // unassigned variables, which doesn't tie them to the assignments
+        // that started it, so everything assigned is blamed.
+        if self.options.propagation == Propagation::Mac {
+            return assignment
+                .keys()
+                .filter(|other| *other != variable)
+                .cloned()
+                .collect();
+        }
+
+        let mut culprits = self.rejecting(wiped_out, domains[wiped_out].iter(), assignment);
+        culprits.extend(
+            self.eliminated_by
+                .get(wiped_out)
+                .into_iter()
+                .flatten()
+                .map(|(_, culprit)| culprit.clone()),
+        );
+
+        culprits.into_iter().collect()
+    }
+
+    /// Notes who to blame for every value that propagating `variable`'s
+    /// assignment removed, i.e. that is missing from `pruned`.
+    fn record_eliminations(
+        &mut self,
+        variable: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+        pruned: &Domains<V, S>,
+    ) {
+        let mut eliminations: Vec<(V, Vec<V>)> = vec![];
+
+        match self.options.propagation {
+            Propagation::None => return,
+            Propagation::ForwardChecking => {
+                for neighbor in self.csp.neighbors(variable) {
+                    if pruned[neighbor].len() < domains[neighbor].len() {
+                        let removed = domains[neighbor]
+                            .iter()
+                            .filter(|index| !pruned[neighbor].contains(*index));
+                        let culprits = self.rejecting(neighbor, removed, assignment);
+
+                        eliminations.push((neighbor.clone(), culprits.into_iter().collect()));
+                    }
+                }
+            }
+            // As with a wipeout, arc consistency can't say which assignments
+            // led to a removal.
+            Propagation::Mac => {
+                for (other, domain) in pruned {
+                    if domain.len() < domains[other].len() {
+                        eliminations.push((other.clone(), assignment.keys().cloned().collect()));
+                    }
+                }
+            }
+        }
+
+        for (pruned_variable, culprits) in eliminations {
+            let entry = self.eliminated_by.entry(pruned_variable).or_default();
+            entry.extend(
+                culprits
+                    .into_iter()
+                    .map(|culprit| (variable.clone(), culprit)),
+            );
+        }
+    }
+
+    fn forget_eliminations(&mut self, variable: &V) {
+        for culprits in self.eliminated_by.values_mut() {
+            culprits.retain(|(pruner, _)| pruner != variable);
+        }
+    }
+
+    fn consistent(&mut self, variable: &V, assignment: &HashMap<V, D>) -> bool {
+        self.stats.consistency_checks += 1;
+
+        if self.options.check_all_constraints {
+            self.csp.is_satisfied(assignment)
+        } else {
+            self.csp.consistent(variable.clone(), assignment)
+        }
+    }
+
+    /// Checks whether the unassigned `variable` could take the value at
+    /// `index` in its domain, leaving `assignment` as it was.
+    fn consistent_with(
+        &mut self,
+        variable: &V,
+        index: usize,
+        assignment: &mut HashMap<V, D>,
+    ) -> bool {
+        assignment.insert(variable.clone(), self.csp.values(variable)[index].clone());
+        let consistent = self.consistent(variable, assignment);
+        assignment.remove(variable);
+
+        consistent
+    }
+
+    fn select_unassigned_variable(
+        &mut self,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+    ) -> Option<V> {
+        let csp = self.csp;
+        let mut unassigned = csp
+            .variables
+            .iter()
+            .filter(|variable| !assignment.contains_key(variable));
+
+        match self.options.variable_ordering {
+            VariableOrdering::FirstUnassigned => unassigned.next().cloned(),
+            VariableOrdering::MRV => {
+                let unassigned: Vec<&V> = unassigned.collect();
+
+                unassigned
+                    .into_iter()
+                    .min_by_key(|variable| self.remaining_values(variable, assignment, domains))
+                    .cloned()
+            }
+            VariableOrdering::MrvThenDegree => {
+                let unassigned: Vec<&V> = unassigned.collect();
+
+                unassigned
+                    .into_iter()
+                    .min_by_key(|variable| {
+                        (
+                            self.remaining_values(variable, assignment, domains),
+                            Reverse(csp.degree(variable, assignment)),
+                        )
+                    })
+                    .cloned()
+            }
+            VariableOrdering::StaticDegree => self
+                .static_order
+                .iter()
+                .find(|variable| !assignment.contains_key(variable))
+                .cloned(),
+            VariableOrdering::DomWdeg => {
+                let unassigned: Vec<&V> = unassigned.collect();
+                let scores: Vec<f64> = unassigned
+                    .iter()
+                    .map(|variable| {
+                        let remaining = self.remaining_values(variable, assignment, domains);
+                        let weight = self.weighted_degree(variable, assignment).max(1);
+                        remaining as f64 / weight as f64
+                    })
+                    .collect();
+
+                unassigned
+                    .into_iter()
+                    .zip(scores)
+                    .min_by(|(_, score1), (_, score2)| score1.total_cmp(score2))
+                    .map(|(variable, _)| variable.clone())
+            }
+        }
+    }
+
+    fn order_domain_values<'d>(
+        &mut self,
+        variable: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &'d Domains<V, S>,
+    ) -> DomainOrder<'d> {
+        let domain = &domains[variable];
+
+        match self.options.value_ordering {
+            ValueOrdering::Declaration => return DomainOrder::Declaration(domain.iter()),
+            ValueOrdering::Random(_) => {
+                let mut indices: Vec<usize> = domain.iter().collect();
+                self.rng.shuffle(&mut indices);
+
+                return DomainOrder::Reordered(indices.into_iter());
+            }
+            ValueOrdering::LeastConstraining => {}
+        }
+
+        // Computed afresh at every level, as the neighbors' remaining values
+        // depend on the current assignment.
+        let neighbors: Vec<V> = self
+            .csp
+            .neighbors(variable)
+            .iter()
+            .filter(|neighbor| !assignment.contains_key(neighbor))
+            .cloned()
+            .collect();
+
+        let mut indices: Vec<usize> = domain.iter().collect();
+
+        indices.sort_by_cached_key(|index| {
+            assignment.insert(variable.clone(), self.csp.values(variable)[*index].clone());
+
+            let mut eliminated = 0;
+            for neighbor in &neighbors {
+                eliminated +=
+                    domains[neighbor].len() - self.remaining_values(neighbor, assignment, domains);
+            }
+
+            assignment.remove(variable);
+
+            eliminated
+        });
+
+        DomainOrder::Reordered(indices.into_iter())
+    }
+
+    /// Counts the values left in `variable`'s domain that are consistent with `assignment`.
+    fn remaining_values(
+        &mut self,
+        variable: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+    ) -> usize {
+        domains[variable]
+            .iter()
+            .filter(|index| self.consistent_with(variable, *index, assignment))
+            .count()
+    }
+
+    /// Prunes `domains` after `variable` was assigned, as configured by
+    /// `options.propagation`, or returns the variable whose domain was wiped
+    /// out.
+    ///
+    /// The pruned domains are a copy: the caller's are left untouched, so
+    /// backtracking out of the assignment restores them for free.
+    fn propagate(
+        &mut self,
+        variable: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+    ) -> Result<Domains<V, S>, V> {
+        let mut pruned = self.forward_check(variable, assignment, domains)?;
+
+        if self.options.propagation != Propagation::Mac {
+            return Ok(pruned);
+        }
+
+        let csp = self.csp;
+        let unassigned_neighbors = |of: &V| -> Vec<V> {
+            csp.neighbors(of)
+                .iter()
+                .filter(|neighbor| !assignment.contains_key(neighbor))
+                .cloned()
+                .collect()
+        };
+
+        // Forward checking only pruned the neighbors of `variable`, so only
+        // the arcs pointing at them may have lost their support.
+        let mut queue: VecDeque<(V, V)> = VecDeque::new();
+        for neighbor in unassigned_neighbors(variable) {
+            for other in unassigned_neighbors(&neighbor) {
+                queue.push_back((other, neighbor.clone()));
+            }
+        }
+
+        while let Some((a, b)) = queue.pop_front() {
+            if csp.revise(&mut pruned, &a, &b) {
+                if pruned[&a].is_empty() {
+                    self.record_wipeout(&a, &b);
+                    return Err(a);
+                }
+
+                for neighbor in unassigned_neighbors(&a) {
+                    if neighbor != b {
+                        queue.push_back((neighbor, a.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// Removes the values that became inconsistent with `assignment` from the
+    /// domains of `variable`'s unassigned neighbors, or returns the first
+    /// neighbor whose domain is wiped out.
+    fn forward_check(
+        &mut self,
+        variable: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &Domains<V, S>,
+    ) -> Result<Domains<V, S>, V> {
+        let mut pruned = domains.clone();
+
+        for neighbor in self.csp.neighbors(variable) {
+            if assignment.contains_key(neighbor) {
+                continue;
+            }
+
+            let domain = pruned.get_mut(neighbor).unwrap();
+            domain.retain(|index| self.consistent_with(neighbor, index, assignment));
+
+            if domain.is_empty() {
+                self.record_wipeout(neighbor, variable);
+                return Err(neighbor.clone());
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// Counts a failure against each constraint on `variable` that
+    /// `assignment` violates, for `VariableOrdering::DomWdeg`.
+    fn record_conflict(&mut self, variable: &V, assignment: &HashMap<V, D>) {
+        if self.options.variable_ordering != VariableOrdering::DomWdeg {
+            return;
+        }
+
+        for constraint in self.csp.constraints_for(variable) {
+            if !constraint.satisfied(assignment) {
+                *self.failures.entry(constraint_key(constraint)).or_default() += 1;
+            }
+        }
+    }
+
+    /// Counts a failure against each constraint between `wiped_out`, whose
+    /// domain was just emptied, and `culprit`, whose assignment or domain
+    /// emptied it, for `VariableOrdering::DomWdeg`.
+    fn record_wipeout(&mut self, wiped_out: &V, culprit: &V) {
+        if self.options.variable_ordering != VariableOrdering::DomWdeg {
+            return;
+        }
+
+        for constraint in self.csp.constraints_for(wiped_out) {
+            if constraint.get_variables().contains(culprit) {
+                *self.failures.entry(constraint_key(constraint)).or_default() += 1;
+            }
+        }
+    }
+
+    /// The summed weights of the constraints linking `variable` to another
+    /// unassigned variable, each weighing 1 plus its failures.
+    fn weighted_degree(&self, variable: &V, assignment: &HashMap<V, D>) -> usize {
+        self.csp
+            .constraints_for(variable)
+            .iter()
+            .filter(|constraint| {
+                constraint
+                    .get_variables()
+                    .iter()
+                    .any(|other| other != variable && !assignment.contains_key(other))
+            })
+            .map(|constraint| 1 + self.failures.get(&constraint_key(constraint)).unwrap_or(&0))
+            .sum()
+    }
+}
+
+/// Identifies a constraint by the address it is shared at, as every
+/// variable it constrains holds the same `Arc`.
+fn constraint_key<V, D>(constraint: &SharedConstraint<V, D>) -> usize {
+    Arc::as_ptr(constraint) as *const () as usize
+}
+
+/// A variable assigned by `Search::solve_iter`, with the indices of the
+/// values still to try for it.
+struct Frame<V> {
+    variable: V,
+    values: std::vec::IntoIter<usize>,
+}
+
+/// The indices of a variable's values, in the order the search tries them.
+enum DomainOrder<'d> {
+    Declaration(finite_domain::Iter<'d>),
+    Reordered(std::vec::IntoIter<usize>),
+}
+
+impl Iterator for DomainOrder<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        match self {
+            DomainOrder::Declaration(indices) => indices.next(),
+            DomainOrder::Reordered(indices) => indices.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch3_constraint_satisfaction_problems::{
+        fx_hash::FxBuildHasher, map_coloring::australia_csp, queens::queens_csp, sudoku::sudoku_csp,
+    };
+    #[test]
+    fn test_csp() {
+        let variables = vec!["A", "B", "C"];
+        let domains: HashMap<&str, Vec<i32>> = [
+            ("A", vec![1, 2, 3]),
+            ("B", vec![1, 2, 3]),
+            ("C", vec![1, 2, 3]),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+
+        let mut csp = CSP::new(variables, domains);
+
+        let a_b_not_equal = Arc::new(NotEqualConstraint {
+            variables: vec!["A", "B"],
+        });
+
+        let b_c_not_equal = Arc::new(NotEqualConstraint {
+            variables: vec!["B", "C"],
+        });
+
+        let a_c_not_equal = Arc::new(NotEqualConstraint {
+            variables: vec!["A", "C"],
+        });
+
+        csp.add_constraint(a_b_not_equal);
+        csp.add_constraint(b_c_not_equal);
+        csp.add_constraint(a_c_not_equal);
+
+        let result = csp.backtracking_search(HashMap::<&str, i32>::new());
+
+        assert!(result.is_some());
+
+        let mut expected: HashMap<&str, i32> = HashMap::new();
+        expected.insert("A", 1);
+        expected.insert("B", 2);
+        expected.insert("C", 3);
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    /// A 3-colorable graph that punishes naive ordering: "X" and "Y" can share a
+    /// color as far as their own edges go, but the triangle they both touch
+    /// forces them apart. Naive ordering assigns them the same color first and
+    /// walks every combination of the free regions before noticing; MRV
+    /// follows the constrained triangle right after "X" and fails fast.
+    fn trap_coloring_csp() -> CSP<&'static str, &'static str> {
+        let variables = vec!["X", "Y", "F1", "F2", "F3", "F4", "F5", "F6", "A", "B", "C"];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec!["red", "green", "blue"]))
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+
+        for (place1, place2) in [
+            ("A", "B"),
+            ("B", "C"),
+            ("A", "C"),
+            ("X", "A"),
+            ("X", "B"),
+            ("Y", "A"),
+            ("Y", "C"),
+        ] {
+            csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![place1, place2])));
+        }
+
+        csp
+    }
+
+    #[test]
+    fn test_static_degree_solves_trap_coloring_with_fewer_nodes() {
+        let csp = trap_coloring_csp();
+
+        let mut naive = Search::new(&csp, SearchOptions::default());
+        assert!(naive.solve(HashMap::new()).is_some());
+
+        let mut static_degree = Search::new(
+            &csp,
+            SearchOptions {
+                variable_ordering: VariableOrdering::StaticDegree,
+                ..SearchOptions::default()
+            },
+        );
+        let solution = static_degree.solve(HashMap::new()).unwrap();
+
+        // The triangle goes first, "A" being on four constraints, and then
+        // "X" and "Y" have one color left each.
+        assert_eq!(static_degree.static_order[..3], ["A", "B", "C"]);
+        assert!(csp.is_satisfied(&solution));
+        assert!(
+            static_degree.stats.nodes_visited * 50 < naive.stats.nodes_visited,
+            "static degree visited {} nodes, naive ordering visited {}",
+            static_degree.stats.nodes_visited,
+            naive.stats.nodes_visited
+        );
+    }
+
+    /// An unsatisfiable CSP whose reason is hidden from static orderings:
+    /// five pigeons "P0".."P4" can't fit four holes, but the eight chain
+    /// variables "C0".."C5" have smaller domains and more constraints, the
+    /// latter on loosely constrained satellites "S0".."S17". MRV colors the
+    /// chain first and walks its 2^5 colorings, finding the pigeons stuck
+    /// below each one.
+    fn hidden_pigeonhole_csp() -> CSP<String, usize> {
+        let chain: Vec<String> = (0..6).map(|i| format!("C{}", i)).collect();
+        let pigeons: Vec<String> = (0..5).map(|i| format!("P{}", i)).collect();
+        let satellites: Vec<String> = (0..18).map(|i| format!("S{}", i)).collect();
+        let domains = chain
+            .iter()
+            .map(|variable| (variable.clone(), (0..3).collect()))
+            .chain(
+                pigeons
+                    .iter()
+                    .map(|variable| (variable.clone(), (0..4).collect())),
+            )
+            .chain(
+                satellites
+                    .iter()
+                    .map(|variable| (variable.clone(), (0..10).collect())),
+            )
+            .collect();
+
+        let variables = [chain.clone(), pigeons.clone(), satellites.clone()].concat();
+        let mut csp = CSP::new(variables, domains);
+
+        for (i, link) in chain.iter().enumerate() {
+            if i > 0 {
+                csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![
+                    chain[i - 1].clone(),
+                    link.clone(),
+                ])));
+            }
+            for satellite in &satellites[3 * i..3 * i + 3] {
+                csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![
+                    link.clone(),
+                    satellite.clone(),
+                ])));
+            }
+        }
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(pigeons)));
+
+        csp
+    }
+
+    #[test]
+    fn test_dom_wdeg_finds_hidden_conflict_with_fewer_nodes() {
+        let csp = hidden_pigeonhole_csp();
+        let run = |variable_ordering| {
+            let mut search = Search::new(
+                &csp,
+                SearchOptions {
+                    variable_ordering,
+                    propagation: Propagation::ForwardChecking,
+                    ..SearchOptions::default()
+                },
+            );
+            assert_eq!(search.solve(HashMap::new()), None);
+            search.stats.nodes_visited
+        };
+
+        let (mrv, dom_wdeg) = (run(VariableOrdering::MRV), run(VariableOrdering::DomWdeg));
+
+        assert!(
+            dom_wdeg * 10 < mrv,
+            "dom/wdeg visited {} nodes, MRV visited {}",
+            dom_wdeg,
+            mrv
+        );
+    }
+
+    #[test]
+    fn test_dom_wdeg_solves_australia() {
+        let csp = australia_csp();
+        let options = SearchOptions {
+            variable_ordering: VariableOrdering::DomWdeg,
+            ..SearchOptions::default()
+        };
+
+        let solution = csp
+            .backtracking_search_with_options(HashMap::new(), options)
+            .unwrap();
+
+        assert!(csp.is_satisfied(&solution));
+        assert_eq!(solution.len(), 7);
+    }
+
+    #[test]
+    fn test_mrv_solves_trap_coloring_with_fewer_nodes() {
+        let csp = trap_coloring_csp();
+
+        let mut naive = Search::new(&csp, SearchOptions::default());
+        let naive_solution = naive.solve(HashMap::new());
+
+        let mut mrv = Search::new(
+            &csp,
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                ..SearchOptions::default()
+            },
+        );
+        let mrv_solution = mrv.solve(HashMap::new());
+
+        assert!(naive_solution.is_some());
+        assert!(mrv_solution.is_some());
+
+        let solution = mrv_solution.unwrap();
+        assert_ne!(solution["X"], solution["Y"]);
+        assert_ne!(solution["A"], solution["B"]);
+        assert_ne!(solution["B"], solution["C"]);
+        assert_ne!(solution["A"], solution["C"]);
+
+        assert!(
+            mrv.stats.nodes_visited * 50 < naive.stats.nodes_visited,
+            "MRV visited {} nodes, naive ordering visited {}",
+            mrv.stats.nodes_visited,
+            naive.stats.nodes_visited
+        );
+    }
+
+    /// Records its priority in `checked` whenever it is checked, and is
+    /// satisfied by anything but `A = B`.
+    struct PrioritizedConstraint {
+        variables: Vec<&'static str>,
+        priority: i32,
+        checked: Arc<std::sync::Mutex<Vec<i32>>>,
+    }
+
+    impl Constraint<&'static str, i32> for PrioritizedConstraint {
+        fn get_variables(&self) -> &Vec<&'static str> {
+            &self.variables
+        }
+
+        fn satisfied(&self, assignment: &HashMap<&'static str, i32>) -> bool {
+            self.checked.lock().unwrap().push(self.priority);
+
+            match (assignment.get("A"), assignment.get("B")) {
+                (Some(a), Some(b)) => a != b,
+                _ => true,
+            }
+        }
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+    }
+
+    #[test]
+    fn test_constraints_are_checked_by_priority() {
+        let checked = Arc::new(std::sync::Mutex::new(vec![]));
+        let domains = [("A", vec![1, 2, 3]), ("B", vec![1, 2, 3])]
+            .into_iter()
+            .collect();
+        let mut csp: CSP<&str, i32> = CSP::new(vec!["A", "B"], domains);
+        let mut unprioritized = csp.clone();
+        unprioritized.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        for priority in [0, -1, 5, 0, 2] {
+            csp.add_constraint(Arc::new(PrioritizedConstraint {
+                variables: vec!["A", "B"],
+                priority,
+                checked: checked.clone(),
+            }));
+        }
+
+        let priorities: Vec<i32> = csp
+            .constraints_for(&"A")
+            .iter()
+            .map(|constraint| constraint.priority())
+            .collect();
+        assert_eq!(priorities, [5, 2, 0, 0, -1]);
+
+        assert!(csp.consistent("A", &HashMap::from([("A", 1), ("B", 2)])));
+        assert_eq!(*checked.lock().unwrap(), [5, 2, 0, 0, -1]);
+
+        // The order only changes how soon a violation is found.
+        checked.lock().unwrap().clear();
+        assert!(!csp.consistent("A", &HashMap::from([("A", 1), ("B", 1)])));
+        assert_eq!(*checked.lock().unwrap(), [5]);
+        assert_eq!(csp.all_solutions(), unprioritized.all_solutions());
+    }
+
+    #[test]
+    fn test_neighbors_match_constraints_on_australia() {
+        let csp = australia_csp();
+
+        for variable in csp.variables() {
+            let mut expected: Vec<&str> = csp
+                .constraints_for(variable)
+                .iter()
+                .flat_map(|constraint| constraint.get_variables().clone())
+                .filter(|other| other != variable)
+                .collect();
+            expected.sort();
+            expected.dedup();
+
+            let mut neighbors = csp.neighbors(variable).to_vec();
+            neighbors.sort();
+
+            assert_eq!(neighbors, expected, "neighbors of {}", variable);
+        }
+        assert_eq!(csp.neighbors(&"South Australia").len(), 5);
+        assert_eq!(csp.neighbors(&"Tasmania"), ["Victoria"]);
+    }
+
+    #[test]
+    fn test_lcv_on_australia_visits_no_more_nodes() {
+        let csp = australia_csp();
+
+        let mut declaration = Search::new(&csp, SearchOptions::default());
+        assert!(declaration.solve(HashMap::new()).is_some());
+
+        let mut lcv = Search::new(
+            &csp,
+            SearchOptions {
+                value_ordering: ValueOrdering::LeastConstraining,
+                ..SearchOptions::default()
+            },
+        );
+        let solution = lcv.solve(HashMap::new()).unwrap();
+
+        assert_eq!(solution.len(), 7);
+        assert_ne!(solution["South Australia"], solution["Victoria"]);
+        assert_ne!(solution["Queensland"], solution["New South Wales"]);
+
+        assert!(lcv.stats.nodes_visited <= declaration.stats.nodes_visited);
+    }
+
+    #[test]
+    fn test_lcv_prefers_value_leaving_neighbors_options() {
+        let variables = vec!["A", "B"];
+        let domains = [("A", vec![1, 2]), ("B", vec![1])].into_iter().collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        let mut lcv = Search::new(
+            &csp,
+            SearchOptions {
+                value_ordering: ValueOrdering::LeastConstraining,
+                ..SearchOptions::default()
+            },
+        );
+
+        let order: Vec<i32> = lcv
+            .order_domain_values(&"A", &mut HashMap::new(), &csp.full_domains())
+            .map(|index| csp.domains["A"][index])
+            .collect();
+        assert_eq!(order, vec![2, 1]);
+
+        let solution = lcv.solve(HashMap::new()).unwrap();
+        assert_eq!(solution["A"], 2);
+        assert_eq!(lcv.stats.nodes_visited, 3);
+    }
+
+    #[test]
+    fn test_forward_checking_visits_fewer_nodes() {
+        let csp = trap_coloring_csp();
+
+        let mut plain = Search::new(&csp, SearchOptions::default());
+        assert!(plain.solve(HashMap::new()).is_some());
+
+        let mut forward_checking = Search::new(
+            &csp,
+            SearchOptions {
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+        );
+        let solution = forward_checking.solve(HashMap::new()).unwrap();
+
+        assert_ne!(solution["X"], solution["Y"]);
+        assert_ne!(solution["A"], solution["B"]);
+        assert_ne!(solution["B"], solution["C"]);
+        assert_ne!(solution["A"], solution["C"]);
+
+        assert!(forward_checking.stats.nodes_visited < plain.stats.nodes_visited);
+    }
+
+    #[test]
+    fn test_forward_checking_backtracks_on_wiped_out_domain() {
+        let variables = vec!["A", "B", "C"];
+        let domains = [("A", vec![1, 2]), ("B", vec![1]), ("C", vec![1, 2])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        let mut forward_checking = Search::new(
+            &csp,
+            SearchOptions {
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+        );
+        let solution = forward_checking.solve(HashMap::new()).unwrap();
+
+        assert_eq!(solution["A"], 2);
+        // A=1 empties B's domain, so the search never descends into it.
+        assert_eq!(forward_checking.stats.nodes_visited, 4);
+    }
+
+    #[test]
+    fn test_ac3_reduces_domains_to_singletons() {
+        let variables = vec!["A", "B", "C"];
+        let domains = [("A", vec![1]), ("B", vec![1, 2]), ("C", vec![1, 2, 3])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["B", "C"])));
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "C"])));
+
+        assert!(csp.ac3());
+
+        assert_eq!(csp.domains["A"], vec![1]);
+        assert_eq!(csp.domains["B"], vec![2]);
+        assert_eq!(csp.domains["C"], vec![3]);
+    }
+
+    #[test]
+    fn test_ac3_detects_wiped_out_domain() {
+        let variables = vec!["A", "B", "C"];
+        let domains = [("A", vec![1, 2]), ("B", vec![1, 2]), ("C", vec![1, 2])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["B", "C"])));
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "C"])));
+        csp.domains.insert("A", vec![1]);
+
+        assert!(!csp.ac3());
+    }
+
+    #[test]
+    fn test_ac3_keeps_australia_solvable() {
+        let mut csp = australia_csp();
+        csp.domains.insert("Western Australia", vec!["red"]);
+        csp.domains.insert("Northern Territory", vec!["green"]);
+
+        assert!(csp.ac3());
+
+        assert_eq!(csp.domains["South Australia"], vec!["blue"]);
+        assert_eq!(csp.domains["Queensland"], vec!["red"]);
+        assert_eq!(csp.domains["New South Wales"], vec!["green"]);
+        assert_eq!(csp.domains["Victoria"], vec!["red"]);
+        assert_eq!(csp.domains["Tasmania"], vec!["green", "blue"]);
+
+        assert!(csp.backtracking_search(HashMap::new()).is_some());
+    }
+
+    #[test]
+    fn test_try_new_reports_missing_domain() {
+        let variables = vec!["A", "B", "C"];
+        let domains: HashMap<&str, Vec<i32>> =
+            [("A", vec![1, 2]), ("C", vec![1, 2])].into_iter().collect();
+
+        let result = CSP::try_new(variables, domains);
+
+        assert_eq!(result.err(), Some(CspError::MissingDomain("B")));
+    }
+
+    #[test]
+    fn test_try_new_rejects_duplicate_variables() {
+        let variables = vec!["A", "A", "B"];
+        let domains: HashMap<&str, Vec<i32>> =
+            [("A", vec![1, 2]), ("B", vec![1, 2])].into_iter().collect();
+
+        let result = CSP::try_new(variables, domains);
+
+        assert_eq!(result.err(), Some(CspError::DuplicateVariable("A")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Every variable should be listed once.")]
+    fn test_new_panics_on_duplicate_variables() {
+        let domains: HashMap<&str, Vec<i32>> = [("A", vec![1, 2])].into_iter().collect();
+
+        CSP::new(vec!["A", "A"], domains);
+    }
+
+    #[test]
+    fn test_search_gives_up_at_once_on_empty_domain() {
+        let mut csp = australia_csp();
+        csp.set_domain(&"Tasmania", vec![]).unwrap();
+
+        let (solution, stats) = csp.backtracking_search_with_stats(HashMap::new());
+
+        assert_eq!(solution, None);
+        assert_eq!(stats.nodes_visited, 0);
+        assert_eq!(csp.backtracking_search_iter(HashMap::new()), None);
+        assert_eq!(csp.count_solutions(), 0);
+
+        // Not even a seed can give Tasmania a value outside its domain.
+        let assigned: HashMap<&str, &str> = [("Tasmania", "red")].into_iter().collect();
+        assert_eq!(csp.backtracking_search(assigned), None);
+    }
+
+    #[test]
+    fn test_search_rejects_seed_outside_the_csp() {
+        let variables = vec!["A", "B", "C"];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec![1, 2]))
+            .collect();
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        for seed in [
+            HashMap::from([("Z", 7), ("A", 1)]),
+            HashMap::from([("A", 99)]),
+        ] {
+            assert_eq!(csp.backtracking_search(seed.clone()), None);
+            assert_eq!(csp.backtracking_search_iter(seed.clone()), None);
+            assert_eq!(csp.branch_and_bound(seed), None);
+        }
+        assert!(csp.backtracking_search(HashMap::from([("A", 1)])).is_some());
+    }
+
+    #[test]
+    fn test_search_rejects_inconsistent_seed() {
+        let csp = australia_csp();
+        let seed: HashMap<&str, &str> =
+            [("Western Australia", "red"), ("Northern Territory", "red")]
+                .into_iter()
+                .collect();
+
+        let (solution, stats) = csp.backtracking_search_with_stats(seed.clone());
+
+        assert_eq!(solution, None);
+        assert_eq!(stats.nodes_visited, 0);
+        assert_eq!(csp.backtracking_search_iter(seed), None);
+
+        // Even a seed assigning every variable is checked, not handed back.
+        let complete: HashMap<&str, &str> = csp
+            .variables()
+            .iter()
+            .map(|variable| (*variable, "red"))
+            .collect();
+        assert_eq!(csp.backtracking_search(complete), None);
+    }
+
+    #[test]
+    fn test_search_completes_consistent_seed() {
+        let csp = australia_csp();
+        let seed: HashMap<&str, &str> = [("South Australia", "blue"), ("Tasmania", "blue")]
+            .into_iter()
+            .collect();
+
+        let solution = csp.backtracking_search(seed).unwrap();
+
+        assert_eq!(solution["South Australia"], "blue");
+        assert_eq!(solution["Tasmania"], "blue");
+        assert!(csp.is_satisfied(&solution));
+    }
+
+    #[test]
+    fn test_missing_domain_reads_as_empty_instead_of_panicking() {
+        let mut csp = australia_csp();
+        csp.domains.remove("Victoria");
+
+        assert_eq!(csp.backtracking_search(HashMap::new()), None);
+        assert_eq!(
+            csp.backtracking_search_with_options(
+                HashMap::new(),
+                SearchOptions {
+                    propagation: Propagation::Mac,
+                    backjumping: true,
+                    ..SearchOptions::default()
+                }
+            ),
+            None
+        );
+        assert_eq!(csp.backtracking_search_iter(HashMap::new()), None);
+        assert_eq!(csp.solutions().next(), None);
+        assert_eq!(csp.count_solutions(), 0);
+        assert_eq!(csp.min_conflicts(100, 1), None);
+        assert!(!csp.ac3());
+    }
+
+    #[test]
+    fn test_try_new_accepts_complete_domains() {
+        let variables = vec!["A", "B"];
+        let domains: HashMap<&str, Vec<i32>> =
+            [("A", vec![1, 2]), ("B", vec![1, 2])].into_iter().collect();
+
+        assert!(CSP::try_new(variables, domains).is_ok());
+    }
+
+    #[test]
+    fn test_csp_error_display() {
+        let error: Box<dyn Error> = Box::new(CspError::MissingDomain("B"));
+
+        assert_eq!(
+            error.to_string(),
+            "variable \"B\" has no domain assigned to it"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Every variable should have a domain assigned to it.")]
+    fn test_new_panics_on_missing_domain() {
+        let domains: HashMap<&str, Vec<i32>> = [("A", vec![1])].into_iter().collect();
+
+        CSP::new(vec!["A", "B"], domains);
+    }
+
+    #[test]
+    fn test_try_add_constraint_reports_unknown_variable() {
+        let mut csp = australia_csp();
+
+        let result = csp.try_add_constraint(Arc::new(NotEqualConstraint::new(vec![
+            "Victoria", "Atlantis", "Tasmania",
+        ])));
+
+        assert_eq!(result, Err(CspError::UnknownVariable("Atlantis")));
+
+        // Neither of the known variables picked up the rejected constraint.
+        assert_eq!(csp.constraints["Victoria"].len(), 3);
+        assert_eq!(csp.constraints["Tasmania"].len(), 1);
+        assert!(!csp.constraints.contains_key("Atlantis"));
+    }
+
+    #[test]
+    fn test_try_add_constraint_registers_every_variable() {
+        let mut csp = australia_csp();
+
+        let result = csp.try_add_constraint(Arc::new(NotEqualConstraint::new(vec![
+            "Western Australia",
+            "Tasmania",
+        ])));
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(csp.constraints["Western Australia"].len(), 3);
+        assert_eq!(csp.constraints["Tasmania"].len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Variable in constraint not in CSP")]
+    fn test_add_constraint_panics_on_unknown_variable() {
+        let mut csp = australia_csp();
+
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![
+            "Victoria", "Atlantis",
+        ])));
+    }
+
+    fn all_different_csp(size: i32) -> CSP<i32, i32> {
+        let variables: Vec<i32> = (0..size).collect();
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, (1..=size).collect()))
+            .collect();
+
+        let mut csp = CSP::new(variables.clone(), domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(variables)));
+
+        csp
+    }
+
+    #[test]
+    fn test_solutions_yields_first_two_lazily() {
+        let csp = all_different_csp(6);
+
+        let first_two: Vec<HashMap<i32, i32>> = csp.solutions().take(2).collect();
+
+        assert_eq!(first_two.len(), 2);
+        assert_ne!(first_two[0], first_two[1]);
+        assert_eq!(
+            Some(first_two[0].clone()),
+            csp.backtracking_search(HashMap::new())
+        );
+
+        for solution in &first_two {
+            let mut values: Vec<i32> = solution.values().cloned().collect();
+            values.sort();
+            assert_eq!(values, vec![1, 2, 3, 4, 5, 6]);
+        }
+    }
+
+    #[test]
+    fn test_all_solutions_enumerates_every_permutation() {
+        let solutions = all_different_csp(4).all_solutions();
+
+        assert_eq!(solutions.len(), 24);
+
+        for (i, solution) in solutions.iter().enumerate() {
+            assert!(!solutions[i + 1..].contains(solution));
+        }
+    }
+
+    #[test]
+    fn test_solutions_of_unsatisfiable_csp_is_empty() {
+        let mut csp = all_different_csp(3);
+        csp.domains.insert(2, vec![1]);
+        csp.domains.insert(1, vec![1]);
+
+        assert_eq!(csp.solutions().next(), None);
+    }
+
+    #[test]
+    fn test_count_solutions_matches_all_solutions() {
+        let csp = all_different_csp(4);
+
+        assert_eq!(csp.count_solutions(), csp.all_solutions().len());
+        assert_eq!(csp.count_solutions(), 24);
+
+        let csp = australia_csp();
+
+        assert_eq!(csp.count_solutions(), csp.all_solutions().len());
+        assert_eq!(csp.count_solutions(), 12);
+    }
+
+    #[test]
+    fn test_constraints_macro_reproduces_australia() {
+        let mut csp = australia_csp();
+        let regions = csp.variables().to_vec();
+        let domains = regions
+            .iter()
+            .map(|region| (*region, csp.domain(region).unwrap().to_vec()))
+            .collect();
+        csp = CSP::new(regions, domains);
+
+        constraints!(csp;
+            "Western Australia" != "Northern Territory",
+            "Western Australia" != "South Australia",
+            "Northern Territory" != "South Australia",
+            "Northern Territory" != "Queensland",
+            "South Australia" != "Queensland",
+            "South Australia" != "New South Wales",
+            "South Australia" != "Victoria",
+            "Queensland" != "New South Wales",
+            "New South Wales" != "Victoria",
+            "Victoria" != "Tasmania",
+        );
+
+        assert_eq!(csp.all_solutions(), australia_csp().all_solutions());
+    }
+
+    #[test]
+    fn test_constraints_macro_mixes_rules() {
+        let domains = ["A", "B", "C", "D"]
+            .into_iter()
+            .map(|variable| (variable, vec![1, 2, 3]))
+            .collect();
+        let mut csp = CSP::new(vec!["A", "B", "C", "D"], domains);
+
+        constraints!(csp; "A" == "D", all_diff("A", "B", "C"), "B" != "D");
+
+        // C can only take D's value, as A already holds it.
+        assert_eq!(csp.count_solutions(), 6);
+        for solution in csp.all_solutions() {
+            assert_eq!(solution["A"], solution["D"]);
+            assert_ne!(solution["B"], solution["C"]);
+        }
+    }
+
+    #[test]
+    fn test_all_solutions_canonical_removes_color_swaps() {
+        let csp = australia_csp();
+        let red_green = Symmetry::swap_values("red", "green");
+        let green_blue = Symmetry::swap_values("green", "blue");
+
+        let canonical = csp.all_solutions_canonical(std::slice::from_ref(&red_green));
+
+        assert_eq!(canonical.len(), 6);
+        for solution in &canonical {
+            assert!(!canonical.contains(&red_green.apply(solution)));
+        }
+
+        // The two swaps generate every permutation of the three colors.
+        assert_eq!(
+            csp.all_solutions_canonical(&[red_green, green_blue]).len(),
+            2
+        );
+        assert_eq!(csp.all_solutions_canonical(&[]), csp.all_solutions());
+    }
+
+    #[test]
+    fn test_all_solutions_canonical_removes_variable_swaps() {
+        let csp = all_different_csp(3);
+
+        let canonical = csp.all_solutions_canonical(&[Symmetry::swap_variables(0, 1)]);
+
+        assert_eq!(canonical.len(), 3);
+        for solution in canonical {
+            assert!(solution[&0] < solution[&1]);
+        }
+    }
+
+    /// The selected nodes of the path 0 - 1 - 2 - 3 - 4 are connected once
+    /// every node is decided.
+    fn connected_path_selection(assignment: &HashMap<usize, bool>) -> bool {
+        if assignment.len() < 5 {
+            return true;
+        }
+
+        let selected: Vec<usize> = (0..5).filter(|node| assignment[node]).collect();
+
+        selected.windows(2).all(|pair| pair[1] == pair[0] + 1)
+    }
+
+    #[test]
+    fn test_global_constraints_select_connected_nodes() {
+        let nodes: Vec<usize> = (0..5).collect();
+        let domains = nodes
+            .iter()
+            .map(|node| (*node, vec![false, true]))
+            .collect();
+        let mut csp = CSP::new(nodes.clone(), domains);
+
+        csp.add_global_constraint(Arc::new(ExactlyKConstraint::new(nodes.clone(), true, 3)));
+        csp.add_global_constraint(Arc::new(FunctionConstraint::new(
+            nodes,
+            connected_path_selection,
+        )));
+
+        // Only runs of three nodes remain, and no node became a neighbor.
+        assert_eq!(csp.count_solutions(), 3);
+        assert!(csp.neighbors(&0).is_empty());
+        assert!(csp.constraints_for(&0).is_empty());
+        assert!(!csp.is_satisfied(&HashMap::from([
+            (0, true),
+            (1, false),
+            (2, true),
+            (3, true),
+            (4, false),
+        ])));
+
+        let solution = csp.min_conflicts(1_000, 5).unwrap();
+        assert!(csp.is_satisfied(&solution));
+    }
+
+    #[test]
+    fn test_backjumping_finds_the_same_solutions_under_global_constraints() {
+        let variables = vec!["x", "y", "z"];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec![0, 1, 2]))
+            .collect();
+        let mut csp = CSP::new(variables.clone(), domains);
+
+        // Every value of z fails under x = y = 0, which only the global
+        // constraint knows to blame on them.
+        csp.add_global_constraint(Arc::new(FunctionConstraint::new(
+            variables,
+            |assignment: &HashMap<&str, i32>| {
+                assignment.len() < 3 || assignment.values().sum::<i32>() == 4
+            },
+        )));
+
+        assert_backjumping_agrees(&csp);
+    }
+
+    #[test]
+    fn test_try_add_global_constraint_rejects_unknown_variable() {
+        let mut csp = all_different_csp(2);
+
+        assert_eq!(
+            csp.try_add_global_constraint(Arc::new(NotEqualConstraint::new(vec![0, 7]))),
+            Err(CspError::UnknownVariable(7))
+        );
+    }
+
+    #[test]
+    fn test_is_consistent_checks_constraints_on_variable() {
+        let csp = australia_csp();
+        let mut assignment = HashMap::from([
+            ("Western Australia", "red"),
+            ("Northern Territory", "green"),
+        ]);
+
+        assert!(csp.is_consistent(&"Northern Territory", &assignment));
+
+        assignment.insert("South Australia", "red");
+        assert!(!csp.is_consistent(&"South Australia", &assignment));
+        // Tasmania borders neither region, so the clash isn't its concern.
+        assert!(csp.is_consistent(&"Tasmania", &assignment));
+    }
+
+    #[test]
+    fn test_count_solutions_with_progress() {
+        let csp = all_different_csp(4);
+        let mut reported = vec![];
+
+        let count = csp.count_solutions_with_progress(5, |count| reported.push(count));
+
+        assert_eq!(count, csp.count_solutions());
+        assert_eq!(reported, vec![5, 10, 15, 20]);
+    }
+
+    #[test]
+    fn test_count_solutions_of_unsatisfiable_csp_is_zero() {
+        let mut csp = all_different_csp(3);
+        csp.domains.insert(1, vec![1]);
+        csp.domains.insert(2, vec![1]);
+
+        assert_eq!(csp.count_solutions(), 0);
+    }
+
+    fn assert_no_queens_attack(solution: &HashMap<usize, usize>, n: usize) {
+        assert_eq!(solution.len(), n);
+
+        for column1 in 0..n {
+            for column2 in column1 + 1..n {
+                let (row1, row2) = (solution[&column1], solution[&column2]);
+
+                assert_ne!(row1, row2);
+                assert_ne!(row1.abs_diff(row2), column1.abs_diff(column2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_conflicts_solves_50_queens() {
+        let csp = queens_csp(50);
+
+        let solution = csp.min_conflicts(10_000, 42).unwrap();
+
+        assert_no_queens_attack(&solution, 50);
+    }
+
+    #[test]
+    fn test_min_conflicts_is_reproducible_for_a_seed() {
+        let csp = queens_csp(12);
+
+        assert_eq!(csp.min_conflicts(5_000, 7), csp.min_conflicts(5_000, 7));
+    }
+
+    #[test]
+    fn test_solve_with_restarts_solves_what_single_runs_miss() {
+        let csp = queens_csp(20);
+        let seeds = 0..20;
+
+        assert!(seeds
+            .clone()
+            .any(|seed| csp.min_conflicts(40, seed).is_none()));
+
+        for seed in seeds {
+            let (solution, stats) = csp.solve_with_restarts_and_stats(40, 200, seed);
+
+            assert_no_queens_attack(&solution.unwrap(), 20);
+            assert!(stats.restarts <= 200);
+        }
+    }
+
+    #[test]
+    fn test_solve_with_restarts_counts_no_restart_when_first_run_succeeds() {
+        let csp = queens_csp(8);
+
+        let (solution, stats) = csp.solve_with_restarts_and_stats(10_000, 5, 3);
+
+        assert!(solution.is_some());
+        assert_eq!(stats.restarts, 0);
+    }
+
+    #[test]
+    fn test_solve_with_restarts_gives_up_after_max_restarts() {
+        let csp = queens_csp(3);
+
+        let (solution, stats) = csp.solve_with_restarts_and_stats(20, 4, 1);
+
+        assert_eq!(solution, None);
+        assert_eq!(stats.restarts, 4);
+        assert_eq!(csp.solve_with_restarts(20, 4, 1), None);
+    }
+
+    #[test]
+    fn test_tabu_search_escapes_where_min_conflicts_cycles() {
+        let csp = queens_csp(6);
+        let seeds = 0..20;
+
+        // Six queens have only four solutions, and min-conflicts keeps
+        // moving between the same near misses from some of these starts.
+        assert!(seeds
+            .clone()
+            .any(|seed| csp.min_conflicts(1_000, seed).is_none()));
+
+        for seed in seeds {
+            let (solution, violated) = csp.tabu_search(1_000, 3, seed).unwrap();
+
+            assert_eq!(violated, 0);
+            assert_no_queens_attack(&solution, 6);
+        }
+    }
+
+    #[test]
+    fn test_tabu_search_returns_best_assignment_of_unsatisfiable_csp() {
+        let csp = queens_csp(3);
+
+        let (assignment, violated) = csp.tabu_search(200, 2, 1).unwrap();
+
+        // Three queens on a 3x3 board attack each other at least once.
+        assert_eq!(assignment.len(), 3);
+        assert_eq!(violated, 1);
+        assert!(!csp.is_satisfied(&assignment));
+        assert_eq!(csp.tabu_search(200, 2, 1), Some((assignment, violated)));
+    }
+
+    #[test]
+    fn test_simulated_annealing_colors_australia() {
+        let csp = australia_csp();
+        let schedule = CoolingSchedule {
+            initial_temperature: 2.0,
+            cooling_rate: 0.99,
+            min_temperature: 0.01,
+        };
+
+        for seed in 0..10 {
+            let (solution, violated) = csp.simulated_annealing(schedule, seed).unwrap();
+
+            assert_eq!(violated, 0);
+            assert!(csp.is_satisfied(&solution));
+            assert_eq!(
+                csp.simulated_annealing(schedule, seed),
+                Some((solution, violated))
+            );
+        }
+    }
+
+    #[test]
+    fn test_simulated_annealing_without_conflicted_variables() {
+        let mut csp = australia_csp();
+        csp.add_global_constraint(Arc::new(FunctionConstraint::new(
+            vec![],
+            |_: &HashMap<&str, &str>| false,
+        )));
+        let schedule = CoolingSchedule {
+            initial_temperature: 2.0,
+            cooling_rate: 0.99,
+            min_temperature: 0.01,
+        };
+
+        let (solution, violated) = csp.simulated_annealing(schedule, 0).unwrap();
+
+        assert_eq!(violated, 1);
+        assert!(australia_csp().is_satisfied(&solution));
+    }
+
+    #[test]
+    #[should_panic(expected = "the cooling schedule has to reach its minimum temperature")]
+    fn test_simulated_annealing_rejects_schedule_that_never_cools() {
+        let schedule = CoolingSchedule {
+            initial_temperature: 1.0,
+            cooling_rate: 1.0,
+            min_temperature: 0.1,
+        };
+
+        australia_csp().simulated_annealing(schedule, 0);
+    }
+
+    #[test]
+    fn test_min_conflicts_gives_up_on_unsatisfiable_csp() {
+        let csp = queens_csp(3);
+
+        assert_eq!(csp.min_conflicts(200, 1), None);
+    }
+
+    #[test]
+    fn test_search_stats_on_australia() {
+        let csp = australia_csp();
+
+        let (solution, stats) = csp.backtracking_search_with_stats(HashMap::new());
+
+        assert!(solution.is_some());
+
+        // Naive ordering colors Australia without a single dead end.
+        assert_eq!(stats.nodes_visited, 8);
+        assert_eq!(stats.backtracks, 0);
+        assert!(stats.consistency_checks >= stats.nodes_visited - 1);
+    }
+
+    #[test]
+    fn test_search_stats_are_internally_consistent() {
+        let csp = trap_coloring_csp();
+
+        let (_, naive) = csp.backtracking_search_with_stats(HashMap::new());
+        assert!(naive.backtracks > 0);
+
+        for options in [
+            SearchOptions::default(),
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                propagation: Propagation::Mac,
+                ..SearchOptions::default()
+            },
+        ] {
+            let (solution, stats) =
+                csp.backtracking_search_with_options_and_stats(HashMap::new(), options);
+
+            assert!(solution.is_some());
+            assert!(stats.nodes_visited > 0);
+            assert!(stats.backtracks < stats.nodes_visited);
+            // Every node below the root was entered through a passed check.
+            assert!(stats.consistency_checks >= stats.nodes_visited - 1);
+        }
+    }
+
+    #[test]
+    fn test_bounded_search_reaches_limit_on_hard_instance() {
+        let csp = trap_coloring_csp();
+
+        assert_eq!(
+            csp.backtracking_search_bounded(HashMap::new(), 10),
+            Completeness::Unknown
+        );
+
+        match csp.backtracking_search_bounded(HashMap::new(), 1_000_000) {
+            Completeness::Solved(solution) => {
+                assert_eq!(Some(solution), csp.backtracking_search(HashMap::new()))
+            }
+            outcome => panic!("expected a solution, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_bounded_search_proves_unsatisfiable() {
+        let mut csp = all_different_csp(3);
+        csp.domains.insert(1, vec![1]);
+        csp.domains.insert(2, vec![1]);
+
+        assert_eq!(
+            csp.backtracking_search_bounded(HashMap::new(), 1_000),
+            Completeness::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn test_timeout_returns_promptly_on_large_problem() {
+        // Thirteen distinct values out of twelve: naive search would walk
+        // through billions of nodes before proving there is no solution.
+        let mut csp = all_different_csp(13);
+        for variable in 0..13 {
+            csp.domains.insert(variable, (1..=12).collect());
+        }
+
+        let start = Instant::now();
+        let outcome = csp.backtracking_search_timeout(HashMap::new(), Duration::from_micros(1));
+
+        assert_eq!(outcome, Completeness::Unknown);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_bounded_searches_agree_with_unbounded_search() {
+        let mut unsatisfiable = all_different_csp(3);
+        unsatisfiable.domains.insert(1, vec![1]);
+        unsatisfiable.domains.insert(2, vec![1]);
+
+        let csp = australia_csp();
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+        assert_eq!(
+            csp.backtracking_search_bounded(HashMap::new(), 1_000),
+            Completeness::Solved(solution.clone())
+        );
+        assert_eq!(
+            csp.backtracking_search_timeout(HashMap::new(), Duration::from_secs(60)),
+            Completeness::Solved(solution)
+        );
+
+        assert_eq!(unsatisfiable.backtracking_search(HashMap::new()), None);
+        assert_eq!(
+            unsatisfiable.backtracking_search_bounded(HashMap::new(), 1_000),
+            Completeness::Unsatisfiable
+        );
+        assert_eq!(
+            unsatisfiable.backtracking_search_timeout(HashMap::new(), Duration::from_secs(60)),
+            Completeness::Unsatisfiable
+        );
+
+        // Out of budget, the same search can't tell either way.
+        assert_eq!(
+            csp.backtracking_search_bounded(HashMap::new(), 3),
+            Completeness::Unknown
+        );
+        assert_eq!(
+            all_different_csp(3).backtracking_search_bounded(HashMap::new(), 0),
+            Completeness::Unknown
+        );
+    }
+
+    #[test]
+    fn test_timeout_leaves_quick_searches_alone() {
+        let csp = australia_csp();
+
+        assert!(matches!(
+            csp.backtracking_search_timeout(HashMap::new(), Duration::from_secs(60)),
+            Completeness::Solved(_)
+        ));
+    }
+
+    #[test]
+    fn test_all_different_ignores_unassigned_variables() {
+        let constraint = AllDifferentConstraint::new(vec!["A", "B", "C"]);
+
+        let empty: HashMap<&str, i32> = HashMap::new();
+        assert!(constraint.satisfied(&empty));
+
+        let partial: HashMap<&str, i32> = [("A", 1), ("C", 2)].into_iter().collect();
+        assert!(constraint.satisfied(&partial));
+
+        let clashing: HashMap<&str, i32> = [("A", 1), ("C", 1)].into_iter().collect();
+        assert!(!constraint.satisfied(&clashing));
+    }
+
+    #[test]
+    fn test_all_different_on_full_assignment() {
+        let constraint = AllDifferentConstraint::new(vec!["A", "B", "C"]);
+
+        let distinct: HashMap<&str, i32> = [("A", 1), ("B", 2), ("C", 3)].into_iter().collect();
+        assert!(constraint.satisfied(&distinct));
+
+        let repeated: HashMap<&str, i32> = [("A", 1), ("B", 2), ("C", 2)].into_iter().collect();
+        assert!(!constraint.satisfied(&repeated));
+    }
+
+    #[test]
+    fn test_all_different_solves_permutation_problem() {
+        let variables = vec!["A", "B", "C"];
+        let domains = [("A", vec![1, 2]), ("B", vec![1]), ("C", vec![1, 2, 3])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables.clone(), domains);
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(variables)));
+
+        let expected: HashMap<&str, i32> = [("A", 2), ("B", 1), ("C", 3)].into_iter().collect();
+        assert_eq!(csp.backtracking_search(HashMap::new()), Some(expected));
+    }
+
+    #[test]
+    fn test_function_constraint_reproduces_not_equal() {
+        let variables = vec!["A", "B", "C"];
+        let domains: HashMap<&str, Vec<i32>> = [
+            ("A", vec![1, 2, 3]),
+            ("B", vec![1, 2, 3]),
+            ("C", vec![1, 2, 3]),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut csp = CSP::new(variables, domains);
+
+        for (first, second) in [("A", "B"), ("B", "C"), ("A", "C")] {
+            csp.add_constraint(Arc::new(FunctionConstraint::new(
+                vec![first, second],
+                move |assignment: &HashMap<&str, i32>| match (
+                    assignment.get(first),
+                    assignment.get(second),
+                ) {
+                    (Some(value1), Some(value2)) => value1 != value2,
+                    _ => true,
+                },
+            )));
+        }
+
+        let expected: HashMap<&str, i32> = [("A", 1), ("B", 2), ("C", 3)].into_iter().collect();
+        assert_eq!(csp.backtracking_search(HashMap::new()), Some(expected));
+    }
+
+    #[test]
+    fn test_function_constraint_delegates_to_closure() {
+        let constraint = FunctionConstraint::new(vec!["A"], |assignment: &HashMap<&str, i32>| {
+            assignment.get("A").is_none_or(|value| value % 2 == 0)
+        });
+
+        assert_eq!(constraint.get_variables(), &vec!["A"]);
+        assert!(constraint.satisfied(&HashMap::new()));
+        assert!(constraint.satisfied(&[("A", 4)].into_iter().collect()));
+        assert!(!constraint.satisfied(&[("A", 3)].into_iter().collect()));
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    #[test]
+    // The point is a constraint that isn't `Send`, which only the `parallel`
+    // feature requires.
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn test_constraints_need_not_be_thread_safe() {
+        let checks = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut csp = australia_csp();
+        csp.add_constraint(Arc::new(FunctionConstraint::new(vec!["Tasmania"], {
+            let checks = std::rc::Rc::clone(&checks);
+            move |_: &HashMap<&str, &str>| {
+                checks.set(checks.get() + 1);
+                true
+            }
+        })));
+
+        assert!(csp.backtracking_search(HashMap::new()).is_some());
+        assert!(checks.get() > 0);
+    }
+
+    #[test]
+    fn test_not_constraint_negates_full_assignments() {
+        let not_less = NotConstraint::new(LessThanConstraint::new("A", "B"));
+
+        assert!(!not_less.satisfied(&HashMap::from([("A", 1), ("B", 2)])));
+        assert!(not_less.satisfied(&HashMap::from([("A", 2), ("B", 2)])));
+        assert!(not_less.satisfied(&HashMap::from([("A", 3), ("B", 2)])));
+        assert_eq!(
+            Constraint::<&str, i32>::get_variables(&not_less),
+            &vec!["A", "B"]
+        );
+    }
+
+    #[test]
+    fn test_not_constraint_tolerates_partial_assignments() {
+        let not_equal = NotConstraint::new(EqualConstraint::new("A", "B"));
+
+        assert!(not_equal.satisfied(&HashMap::<&str, i32>::new()));
+        assert!(not_equal.satisfied(&HashMap::from([("A", 1)])));
+
+        let domains = [("A", vec![1, 2]), ("B", vec![1, 2])].into_iter().collect();
+        let mut csp = CSP::new(vec!["A", "B"], domains);
+        csp.add_constraint(Arc::new(not_equal));
+
+        assert_eq!(csp.count_solutions(), 2);
+    }
+
+    fn less_than(a: &'static str, b: &'static str) -> SharedConstraint<&'static str, i32> {
+        Arc::new(LessThanConstraint::new(a, b))
+    }
+
+    #[test]
+    fn test_and_constraint_requires_every_inner_constraint() {
+        let and = AndConstraint::new(vec![less_than("A", "B"), less_than("B", "C")]);
+
+        assert_eq!(and.get_variables(), &vec!["A", "B", "C"]);
+        assert!(and.satisfied(&HashMap::from([("A", 1), ("B", 2), ("C", 3)])));
+        assert!(!and.satisfied(&HashMap::from([("A", 1), ("B", 3), ("C", 2)])));
+        // A violated conjunct is enough, even with "C" unassigned.
+        assert!(!and.satisfied(&HashMap::from([("A", 2), ("B", 1)])));
+        assert!(and.satisfied(&HashMap::from([("A", 1), ("B", 2)])));
+    }
+
+    #[test]
+    fn test_or_constraint_requires_one_inner_constraint() {
+        let or = OrConstraint::new(vec![less_than("A", "B"), less_than("B", "A")]);
+
+        assert_eq!(or.get_variables(), &vec!["A", "B"]);
+        assert!(or.satisfied(&HashMap::from([("A", 1), ("B", 2)])));
+        assert!(or.satisfied(&HashMap::from([("A", 2), ("B", 1)])));
+        assert!(!or.satisfied(&HashMap::from([("A", 2), ("B", 2)])));
+        assert!(or.satisfied(&HashMap::from([("A", 2)])));
+    }
+
+    #[test]
+    fn test_or_constraint_waits_for_partial_disjuncts() {
+        // "A" and "B" must be equal, or "C" must be 3, the latter disjunct
+        // rejecting any assignment that leaves "C" out.
+        let c_is_three: SharedConstraint<&str, i32> = Arc::new(FunctionConstraint::new(
+            vec!["C"],
+            |assignment: &HashMap<&str, i32>| assignment.get("C") == Some(&3),
+        ));
+        let or = OrConstraint::new(vec![Arc::new(EqualConstraint::new("A", "B")), c_is_three]);
+
+        assert!(or.satisfied(&HashMap::from([("A", 1), ("B", 2)])));
+        assert!(!or.satisfied(&HashMap::from([("A", 1), ("B", 2), ("C", 1)])));
+        assert!(or.satisfied(&HashMap::from([("A", 1), ("B", 2), ("C", 3)])));
+        assert!(or.satisfied(&HashMap::from([("A", 2), ("B", 2), ("C", 1)])));
+    }
+
+    /// "If the sunroof is fitted, the roof must be metal": option 1 of
+    /// "sunroof" is fitted, and roof 0 is metal.
+    fn sunroof_implication() -> ImplicationConstraint<&'static str, i32> {
+        let fitted: SharedConstraint<&str, i32> = Arc::new(FunctionConstraint::new(
+            vec!["sunroof"],
+            |assignment: &HashMap<&str, i32>| assignment.get("sunroof") == Some(&1),
+        ));
+        let metal: SharedConstraint<&str, i32> = Arc::new(FunctionConstraint::new(
+            vec!["roof"],
+            |assignment: &HashMap<&str, i32>| assignment.get("roof").is_none_or(|roof| *roof == 0),
+        ));
+
+        ImplicationConstraint::new(fitted, metal)
+    }
+
+    #[test]
+    fn test_implication_enforces_consequence_when_condition_holds() {
+        let implication = sunroof_implication();
+
+        assert_eq!(implication.get_variables(), &vec!["sunroof", "roof"]);
+        assert!(implication.satisfied(&HashMap::from([("sunroof", 1), ("roof", 0)])));
+        assert!(!implication.satisfied(&HashMap::from([("sunroof", 1), ("roof", 1)])));
+        // With the roof still open, the consequence accepts it for now.
+        assert!(implication.satisfied(&HashMap::from([("sunroof", 1)])));
+    }
+
+    #[test]
+    // Outside the `parallel` feature, the constraints a combinator holds
+    // needn't be `Send`, and neither is the combinator.
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn test_implication_holds_when_condition_fails_or_is_undecided() {
+        let implication = sunroof_implication();
+
+        assert!(implication.satisfied(&HashMap::from([("sunroof", 0), ("roof", 1)])));
+        // The condition rejects a missing "sunroof", but that doesn't make
+        // it false yet.
+        assert!(implication.satisfied(&HashMap::from([("roof", 1)])));
+
+        let domains = [("sunroof", vec![0, 1]), ("roof", vec![0, 1])]
+            .into_iter()
+            .collect();
+        let mut csp = CSP::new(vec!["sunroof", "roof"], domains);
+        csp.add_constraint(Arc::new(implication));
+
+        assert_eq!(csp.count_solutions(), 3);
+    }
+
+    fn two_color_triangle_csp() -> CSP<&'static str, &'static str> {
+        let variables = vec!["A", "B", "C"];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec!["red", "green"]))
+            .collect();
+
+        CSP::new(variables, domains)
+    }
+
+    #[test]
+    fn test_branch_and_bound_picks_cheapest_clash() {
+        let mut csp = two_color_triangle_csp();
+
+        for (place1, place2, weight) in [("A", "B", 5.0), ("B", "C", 1.0), ("A", "C", 3.0)] {
+            csp.add_soft_constraint(WeightedConstraint::new(
+                Arc::new(NotEqualConstraint::new(vec![place1, place2])),
+                weight,
+            ));
+        }
+
+        // Two colors for a triangle: some pair has to clash, but only soft
+        // constraints are broken, which the plain search doesn't look at.
+        assert!(csp.backtracking_search(HashMap::new()).is_some());
+
+        let (solution, penalty) = csp.branch_and_bound(HashMap::new()).unwrap();
+
+        assert_eq!(penalty, 1.0);
+        assert_eq!(solution["B"], solution["C"]);
+        assert_ne!(solution["A"], solution["B"]);
+    }
+
+    #[test]
+    fn test_branch_and_bound_honors_hard_constraints() {
+        let mut csp = two_color_triangle_csp();
+
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["B", "C"])));
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+        csp.add_soft_constraint(WeightedConstraint::new(
+            Arc::new(NotEqualConstraint::new(vec!["A", "C"])),
+            2.5,
+        ));
+
+        let (solution, penalty) = csp.branch_and_bound(HashMap::new()).unwrap();
+
+        assert_eq!(penalty, 2.5);
+        assert_eq!(solution["A"], solution["C"]);
+
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "C"])));
+
+        assert_eq!(csp.branch_and_bound(HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_branch_and_bound_rejects_inconsistent_seed() {
+        let mut csp = two_color_triangle_csp();
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        assert_eq!(
+            csp.branch_and_bound(HashMap::from([("A", "red"), ("B", "red")])),
+            None
+        );
+        assert_eq!(
+            csp.branch_and_bound(HashMap::from([("A", "red"), ("B", "red"), ("C", "red")])),
+            None
+        );
+        assert!(csp
+            .branch_and_bound(HashMap::from([("A", "red")]))
+            .is_some());
+    }
+
+    /// `A + B == C` over distinct digits.
+    fn digit_sum_csp() -> CSP<&'static str, i64> {
+        let variables = vec!["A", "B", "C"];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, (0..=9).collect()))
+            .collect();
+
+        let mut csp = CSP::new(variables.clone(), domains);
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(variables)));
+        csp.add_constraint(Arc::new(FunctionConstraint::new(
+            vec!["A", "B", "C"],
+            |assignment: &HashMap<&str, i64>| match (
+                assignment.get("A"),
+                assignment.get("B"),
+                assignment.get("C"),
+            ) {
+                (Some(a), Some(b), Some(c)) => a + b == *c,
+                _ => true,
+            },
+        )));
+
+        csp
+    }
+
+    fn digit_sum(assignment: &HashMap<&str, i64>) -> i64 {
+        assignment.values().sum()
+    }
+
+    #[test]
+    fn test_optimize_maximizes_digit_sum() {
+        let csp = digit_sum_csp();
+
+        let best = csp.optimize(digit_sum, Goal::Maximize).unwrap();
+
+        assert_eq!(best["A"] + best["B"], best["C"]);
+        assert_eq!(digit_sum(&best), 18);
+    }
+
+    #[test]
+    fn test_optimize_minimizes_digit_sum() {
+        let csp = digit_sum_csp();
+
+        let best = csp.optimize(digit_sum, Goal::Minimize).unwrap();
+
+        // Using 0 would make the other two digits equal.
+        assert_eq!(digit_sum(&best), 6);
+    }
+
+    #[test]
+    fn test_optimize_with_bound_agrees_with_exhaustive_search() {
+        let csp = digit_sum_csp();
+
+        // Every unassigned digit adds at most 9.
+        let upper_bound = |assignment: &HashMap<&str, i64>| {
+            digit_sum(assignment) + 9 * (3 - assignment.len() as i64)
+        };
+
+        let bounded = csp
+            .optimize_with_bound(digit_sum, upper_bound, Goal::Maximize)
+            .unwrap();
+
+        assert_eq!(digit_sum(&bounded), 18);
+        assert_eq!(bounded["A"] + bounded["B"], bounded["C"]);
+    }
+
+    #[test]
+    fn test_optimize_without_solution() {
+        let mut csp = digit_sum_csp();
+        csp.domains.insert("C", vec![0]);
+
+        assert_eq!(csp.optimize(digit_sum, Goal::Maximize), None);
+    }
+
+    #[test]
+    fn test_accessors_reflect_construction() {
+        let variables = vec!["A", "B", "C"];
+        let domains = [("A", vec![1, 2]), ("B", vec![3]), ("C", vec![])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+
+        assert_eq!(csp.variables(), &["A", "B", "C"]);
+        assert_eq!(csp.domain(&"A"), Some(&[1, 2][..]));
+        assert_eq!(csp.domain(&"B"), Some(&[3][..]));
+        assert_eq!(csp.domain(&"C"), Some(&[][..]));
+        assert_eq!(csp.domain(&"D"), None);
+        assert!(csp.constraints_for(&"A").is_empty());
+
+        let a_b: SharedConstraint<&str, i32> = Arc::new(NotEqualConstraint::new(vec!["A", "B"]));
+        let a_c: SharedConstraint<&str, i32> = Arc::new(NotEqualConstraint::new(vec!["A", "C"]));
+        csp.add_constraint(a_b.clone());
+        csp.add_constraint(a_c.clone());
+
+        let for_a = csp.constraints_for(&"A");
+        assert_eq!(for_a.len(), 2);
+        assert!(Arc::ptr_eq(&for_a[0], &a_b));
+        assert!(Arc::ptr_eq(&for_a[1], &a_c));
+
+        assert_eq!(csp.constraints_for(&"B").len(), 1);
+        assert!(Arc::ptr_eq(&csp.constraints_for(&"C")[0], &a_c));
+        assert!(csp.constraints_for(&"D").is_empty());
+    }
+
+    #[test]
+    fn test_restrict_domain_to_singleton_forces_value() {
+        let mut csp = australia_csp();
+
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+        assert_eq!(solution["South Australia"], "blue");
+
+        csp.restrict_domain(&"South Australia", &["red", "purple"])
+            .unwrap();
+        assert_eq!(csp.domain(&"South Australia"), Some(&["red"][..]));
+
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+        assert_eq!(solution["South Australia"], "red");
+        assert_ne!(solution["Western Australia"], "red");
+    }
+
+    #[test]
+    fn test_set_domain_replaces_domain() {
+        let mut csp = australia_csp();
+
+        csp.set_domain(&"Tasmania", vec!["yellow"]).unwrap();
+
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+        assert_eq!(solution["Tasmania"], "yellow");
+    }
+
+    #[test]
+    fn test_set_value_order_sets_order_values_are_tried_in() {
+        let tried = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut csp = CSP::new(vec!["x"], HashMap::from([("x", vec![1, 2, 3, 4])]));
+        csp.add_constraint(Arc::new(FunctionConstraint::new(vec!["x"], {
+            let tried = Arc::clone(&tried);
+            move |assignment: &HashMap<&str, i32>| {
+                let Some(x) = assignment.get("x") else {
+                    return true;
+                };
+                tried.lock().unwrap().push(*x);
+                *x == 1
+            }
+        })));
+
+        csp.set_value_order(&"x", vec![3, 5, 1]).unwrap();
+        assert_eq!(csp.domain(&"x"), Some(&[3, 1, 2, 4][..]));
+
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+        assert_eq!(solution["x"], 1);
+        assert_eq!(*tried.lock().unwrap(), vec![3, 1]);
+
+        assert_eq!(
+            csp.set_value_order(&"y", vec![1]),
+            Err(CspError::UnknownVariable("y"))
+        );
+    }
+
+    #[test]
+    fn test_domain_editing_rejects_unknown_variable() {
+        let mut csp = australia_csp();
+
+        assert_eq!(
+            csp.set_domain(&"Atlantis", vec!["red"]),
+            Err(CspError::UnknownVariable("Atlantis"))
+        );
+        assert_eq!(
+            csp.restrict_domain(&"Atlantis", &["red"]),
+            Err(CspError::UnknownVariable("Atlantis"))
+        );
+        assert_eq!(csp.domain(&"Atlantis"), None);
+    }
+
+    #[test]
+    fn test_add_variable_builds_csp_incrementally() {
+        let mut csp: CSP<&str, i32> = CSP::new(vec![], HashMap::new());
+        assert_eq!(
+            csp.backtracking_search(HashMap::new()),
+            Some(HashMap::new())
+        );
+
+        csp.add_variable("A", vec![1, 2, 3]).unwrap();
+        csp.add_variable("B", vec![1, 2]).unwrap();
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+        csp.add_variable("C", vec![1]).unwrap();
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B", "C"])));
+
+        assert_eq!(csp.variables(), &["A", "B", "C"]);
+
+        let expected: HashMap<&str, i32> = [("A", 3), ("B", 2), ("C", 1)].into_iter().collect();
+        assert_eq!(csp.backtracking_search(HashMap::new()), Some(expected));
+        assert_eq!(csp.count_solutions(), 1);
+    }
+
+    #[test]
+    fn test_add_variable_rejects_duplicate() {
+        let mut csp = australia_csp();
+
+        assert_eq!(
+            csp.add_variable("Tasmania", vec!["purple"]),
+            Err(CspError::DuplicateVariable("Tasmania"))
+        );
+        assert_eq!(csp.variables().len(), 7);
+        assert_eq!(csp.domain(&"Tasmania").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_csp_with_fx_hasher_solves_australia() {
+        let reference = australia_csp();
+
+        let domains: HashMap<&str, Vec<&str>, FxBuildHasher> = reference
+            .variables()
+            .iter()
+            .map(|variable| (*variable, vec!["red", "green", "blue"]))
+            .collect();
+        let mut csp = CSP::with_hasher(reference.variables().to_vec(), domains);
+
+        for variable in reference.variables() {
+            for constraint in reference.constraints_for(variable) {
+                if constraint.get_variables()[0] == *variable {
+                    csp.add_constraint(constraint.clone());
+                }
+            }
+        }
+
+        assert_eq!(
+            csp.backtracking_search(HashMap::new()),
+            reference.backtracking_search(HashMap::new())
+        );
+        assert_eq!(csp.count_solutions(), 12);
+    }
+
+    #[test]
+    fn test_default_options_match_plain_search() {
+        let csp = trap_coloring_csp();
+
+        assert_eq!(
+            csp.backtracking_search(HashMap::new()),
+            csp.backtracking_search_with_options(HashMap::new(), SearchOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_forward_checking_solves_sudoku() {
+        let givens = [
+            [5, 3, 0, 0, 7, 0, 0, 0, 0],
+            [6, 0, 0, 1, 9, 5, 0, 0, 0],
+            [0, 9, 8, 0, 0, 0, 0, 6, 0],
+            [8, 0, 0, 0, 6, 0, 0, 0, 3],
+            [4, 0, 0, 8, 0, 3, 0, 0, 1],
+            [7, 0, 0, 0, 2, 0, 0, 0, 6],
+            [0, 6, 0, 0, 0, 0, 2, 8, 0],
+            [0, 0, 0, 4, 1, 9, 0, 0, 5],
+            [0, 0, 0, 0, 8, 0, 0, 7, 9],
+        ];
+        let csp = sudoku_csp(givens);
+        let options = SearchOptions {
+            variable_ordering: VariableOrdering::MRV,
+            propagation: Propagation::ForwardChecking,
+            ..SearchOptions::default()
+        };
+
+        let solution = csp
+            .backtracking_search_with_options(HashMap::new(), options)
+            .unwrap();
+
+        assert_eq!(solution[&(0, 2)], 4);
+        assert_eq!(solution[&(8, 0)], 3);
+        for row in 0..9 {
+            let mut digits: Vec<u32> = (0..9).map(|column| solution[&(row, column)]).collect();
+            digits.sort();
+            assert_eq!(digits, (1..=9).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn test_mac_visits_fewer_nodes_than_forward_checking_on_sudoku() {
+        let givens = [
+            [0, 0, 0, 0, 0, 0, 0, 0, 0],
+            [0, 0, 0, 1, 9, 5, 0, 0, 0],
+            [0, 9, 8, 0, 0, 0, 0, 6, 0],
+            [8, 0, 0, 0, 6, 0, 0, 0, 3],
+            [4, 0, 0, 8, 0, 3, 0, 0, 1],
+            [7, 0, 0, 0, 2, 0, 0, 0, 6],
+            [0, 6, 0, 0, 0, 0, 2, 8, 0],
+            [0, 0, 0, 4, 1, 9, 0, 0, 5],
+            [0, 0, 0, 0, 8, 0, 0, 7, 9],
+        ];
+        let csp = sudoku_csp(givens);
+        let search = |propagation| {
+            csp.backtracking_search_with_options_and_stats(
+                HashMap::new(),
+                SearchOptions {
+                    variable_ordering: VariableOrdering::MRV,
+                    propagation,
+                    ..SearchOptions::default()
+                },
+            )
+        };
+
+        let (_, forward_checking) = search(Propagation::ForwardChecking);
+        let (solution, mac) = search(Propagation::Mac);
+        let solution = solution.unwrap();
+
+        for row in 0..9 {
+            let mut digits: Vec<u32> = (0..9).map(|column| solution[&(row, column)]).collect();
+            digits.sort();
+            assert_eq!(digits, (1..=9).collect::<Vec<u32>>());
+        }
+        assert!(mac.nodes_visited < forward_checking.nodes_visited);
+    }
+
+    #[test]
+    fn test_mac_backtracks_on_wipeout_forward_checking_misses() {
+        // B and C can't both differ from A and from each other with two
+        // values, which forward checking only finds out once B is assigned.
+        let variables = vec!["A", "B", "C"];
+        let domains = [("A", vec![1, 2, 3]), ("B", vec![1, 2]), ("C", vec![1, 2])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(vec!["A", "B", "C"])));
+
+        let mut forward_checking = Search::new(
+            &csp,
+            SearchOptions {
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+        );
+        let mut mac = Search::new(
+            &csp,
+            SearchOptions {
+                propagation: Propagation::Mac,
+                ..SearchOptions::default()
+            },
+        );
+
+        assert_eq!(
+            mac.solve(HashMap::new()),
+            forward_checking.solve(HashMap::new())
+        );
+        // A=1 and A=2 are both refuted without descending: root, A=3, B, C.
+        assert_eq!(mac.stats.nodes_visited, 4);
+        assert!(forward_checking.stats.nodes_visited > mac.stats.nodes_visited);
+    }
+
+    /// `A` is assigned first and only clashes with `Y` and `Z`, which come
+    /// after four unconstrained variables, so chronological backtracking
+    /// retries every combination of those before it changes `A`.
+    fn distant_culprit_csp() -> CSP<&'static str, u32> {
+        let variables = vec!["A", "X1", "X2", "X3", "X4", "Y", "Z"];
+        let domains = variables
+            .iter()
+            .map(|&variable| match variable {
+                "A" => (variable, vec![1, 2, 3]),
+                _ => (variable, vec![1, 2]),
+            })
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(vec!["A", "Y", "Z"])));
+
+        csp
+    }
+
+    #[test]
+    fn test_backjumping_skips_variables_not_in_the_conflict() {
+        let csp = distant_culprit_csp();
+
+        let (chronological, plain) = csp.backtracking_search_with_stats(HashMap::new());
+        let (solution, backjumping) = csp.backtracking_search_with_options_and_stats(
+            HashMap::new(),
+            SearchOptions {
+                backjumping: true,
+                ..SearchOptions::default()
+            },
+        );
+
+        assert_eq!(solution, chronological);
+        assert_eq!(solution.unwrap()["A"], 3);
+        assert!(backjumping.backtracks < plain.backtracks);
+        assert!(backjumping.nodes_visited < plain.nodes_visited);
+        // Only Y and Z are retried under A=1 and under A=2.
+        assert_eq!(backjumping.backtracks, 4);
+    }
+
+    /// Checks that backjumping only skips subtrees without solutions, so it
+    /// finds the very solution chronological backtracking finds first.
+    fn assert_backjumping_agrees<V, D, S>(csp: &CSP<V, D, S>)
+    where
+        V: Eq + Hash + Clone + Debug,
+        D: Clone + PartialEq + Debug,
+        S: BuildHasher + Default + Clone,
+    {
+        for options in [
+            SearchOptions::default(),
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                propagation: Propagation::Mac,
+                ..SearchOptions::default()
+            },
+        ] {
+            let backjumping = SearchOptions {
+                backjumping: true,
+                ..options
+            };
+
+            assert_eq!(
+                csp.backtracking_search_with_options(HashMap::new(), backjumping),
+                csp.backtracking_search_with_options(HashMap::new(), options)
+            );
+        }
+    }
+
+    /// Six variables over three values, under four constraints that each
+    /// forbid random combinations of three of them.
+    fn random_ternary_csp(seed: u64) -> CSP<usize, usize> {
+        let mut rng = Rng::new(seed);
+        let variables: Vec<usize> = (0..6).collect();
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec![0, 1, 2]))
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+
+        for _ in 0..4 {
+            let mut scope: Vec<usize> = (0..6).collect();
+            rng.shuffle(&mut scope);
+            scope.truncate(3);
+
+            let forbidden: Vec<Vec<usize>> = (0..9)
+                .map(|_| (0..3).map(|_| rng.below(3)).collect())
+                .collect();
+
+            csp.add_constraint(Arc::new(FunctionConstraint::new(
+                scope.clone(),
+                move |assignment: &HashMap<usize, usize>| {
+                    let values: Option<Vec<usize>> = scope
+                        .iter()
+                        .map(|variable| assignment.get(variable).copied())
+                        .collect();
+
+                    values.is_none_or(|values| !forbidden.contains(&values))
+                },
+            )));
+        }
+
+        csp
+    }
+
+    #[test]
+    fn test_backjumping_finds_the_same_solutions() {
+        assert_backjumping_agrees(&trap_coloring_csp());
+        assert_backjumping_agrees(&australia_csp());
+        assert_backjumping_agrees(&distant_culprit_csp());
+
+        for n in [3, 6, 8] {
+            assert_backjumping_agrees(&queens_csp(n));
+        }
+    }
+
+    #[test]
+    fn test_backjumping_finds_the_same_solutions_under_ternary_constraints() {
+        for seed in 0..1000 {
+            assert_backjumping_agrees(&random_ternary_csp(seed));
+        }
+    }
+
+    #[test]
+    fn test_is_satisfied_accepts_valid_assignment() {
+        let csp = australia_csp();
+        let solution = csp.backtracking_search(HashMap::new()).unwrap();
+
+        assert!(csp.is_satisfied(&solution));
+        assert!(csp.is_satisfied(&HashMap::new()));
+
+        let partial: HashMap<&str, &str> = [
+            ("Western Australia", "red"),
+            ("Northern Territory", "green"),
+        ]
+        .into_iter()
+        .collect();
+        assert!(csp.is_satisfied(&partial));
+    }
+
+    /// A value that can be compared but not cloned.
+    #[derive(Debug, PartialEq)]
+    struct Token(u32);
+
+    #[test]
+    fn test_non_clone_values_can_be_built_and_checked() {
+        let domains = HashMap::from([("A", vec![Token(1), Token(2)]), ("B", vec![Token(2)])]);
+        let mut csp = CSP::try_new(vec!["A", "B"], domains).unwrap();
+
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+        csp.restrict_domain(&"A", &[Token(2)]).unwrap();
+
+        assert_eq!(csp.domain(&"A"), Some(&[Token(2)][..]));
+        assert_eq!(csp.constraints_for(&"B").len(), 1);
+        assert!(!csp.is_satisfied(&HashMap::from([("A", Token(2)), ("B", Token(2))])));
+        assert!(csp.is_satisfied(&HashMap::from([("B", Token(2))])));
+        assert_eq!(
+            format!("{:?}", csp),
+            r#"CSP { domain_sizes: [("A", 1), ("B", 1)], constraints: 1, soft_constraints: 0 }"#
+        );
+    }
+
+    #[test]
+    fn test_is_satisfied_rejects_single_violation() {
+        let csp = australia_csp();
+        let mut assignment = csp.backtracking_search(HashMap::new()).unwrap();
+        let western_australia = assignment["Western Australia"];
+
+        assignment.insert("Northern Territory", western_australia);
+
+        assert!(!csp.is_satisfied(&assignment));
+    }
+
+    #[test]
+    fn test_every_constraint_is_registered_under_each_of_its_variables() {
+        for csp in [trap_coloring_csp(), australia_csp()] {
+            for constraint in csp.all_constraints() {
+                for variable in constraint.get_variables() {
+                    assert!(csp
+                        .constraints_for(variable)
+                        .iter()
+                        .any(|registered| Arc::ptr_eq(registered, &constraint)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_incremental_check_matches_full_check() {
+        let full_check = SearchOptions {
+            check_all_constraints: true,
+            ..SearchOptions::default()
+        };
+
+        for csp in [trap_coloring_csp(), australia_csp()] {
+            let solutions: Vec<_> = csp.solutions().collect();
+            assert!(solutions.iter().all(|solution| csp.is_satisfied(solution)));
+
+            assert_eq!(
+                csp.backtracking_search_with_options(HashMap::new(), full_check),
+                csp.backtracking_search(HashMap::new())
+            );
+        }
+
+        let csp = queens_csp(8);
+        assert_eq!(
+            csp.backtracking_search_with_options(HashMap::new(), full_check),
+            csp.backtracking_search(HashMap::new())
+        );
+    }
+
+    #[test]
+    fn test_full_check_rejects_inconsistent_initial_assignment() {
+        let variables = vec!["A", "B", "C"];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec![1, 2]))
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        let initial: HashMap<&str, i32> = [("A", 1), ("B", 1)].into_iter().collect();
+
+        // Only C's constraints are checked when C is assigned, but the seed
+        // is checked as a whole before the search starts.
+        assert_eq!(csp.backtracking_search(initial.clone()), None);
+
+        let full_check = SearchOptions {
+            check_all_constraints: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            csp.backtracking_search_with_options(initial, full_check),
+            None
+        );
+    }
+
+    #[test]
+    fn test_all_different_supports_matches_satisfied() {
+        let constraint = AllDifferentConstraint::new(vec!["A", "B", "C"]);
+
+        for (a, b) in [("A", "B"), ("A", "C"), ("B", "A"), ("A", "A"), ("A", "D")] {
+            for va in 1..=2 {
+                for vb in 1..=2 {
+                    let assignment: HashMap<&str, i32> = [(a, va), (b, vb)].into_iter().collect();
+
+                    assert_eq!(
+                        constraint.supports(&a, &va, &b, &vb),
+                        constraint.satisfied(&assignment)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cloned_csp_finds_the_same_solution() {
+        let csp = australia_csp();
+        let mut clone = csp.clone();
+
+        assert_eq!(
+            clone.backtracking_search(HashMap::new()),
+            csp.backtracking_search(HashMap::new())
+        );
+
+        clone.set_domain(&"Tasmania", vec!["red"]).unwrap();
+        assert_eq!(csp.domain(&"Tasmania").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_debug_shows_domain_sizes_and_constraint_count() {
+        let variables = vec!["A", "B"];
+        let domains = [("A", vec![1, 2]), ("B", vec![1, 2, 3])]
+            .into_iter()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec!["A", "B"])));
+
+        assert_eq!(
+            format!("{:?}", csp),
+            r#"CSP { domain_sizes: [("A", 2), ("B", 3)], constraints: 1, soft_constraints: 0 }"#
+        );
+    }
+
+    #[test]
+    fn test_iterative_search_matches_recursive_search() {
+        let all_options = [
+            SearchOptions::default(),
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                value_ordering: ValueOrdering::LeastConstraining,
+                propagation: Propagation::None,
+                backjumping: false,
+                check_all_constraints: false,
+            },
+            SearchOptions {
+                variable_ordering: VariableOrdering::MRV,
+                propagation: Propagation::ForwardChecking,
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                propagation: Propagation::Mac,
+                ..SearchOptions::default()
+            },
+        ];
+
+        for csp in [
+            trap_coloring_csp(),
+            australia_csp(),
+            two_color_triangle_csp(),
+        ] {
+            for options in all_options {
+                let mut recursive = Search::new(&csp, options);
+                let mut iterative = Search::new(&csp, options);
+
+                assert_eq!(
+                    iterative.solve_iter(HashMap::new()),
+                    recursive.solve(HashMap::new())
+                );
+                assert_eq!(iterative.stats.nodes_visited, recursive.stats.nodes_visited);
+                assert_eq!(iterative.stats.backtracks, recursive.stats.backtracks);
+            }
+        }
+
+        let csp = queens_csp(8);
+        assert_eq!(
+            csp.backtracking_search_iter(HashMap::new()),
+            csp.backtracking_search(HashMap::new())
+        );
+    }
+
+    #[test]
+    fn test_iterative_search_handles_thousands_of_variables() {
+        let variables: Vec<usize> = (0..5000).collect();
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec![0, 1]))
+            .collect();
+
+        let mut csp = CSP::new(variables, domains);
+        for variable in 1..5000 {
+            csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![
+                variable - 1,
+                variable,
+            ])));
+        }
+
+        let solution = csp.backtracking_search_iter(HashMap::new()).unwrap();
+
+        assert_eq!(solution.len(), 5000);
+        assert!((0..5000).all(|variable| solution[&variable] == variable % 2));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_backtracking_search_colors_australia() {
+        let csp = australia_csp();
+
+        let solution = csp.par_backtracking_search().unwrap();
+
+        assert_eq!(solution.len(), 7);
+        assert!(csp.variables().iter().all(|variable| csp
+            .constraints_for(variable)
+            .iter()
+            .all(|constraint| constraint.satisfied(&solution))));
+        assert!(csp.backtracking_search(HashMap::new()).is_some());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_backtracking_search_reports_unsatisfiable() {
+        let mut csp = two_color_triangle_csp();
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(vec!["A", "B", "C"])));
+
+        assert_eq!(csp.par_backtracking_search(), None);
+        assert_eq!(csp.backtracking_search(HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_for_each_stops_after_three_solutions() {
+        let csp = all_different_csp(4);
+        let mut first_three = vec![];
+
+        csp.backtracking_search_for_each(|solution| {
+            first_three.push(solution.clone());
+
+            if first_three.len() == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        assert_eq!(first_three, csp.solutions().take(3).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_less_than_constraint() {
+        let constraint = LessThanConstraint::new("A", "B");
+        let assignment = |a, b| -> HashMap<&str, i32> { [("A", a), ("B", b)].into() };
+
+        assert!(constraint.satisfied(&assignment(1, 2)));
+        assert!(!constraint.satisfied(&assignment(2, 2)));
+        assert!(!constraint.satisfied(&assignment(3, 2)));
+        assert!(constraint.satisfied(&HashMap::from([("A", 3)])));
+    }
+
+    #[test]
+    fn test_distance_constraint() {
+        let constraint = DistanceConstraint::new("A", "B", 2);
+        let assignment = |a, b| -> HashMap<&str, u32> { [("A", a), ("B", b)].into() };
+
+        let pairs: Vec<(u32, u32)> = (1..=5)
+            .flat_map(|a| (1..=5).map(move |b| (a, b)))
+            .filter(|&(a, b)| constraint.satisfied(&assignment(a, b)))
+            .collect();
+
+        assert_eq!(pairs, vec![(1, 3), (2, 4), (3, 1), (3, 5), (4, 2), (5, 3)]);
+        assert!(constraint.satisfied(&HashMap::from([("A", 1)])));
+        assert!(constraint.satisfied(&HashMap::from([("B", 5)])));
+    }
+
+    #[test]
+    fn test_next_to_constraint() {
+        let constraint = NextToConstraint::new("A", "B");
+        let assignment = |a, b| -> HashMap<&str, u32> { [("A", a), ("B", b)].into() };
+
+        for a in 1..=5 {
+            for b in 1..=5 {
+                assert_eq!(constraint.satisfied(&assignment(a, b)), a.abs_diff(b) == 1);
+            }
+        }
+        assert!(constraint.satisfied(&HashMap::from([("A", 3)])));
+    }
+
+    #[test]
+    fn test_sum_constraint_waits_for_every_variable() {
+        let constraint = SumConstraint::new(vec!["A", "B", "C"], 6);
+
+        assert!(constraint.satisfied(&HashMap::from([("A", 1), ("B", 2), ("C", 3)])));
+        assert!(!constraint.satisfied(&HashMap::from([("A", 2), ("B", 2), ("C", 3)])));
+        assert!(constraint.satisfied(&HashMap::from([("A", 5), ("B", 5)])));
+    }
+
+    /// x + 2y - z with every variable in 0..=3, so ranging over -3..=9.
+    fn linear_constraint(relation: Relation, target: i64) -> LinearConstraint<&'static str> {
+        LinearConstraint::new(vec![(1, "x"), (2, "y"), (-1, "z")], relation, target, 0..=3)
+    }
+
+    #[test]
+    fn test_linear_constraint_less_or_equal() {
+        let constraint = linear_constraint(Relation::LessOrEqual, 2);
+
+        assert!(constraint.satisfied(&HashMap::from([("x", 1), ("y", 1), ("z", 1)])));
+        assert!(!constraint.satisfied(&HashMap::from([("x", 3), ("y", 1), ("z", 1)])));
+        // z can still take off 3, but x and y already add 5.
+        assert!(constraint.satisfied(&HashMap::from([("x", 3), ("y", 1)])));
+        assert!(!constraint.satisfied(&HashMap::from([("x", 3), ("y", 2)])));
+    }
+
+    #[test]
+    fn test_linear_constraint_greater_or_equal() {
+        let constraint = linear_constraint(Relation::GreaterOrEqual, 7);
+
+        assert!(constraint.satisfied(&HashMap::from([("x", 3), ("y", 2), ("z", 0)])));
+        assert!(!constraint.satisfied(&HashMap::from([("x", 3), ("y", 2), ("z", 1)])));
+        // y can still add 6, but z already takes off 3.
+        assert!(constraint.satisfied(&HashMap::from([("x", 3), ("z", 2)])));
+        assert!(!constraint.satisfied(&HashMap::from([("x", 3), ("z", 3)])));
+    }
+
+    #[test]
+    fn test_linear_constraint_equal() {
+        let constraint = linear_constraint(Relation::Equal, 4);
+
+        assert!(constraint.satisfied(&HashMap::from([("x", 2), ("y", 2), ("z", 2)])));
+        assert!(!constraint.satisfied(&HashMap::from([("x", 2), ("y", 2), ("z", 1)])));
+        // x - z only ranges over -3..=3, too little without any y and too
+        // much with y at 3 and x at 3.
+        assert!(!constraint.satisfied(&HashMap::from([("y", 0)])));
+        assert!(!constraint.satisfied(&HashMap::from([("x", 3), ("y", 3)])));
+        assert!(constraint.satisfied(&HashMap::from([("y", 1)])));
+
+        let domains = ["x", "y", "z"]
+            .into_iter()
+            .map(|variable| (variable, vec![0, 1, 2, 3]))
+            .collect();
+        let mut csp: CSP<&str, i64> = CSP::new(vec!["x", "y", "z"], domains);
+        csp.add_constraint(Arc::new(constraint));
+
+        // x - z = 4 - 2y has 2 solutions for y = 1, 4 for y = 2 and 2 for
+        // y = 3.
+        assert_eq!(csp.count_solutions(), 8);
+    }
+
+    #[test]
+    fn test_table_constraint() {
+        let constraint = TableConstraint::new(vec!["A", "B"], vec![vec![1, 2], vec![2, 1]]);
+
+        assert!(constraint.satisfied(&HashMap::from([("A", 1), ("B", 2)])));
+        assert!(constraint.satisfied(&HashMap::from([("A", 2), ("B", 1)])));
+        assert!(!constraint.satisfied(&HashMap::from([("A", 1), ("B", 1)])));
+        assert!(constraint.satisfied(&HashMap::from([("B", 1)])));
+        assert!(!constraint.satisfied(&HashMap::from([("A", 3)])));
+        assert!(constraint.satisfied(&HashMap::new()));
+    }
+
+    #[test]
+    fn test_table_constraint_drives_search() {
+        let domains = [("A", vec![1, 2, 3]), ("B", vec![1, 2, 3])].into();
+        let mut csp = CSP::new(vec!["A", "B"], domains);
+        csp.add_constraint(Arc::new(TableConstraint::new(
+            vec!["A", "B"],
+            vec![vec![1, 2], vec![2, 1]],
+        )));
+
+        assert_eq!(csp.count_solutions(), 2);
+        assert_eq!(
+            csp.backtracking_search(HashMap::new()),
+            Some(HashMap::from([("A", 1), ("B", 2)]))
+        );
+    }
+
+    fn four_flags_csp() -> CSP<i32, bool> {
+        let variables = vec![0, 1, 2, 3];
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, vec![false, true]))
+            .collect();
+
+        CSP::new(variables, domains)
+    }
+
+    #[test]
+    fn test_element_constraint() {
+        let constraint = ElementConstraint::new("index", vec![10, 20, 30], "value");
+
+        assert!(constraint.satisfied(&HashMap::from([("index", 1), ("value", 20)])));
+        assert!(!constraint.satisfied(&HashMap::from([("index", 1), ("value", 30)])));
+        assert!(!constraint.satisfied(&HashMap::from([("index", 3), ("value", 30)])));
+        assert!(!constraint.satisfied(&HashMap::from([("index", -1), ("value", 10)])));
+        assert!(constraint.satisfied(&HashMap::from([("index", 7)])));
+
+        let domains = [("index", vec![0, 1, 2, 3]), ("value", vec![10, 30, 40])]
+            .into_iter()
+            .collect();
+        let mut csp = CSP::new(vec!["index", "value"], domains);
+        csp.add_constraint(Arc::new(constraint));
+
+        assert_eq!(csp.count_solutions(), 2);
+    }
+
+    #[test]
+    fn test_at_most_k_constraint() {
+        let mut csp = four_flags_csp();
+        let constraint = AtMostKConstraint::new(vec![0, 1, 2, 3], true, 1);
+
+        assert!(constraint.satisfied(&HashMap::from([(0, true), (1, false)])));
+        assert!(!constraint.satisfied(&HashMap::from([(0, true), (1, true)])));
+
+        csp.add_constraint(Arc::new(constraint));
+        assert_eq!(csp.count_solutions(), 5);
+    }
+
+    #[test]
+    fn test_at_least_k_constraint() {
+        let mut csp = four_flags_csp();
+        let constraint = AtLeastKConstraint::new(vec![0, 1, 2, 3], true, 3);
+
+        assert!(constraint.satisfied(&HashMap::from([(0, false)])));
+        assert!(!constraint.satisfied(&HashMap::from([(0, false), (1, false)])));
+
+        csp.add_constraint(Arc::new(constraint));
+        assert_eq!(csp.count_solutions(), 5);
+    }
+
+    #[test]
+    fn test_exactly_k_constraint() {
+        let mut csp = four_flags_csp();
+        let constraint = ExactlyKConstraint::new(vec![0, 1, 2, 3], true, 2);
+
+        assert!(constraint.satisfied(&HashMap::from([(0, true), (1, true)])));
+        assert!(!constraint.satisfied(&HashMap::from([(0, true), (1, true), (2, true)])));
+        assert!(!constraint.satisfied(&HashMap::from([(0, false), (1, false), (2, false)])));
+
+        csp.add_constraint(Arc::new(constraint));
+        assert_eq!(csp.count_solutions(), 6);
+    }
+
+    #[test]
+    fn test_global_cardinality_constraint() {
+        let colors = vec!["A", "B", "C", "D"];
+        let constraint =
+            GlobalCardinalityConstraint::new(colors.clone(), vec![("red", 2, 3), ("blue", 0, 1)]);
+        let assignment = |values: &[&'static str]| -> HashMap<&str, &str> {
+            colors.iter().copied().zip(values.iter().copied()).collect()
+        };
+
+        assert!(constraint.satisfied(&assignment(&["red", "green"])));
+        assert!(constraint.satisfied(&assignment(&["red", "red", "blue", "green"])));
+        // Too many reds, then too many blues.
+        assert!(!constraint.satisfied(&assignment(&["red", "red", "red", "red"])));
+        assert!(!constraint.satisfied(&assignment(&["blue", "blue"])));
+        // The last variable can still bring one red up to two, but not none.
+        assert!(constraint.satisfied(&assignment(&["red", "green", "blue"])));
+        assert!(!constraint.satisfied(&assignment(&["green", "blue", "green"])));
+
+        let domains = colors
+            .iter()
+            .map(|color| (*color, vec!["red", "green", "blue"]))
+            .collect();
+        let mut csp = CSP::new(colors.clone(), domains);
+        csp.add_constraint(Arc::new(constraint));
+
+        // Two reds, the other two both green or one of them blue: 6 * 3,
+        // plus three reds: 4 * 2.
+        assert_eq!(csp.count_solutions(), 26);
+    }
+
+    #[test]
+    fn test_ordering_constraint_waits_for_both_neighbors() {
+        let constraint = OrderingConstraint::new(vec!["A", "B", "C"]);
+
+        assert!(constraint.satisfied(&HashMap::from([("A", 1), ("B", 2), ("C", 3)])));
+        assert!(!constraint.satisfied(&HashMap::from([("A", 1), ("B", 3), ("C", 2)])));
+        assert!(constraint.satisfied(&HashMap::from([("A", 3), ("C", 2)])));
+    }
+
+    #[test]
+    fn test_ordering_constraint_removes_mirrored_queens() {
+        let mut csp = queens_csp(4);
+        assert_eq!(csp.count_solutions(), 2);
+
+        // Flipping the board top to bottom maps each solution to the other,
+        // and swaps which of the outer queens stands higher.
+        csp.add_constraint(Arc::new(OrderingConstraint::new(vec![0, 3])));
+
+        assert_eq!(
+            csp.all_solutions(),
+            vec![HashMap::from([(0, 1), (1, 3), (2, 0), (3, 2)])]
+        );
+    }
+
+    #[test]
+    fn test_value_precedence_constraint() {
+        let constraint = ValuePrecedenceConstraint::new(vec!["A", "B", "C"], vec![1, 2, 3]);
+
+        assert!(constraint.satisfied(&HashMap::from([("A", 1), ("B", 2), ("C", 1)])));
+        assert!(!constraint.satisfied(&HashMap::from([("A", 1), ("B", 3)])));
+        assert!(!constraint.satisfied(&HashMap::from([("A", 2)])));
+        assert!(constraint.satisfied(&HashMap::from([("B", 3), ("C", 2)])));
+    }
+
+    #[test]
+    fn test_break_value_symmetry_keeps_one_coloring_per_permutation() {
+        let mut csp = australia_csp();
+        assert_eq!(csp.count_solutions(), 12);
+
+        csp.break_value_symmetry(&["red", "green", "blue"]);
+
+        // The mainland has a single coloring up to renaming the colors, and
+        // Tasmania can take either color that Victoria doesn't have.
+        assert_eq!(csp.count_solutions(), 2);
+        assert!(csp
+            .all_solutions()
+            .iter()
+            .all(|solution| solution["Western Australia"] == "red"));
+    }
+
+    #[test]
+    fn test_break_variable_symmetry() {
+        let domains = [("A", vec![1, 2, 3]), ("B", vec![1, 2, 3])].into();
+        let mut csp = CSP::new(vec!["A", "B"], domains);
+        assert_eq!(csp.count_solutions(), 9);
+
+        csp.break_variable_symmetry(&["A", "B"]);
+
+        assert_eq!(csp.count_solutions(), 6);
+    }
+
+    #[test]
+    fn test_explain_unsat_finds_the_clique() {
+        let regions = vec!["A", "B", "C", "D", "E"];
+        let domains = regions
+            .iter()
+            .map(|region| (*region, vec!["red", "green", "blue"]))
+            .collect();
+        let mut csp = CSP::new(regions, domains);
+
+        let clique = [
+            ("A", "B"),
+            ("A", "C"),
+            ("A", "D"),
+            ("B", "C"),
+            ("B", "D"),
+            ("C", "D"),
+        ];
+        for (region1, region2) in clique.iter().chain(&[("A", "E"), ("D", "E")]) {
+            csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![*region1, *region2])));
+        }
+
+        let core = csp.explain_unsat().unwrap();
+
+        let mut edges: Vec<(&str, &str)> = core
+            .iter()
+            .map(|constraint| (constraint.get_variables()[0], constraint.get_variables()[1]))
+            .collect();
+        edges.sort();
+        assert_eq!(edges, clique);
+    }
+
+    #[test]
+    fn test_explain_unsat_of_satisfiable_csp() {
+        assert!(australia_csp().explain_unsat().is_none());
+    }
+
+    #[test]
+    fn test_degree_breaks_mrv_ties_with_fewer_nodes() {
+        let regions: Vec<i32> = (0..8).collect();
+        let domains = regions
+            .iter()
+            .map(|region| (*region, vec!["red", "green", "blue"]))
+            .collect();
+        let mut csp = CSP::new(regions, domains);
+
+        for (region1, region2) in [
+            (0, 1),
+            (0, 7),
+            (1, 2),
+            (1, 5),
+            (2, 4),
+            (2, 6),
+            (3, 6),
+            (4, 5),
+            (4, 6),
+            (4, 7),
+            (5, 7),
+            (6, 7),
+        ] {
+            csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![region1, region2])));
+        }
+
+        let search_with = |variable_ordering| {
+            let mut search = Search::new(
+                &csp,
+                SearchOptions {
+                    variable_ordering,
+                    ..SearchOptions::default()
+                },
+            );
+            let solution = search.solve(HashMap::new());
+            assert!(solution.is_some());
+
+            search.stats
+        };
+
+        // Every region starts with three colors, so pure MRV starts from
+        // region 0 and ends up backtracking; the degree heuristic starts from
+        // the busiest regions and never has to.
+        let mrv = search_with(VariableOrdering::MRV);
+        let degree = search_with(VariableOrdering::MrvThenDegree);
+
+        assert!(mrv.backtracks > 0);
+        assert_eq!(degree.backtracks, 0);
+        assert!(degree.nodes_visited < mrv.nodes_visited);
+    }
+
+    #[test]
+    fn test_random_value_ordering_is_reproducible() {
+        let csp = all_different_csp(6);
+        let solve_with_seed = |seed| {
+            let options = SearchOptions {
+                value_ordering: ValueOrdering::Random(seed),
+                ..SearchOptions::default()
+            };
+            let mut search = Search::new(&csp, options);
+            let solution = search.solve(HashMap::new()).unwrap();
+
+            (solution, search.stats.nodes_visited)
+        };
+
+        assert_eq!(solve_with_seed(42), solve_with_seed(42));
+
+        let mut solutions: Vec<HashMap<i32, i32>> = vec![];
+        for seed in 0..5 {
+            let (solution, _) = solve_with_seed(seed);
+            if !solutions.contains(&solution) {
+                solutions.push(solution);
+            }
+        }
+        assert!(solutions.len() > 1);
     }
 }
@@ -0,0 +1,126 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{CspError, SharedConstraint, CSP};
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+/// The plain-data part of a CSP: its variables and their domains.
+///
+/// Constraints are trait objects and can't be saved along with it, so a
+/// definition is turned back into a `CSP` with `into_csp`, which takes the
+/// constraints to reattach. This is the shape to serialize when storing or
+/// exchanging problems, which the `serde` feature derives.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CspDefinition<V: Eq + Hash, D> {
+    pub variables: Vec<V>,
+    pub domains: HashMap<V, Vec<D>>,
+}
+
+impl<V: Eq + PartialEq + Hash + Clone, D: Clone + PartialEq> CspDefinition<V, D> {
+    pub fn new(variables: Vec<V>, domains: HashMap<V, Vec<D>>) -> Self {
+        CspDefinition { variables, domains }
+    }
+
+    /// Builds the CSP, adding `constraints` to it. Fails like `CSP::try_new`
+    /// and `CSP::try_add_constraint` when a variable has no domain or a
+    /// constraint names an unknown variable.
+    pub fn into_csp(
+        self,
+        constraints: impl IntoIterator<Item = SharedConstraint<V, D>>,
+    ) -> Result<CSP<V, D>, CspError<V>> {
+        let mut csp = CSP::try_new(self.variables, self.domains)?;
+
+        for constraint in constraints {
+            csp.try_add_constraint(constraint)?;
+        }
+
+        Ok(csp)
+    }
+}
+
+impl<V, D, S> From<&CSP<V, D, S>> for CspDefinition<V, D>
+where
+    V: Eq + PartialEq + Hash + Clone,
+    D: Clone + PartialEq,
+    S: BuildHasher + Default + Clone,
+{
+    fn from(csp: &CSP<V, D, S>) -> Self {
+        let domains = csp
+            .variables()
+            .iter()
+            .map(|variable| (variable.clone(), csp.domain(variable).unwrap().to_vec()))
+            .collect();
+
+        CspDefinition::new(csp.variables().to_vec(), domains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch3_constraint_satisfaction_problems::csp::NotEqualConstraint;
+    #[cfg(feature = "serde")]
+    use crate::ch3_constraint_satisfaction_problems::map_coloring::australia_csp;
+    use std::sync::Arc;
+
+    fn borders() -> Vec<SharedConstraint<&'static str, &'static str>> {
+        [
+            ("Western Australia", "Northern Territory"),
+            ("Western Australia", "South Australia"),
+            ("Northern Territory", "South Australia"),
+            ("South Australia", "Tasmania"),
+        ]
+        .into_iter()
+        .map(|(place1, place2)| -> SharedConstraint<_, _> {
+            Arc::new(NotEqualConstraint::new(vec![place1, place2]))
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_definition_round_trips_a_csp() {
+        let places = vec![
+            "Western Australia",
+            "Northern Territory",
+            "South Australia",
+            "Tasmania",
+        ];
+        let domains = places
+            .iter()
+            .map(|place| (*place, vec!["red", "green", "blue"]))
+            .collect();
+        let definition = CspDefinition::new(places, domains);
+
+        let csp = definition.clone().into_csp(borders()).unwrap();
+
+        assert_eq!(CspDefinition::from(&csp), definition);
+        assert_eq!(csp.count_solutions(), 12);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_definition_round_trips_through_json() {
+        let csp = australia_csp();
+        let definition = CspDefinition::from(&csp);
+
+        let json = serde_json::to_string(&definition).unwrap();
+        let parsed: CspDefinition<&str, &str> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, definition);
+        assert_eq!(parsed.variables, csp.variables());
+    }
+
+    #[test]
+    fn test_into_csp_rejects_unknown_variables() {
+        let definition = CspDefinition::new(
+            vec!["Western Australia"],
+            [("Western Australia", vec!["red"])].into_iter().collect(),
+        );
+
+        assert_eq!(
+            definition.into_csp(borders()).err(),
+            Some(CspError::UnknownVariable("Northern Territory"))
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Constraint, NextToConstraint, Propagation, SearchOptions,
+    VariableOrdering, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// The five kinds of things the houses of the puzzle differ in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    Nationality,
+    Color,
+    Pet,
+    Drink,
+    Smoke,
+}
+
+impl Attribute {
+    pub const ALL: [Attribute; 5] = [
+        Attribute::Nationality,
+        Attribute::Color,
+        Attribute::Pet,
+        Attribute::Drink,
+        Attribute::Smoke,
+    ];
+
+    /// The five values of the attribute, one per house.
+    pub fn values(self) -> [&'static str; 5] {
+        match self {
+            Attribute::Nationality => [
+                "Englishman",
+                "Spaniard",
+                "Ukrainian",
+                "Norwegian",
+                "Japanese",
+            ],
+            Attribute::Color => ["red", "green", "ivory", "yellow", "blue"],
+            Attribute::Pet => ["dog", "snails", "fox", "horse", "zebra"],
+            Attribute::Drink => ["coffee", "tea", "milk", "orange juice", "water"],
+            Attribute::Smoke => [
+                "Old Gold",
+                "Kools",
+                "Chesterfield",
+                "Lucky Strike",
+                "Parliament",
+            ],
+        }
+    }
+}
+
+/// The two values belong to the same house.
+#[derive(Clone, Debug)]
+pub struct SameHouseConstraint {
+    values: Vec<&'static str>,
+}
+
+impl SameHouseConstraint {
+    pub fn new(value1: &'static str, value2: &'static str) -> Self {
+        Self {
+            values: vec![value1, value2],
+        }
+    }
+}
+
+impl Constraint<&'static str, u32> for SameHouseConstraint {
+    fn get_variables(&self) -> &Vec<&'static str> {
+        &self.values
+    }
+
+    fn satisfied(&self, assignment: &HashMap<&'static str, u32>) -> bool {
+        match (
+            assignment.get(self.values[0]),
+            assignment.get(self.values[1]),
+        ) {
+            (Some(house1), Some(house2)) => house1 == house2,
+            _ => true,
+        }
+    }
+}
+
+/// The house of the second value is `offset` houses to the right of the
+/// house of the first one (to the left for a negative `offset`).
+#[derive(Clone, Debug)]
+pub struct OffsetConstraint {
+    values: Vec<&'static str>,
+    offset: i64,
+}
+
+impl OffsetConstraint {
+    pub fn new(value1: &'static str, value2: &'static str, offset: i64) -> Self {
+        Self {
+            values: vec![value1, value2],
+            offset,
+        }
+    }
+}
+
+impl Constraint<&'static str, u32> for OffsetConstraint {
+    fn get_variables(&self) -> &Vec<&'static str> {
+        &self.values
+    }
+
+    fn satisfied(&self, assignment: &HashMap<&'static str, u32>) -> bool {
+        match (
+            assignment.get(self.values[0]),
+            assignment.get(self.values[1]),
+        ) {
+            (Some(house1), Some(house2)) => i64::from(*house2) - i64::from(*house1) == self.offset,
+            _ => true,
+        }
+    }
+}
+
+/// The CSP of the zebra puzzle: every value of every attribute is a
+/// variable, whose value is the number (1 to 5, from the left) of the house
+/// it belongs to.
+fn einstein_csp() -> CSP<&'static str, u32> {
+    let variables: Vec<&'static str> = Attribute::ALL
+        .iter()
+        .flat_map(|attribute| attribute.values())
+        .collect();
+    let mut domains: HashMap<&'static str, Vec<u32>> = variables
+        .iter()
+        .map(|variable| (*variable, (1..=5).collect()))
+        .collect();
+
+    // The milk is drunk in the middle house; the Norwegian lives in the first.
+    domains.insert("milk", vec![3]);
+    domains.insert("Norwegian", vec![1]);
+
+    let mut csp = CSP::new(variables, domains);
+
+    for attribute in Attribute::ALL {
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(
+            attribute.values().to_vec(),
+        )));
+    }
+
+    let same_house = [
+        ("Englishman", "red"),
+        ("Spaniard", "dog"),
+        ("coffee", "green"),
+        ("Ukrainian", "tea"),
+        ("Old Gold", "snails"),
+        ("Kools", "yellow"),
+        ("Lucky Strike", "orange juice"),
+        ("Japanese", "Parliament"),
+    ];
+    for (value1, value2) in same_house {
+        csp.add_constraint(Arc::new(SameHouseConstraint::new(value1, value2)));
+    }
+
+    // The green house is immediately to the right of the ivory house.
+    csp.add_constraint(Arc::new(OffsetConstraint::new("ivory", "green", 1)));
+
+    let next_to = [
+        ("Chesterfield", "fox"),
+        ("Kools", "horse"),
+        ("Norwegian", "blue"),
+    ];
+    for (value1, value2) in next_to {
+        csp.add_constraint(Arc::new(NextToConstraint::new(value1, value2)));
+    }
+
+    csp
+}
+
+/// Solves the zebra puzzle, returning for each attribute its values in house
+/// order, from left to right.
+pub fn solve() -> HashMap<Attribute, [&'static str; 5]> {
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = einstein_csp()
+        .backtracking_search_with_options(HashMap::new(), options)
+        .expect("the zebra puzzle has a solution");
+
+    Attribute::ALL
+        .iter()
+        .map(|attribute| {
+            let mut houses = [""; 5];
+            for value in attribute.values() {
+                houses[solution[value] as usize - 1] = value;
+            }
+
+            (*attribute, houses)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_einstein_puzzle() {
+        let houses = solve();
+
+        let owner = |attribute: Attribute, value| {
+            let house = houses[&attribute].iter().position(|v| *v == value).unwrap();
+            houses[&Attribute::Nationality][house]
+        };
+
+        assert_eq!(owner(Attribute::Pet, "zebra"), "Japanese");
+        assert_eq!(owner(Attribute::Drink, "water"), "Norwegian");
+        assert_eq!(
+            houses[&Attribute::Color],
+            ["yellow", "blue", "red", "ivory", "green"]
+        );
+    }
+
+    #[test]
+    fn test_puzzle_has_a_unique_solution() {
+        assert_eq!(einstein_csp().count_solutions(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+/// A set of indices into a variable's domain, stored as a bitset.
+///
+/// The solvers use it to track which of a variable's values are still
+/// possible: membership and removal are single bit operations, and the value
+/// itself is looked up by index in the variable's domain only when needed.
+/// The first 64 indices live inline, so cloning the small domains of typical
+/// puzzles (colors, digits) doesn't allocate; larger domains spill into extra
+/// words.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FiniteDomain {
+    low: u64,
+    high: Vec<u64>,
+}
+
+impl FiniteDomain {
+    /// The domain holding every index in `0..size`.
+    pub fn full(size: usize) -> Self {
+        let mut domain = FiniteDomain::default();
+
+        for word_index in 0..size.div_ceil(64) {
+            let bits = (size - word_index * 64).min(64);
+            let word = if bits == 64 {
+                u64::MAX
+            } else {
+                (1 << bits) - 1
+            };
+
+            *domain.word_mut(word_index) = word;
+        }
+
+        domain
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.word(index / 64) & (1 << (index % 64)) != 0
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        *self.word_mut(index / 64) |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index / 64 <= self.high.len() {
+            *self.word_mut(index / 64) &= !(1 << (index % 64));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.low.count_ones() as usize
+            + self
+                .high
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.low == 0 && self.high.iter().all(|word| *word == 0)
+    }
+
+    /// The indices in the domain, in increasing order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            domain: self,
+            word_index: 0,
+            word: self.low,
+        }
+    }
+
+    /// Removes every index for which `keep` returns `false`.
+    pub fn retain(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        for word_index in 0..=self.high.len() {
+            let mut word = self.word(word_index);
+            let mut remaining = word;
+
+            while remaining != 0 {
+                let bit = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+
+                if !keep(word_index * 64 + bit) {
+                    word &= !(1 << bit);
+                }
+            }
+
+            *self.word_mut(word_index) = word;
+        }
+    }
+
+    fn word(&self, word_index: usize) -> u64 {
+        match word_index {
+            0 => self.low,
+            _ => self.high.get(word_index - 1).copied().unwrap_or(0),
+        }
+    }
+
+    fn word_mut(&mut self, word_index: usize) -> &mut u64 {
+        if word_index == 0 {
+            return &mut self.low;
+        }
+
+        if self.high.len() < word_index {
+            self.high.resize(word_index, 0);
+        }
+
+        &mut self.high[word_index - 1]
+    }
+}
+
+/// Iterator over the indices of a [`FiniteDomain`].
+pub struct Iter<'a> {
+    domain: &'a FiniteDomain,
+    word_index: usize,
+    word: u64,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            if self.word_index == self.domain.high.len() {
+                return None;
+            }
+
+            self.word_index += 1;
+            self.word = self.domain.word(self.word_index);
+        }
+
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+
+        Some(self.word_index * 64 + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_domain() {
+        let domain = FiniteDomain::full(9);
+
+        assert_eq!(domain.len(), 9);
+        assert!(domain.contains(0));
+        assert!(domain.contains(8));
+        assert!(!domain.contains(9));
+        assert!(!domain.contains(200));
+        assert_eq!(
+            domain.iter().collect::<Vec<_>>(),
+            (0..9).collect::<Vec<_>>()
+        );
+
+        assert!(FiniteDomain::full(0).is_empty());
+        assert_eq!(FiniteDomain::full(64).len(), 64);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut domain = FiniteDomain::full(5);
+
+        domain.remove(1);
+        domain.remove(3);
+        domain.remove(300);
+        assert_eq!(domain.iter().collect::<Vec<_>>(), vec![0, 2, 4]);
+
+        domain.insert(3);
+        assert!(domain.contains(3));
+        assert_eq!(domain.len(), 4);
+
+        for index in [0, 2, 3, 4] {
+            domain.remove(index);
+        }
+        assert!(domain.is_empty());
+        assert_eq!(domain.iter().next(), None);
+    }
+
+    #[test]
+    fn test_domains_beyond_one_word() {
+        let mut domain = FiniteDomain::full(150);
+
+        assert_eq!(domain.len(), 150);
+        assert!(domain.contains(149));
+        assert!(!domain.contains(150));
+
+        domain.retain(|index| index % 50 == 0);
+        assert_eq!(domain.iter().collect::<Vec<_>>(), vec![0, 50, 100]);
+
+        domain.retain(|index| index > 60);
+        assert_eq!(domain.iter().collect::<Vec<_>>(), vec![100]);
+        assert!(!domain.is_empty());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut domain = FiniteDomain::full(9);
+
+        domain.retain(|index| index % 2 == 0);
+
+        assert_eq!(domain.iter().collect::<Vec<_>>(), vec![0, 2, 4, 6, 8]);
+        assert_eq!(domain.len(), 5);
+    }
+}
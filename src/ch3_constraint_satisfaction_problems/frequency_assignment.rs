@@ -0,0 +1,112 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    Constraint, Propagation, SearchOptions, ThreadSafe, VariableOrdering, CSP,
+};
+use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+/// The frequencies of two transmitters must be at least `min_diff` apart,
+/// in either direction, once both are assigned.
+#[derive(Clone, Debug)]
+pub struct MinSeparationConstraint<Id> {
+    transmitters: Vec<Id>,
+    min_diff: u32,
+}
+
+impl<Id> MinSeparationConstraint<Id> {
+    pub fn new(a: Id, b: Id, min_diff: u32) -> Self {
+        Self {
+            transmitters: vec![a, b],
+            min_diff,
+        }
+    }
+}
+
+impl<Id: Eq + Hash + Clone> Constraint<Id, u32> for MinSeparationConstraint<Id> {
+    fn get_variables(&self) -> &Vec<Id> {
+        &self.transmitters
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Id, u32>) -> bool {
+        match (
+            assignment.get(&self.transmitters[0]),
+            assignment.get(&self.transmitters[1]),
+        ) {
+            (Some(a), Some(b)) => a.abs_diff(*b) >= self.min_diff,
+            _ => true,
+        }
+    }
+}
+
+/// Assigns each of `transmitters` one of `available_freqs`, keeping each
+/// `(a, b, min_sep)` of `neighbors_with_min_sep` at least `min_sep` apart.
+pub fn assign<Id>(
+    transmitters: &[Id],
+    neighbors_with_min_sep: &[(Id, Id, u32)],
+    available_freqs: &[u32],
+) -> Option<HashMap<Id, u32>>
+where
+    Id: Eq + Hash + Clone + ThreadSafe + 'static,
+{
+    let domains = transmitters
+        .iter()
+        .map(|transmitter| (transmitter.clone(), available_freqs.to_vec()))
+        .collect();
+
+    let mut csp = CSP::new(transmitters.to_vec(), domains);
+
+    for (a, b, min_sep) in neighbors_with_min_sep {
+        csp.add_constraint(Arc::new(MinSeparationConstraint::new(
+            a.clone(),
+            b.clone(),
+            *min_sep,
+        )));
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MrvThenDegree,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+
+    csp.backtracking_search_with_options(HashMap::new(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_of_three_transmitters() {
+        let neighbors = [("north", "center", 2), ("center", "south", 2)];
+
+        let frequencies = assign(&["north", "center", "south"], &neighbors, &[1, 2, 3]).unwrap();
+
+        // Only the ends of the band are far enough from the middle.
+        assert_eq!(frequencies["center"] % 2, 1);
+        for (a, b, min_sep) in neighbors {
+            assert!(frequencies[a].abs_diff(frequencies[b]) >= min_sep);
+        }
+    }
+
+    #[test]
+    fn test_too_narrow_band_is_unassignable() {
+        let neighbors = [
+            ("north", "center", 2),
+            ("center", "south", 2),
+            ("north", "south", 2),
+        ];
+
+        assert_eq!(
+            assign(&["north", "center", "south"], &neighbors, &[1, 2, 3, 4]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_min_separation_constraint() {
+        let constraint = MinSeparationConstraint::new('a', 'b', 3);
+
+        assert!(constraint.satisfied(&[('a', 10), ('b', 7)].into()));
+        assert!(!constraint.satisfied(&[('a', 10), ('b', 8)].into()));
+        assert!(constraint.satisfied(&[('a', 10)].into()));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::LessThanConstraint,
+    grid::{to_grid, Cell, Grid},
+    latin_square::latin_square_csp,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Solves an `n`×`n` Futoshiki: a Latin square with the numbers 1 to `n`,
+/// where each `(row, column, value)` of `givens` is already filled in and
+/// each `(smaller, larger)` pair of `inequalities` relates two cells by `<`.
+pub fn solve(
+    n: usize,
+    givens: &[(usize, usize, u32)],
+    inequalities: &[(Cell, Cell)],
+) -> Option<Grid> {
+    let mut csp = latin_square_csp(n, givens);
+
+    for &(smaller, larger) in inequalities {
+        csp.add_constraint(Arc::new(LessThanConstraint::new(smaller, larger)));
+    }
+
+    csp.backtracking_search(HashMap::new())
+        .map(|solution| to_grid(n, &solution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_by_four_futoshiki() {
+        // These clues only admit one solution.
+        let givens = [(0, 0, 1), (2, 3, 1), (3, 0, 2)];
+        let inequalities = [
+            ((0, 1), (0, 2)),
+            ((0, 2), (0, 3)),
+            ((0, 0), (1, 0)),
+            ((1, 0), (2, 0)),
+            ((1, 2), (1, 3)),
+            ((2, 2), (2, 1)),
+            ((3, 0), (2, 0)),
+        ];
+
+        assert_eq!(
+            solve(4, &givens, &inequalities),
+            Some(vec![
+                vec![1, 2, 3, 4],
+                vec![3, 4, 1, 2],
+                vec![4, 3, 2, 1],
+                vec![2, 1, 4, 3],
+            ])
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// The multiplicative constant used by the Fx hash of rustc and Firefox.
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// A fast, non-cryptographic hasher for small keys such as integers and short
+/// strings. It offers no protection against crafted collisions, so it is only
+/// meant for keys the program controls, like the variables of a CSP.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+
+            self.add_to_hash(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, hash::BuildHasher};
+
+    #[test]
+    fn test_fx_hash_is_deterministic() {
+        let hash_builder = FxBuildHasher::default();
+
+        assert_eq!(
+            hash_builder.hash_one("Tasmania"),
+            hash_builder.hash_one("Tasmania")
+        );
+        assert_ne!(
+            hash_builder.hash_one("Tasmania"),
+            hash_builder.hash_one("Victoria")
+        );
+        assert_ne!(hash_builder.hash_one(1usize), hash_builder.hash_one(2usize));
+    }
+
+    #[test]
+    fn test_fx_hash_map() {
+        let mut map: HashMap<&str, i32, FxBuildHasher> = HashMap::default();
+        map.insert("A", 1);
+        map.insert("B", 2);
+
+        assert_eq!(map.get("A"), Some(&1));
+        assert_eq!(map.get("B"), Some(&2));
+        assert_eq!(map.get("C"), None);
+    }
+}
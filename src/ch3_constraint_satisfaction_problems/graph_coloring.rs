@@ -0,0 +1,296 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{NotEqualConstraint, ThreadSafe, CSP};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    hash::Hash,
+    sync::Arc,
+};
+
+/// The CSP coloring the vertices of a graph so that no two adjacent ones
+/// share a color.
+///
+/// Every vertex mentioned in `adjacency`, as a key or as a neighbor, becomes
+/// a variable with `colors` as its domain. An edge may be listed from both
+/// ends or just one; either way it yields a single constraint.
+///
+/// The variables are in ascending order of the vertices, and not in the
+/// map's iteration order, which changes from run to run: the same graph
+/// thus always gets the same coloring.
+pub fn graph_coloring_csp<T, C>(adjacency: &HashMap<T, Vec<T>>, colors: Vec<C>) -> CSP<T, C>
+where
+    T: Eq + Hash + Ord + Clone + ThreadSafe + 'static,
+    C: Clone + PartialEq,
+{
+    let mut entries: Vec<(&T, &Vec<T>)> = adjacency.iter().collect();
+    entries.sort_by_key(|(vertex, _)| *vertex);
+
+    let mut vertices: BTreeSet<T> = BTreeSet::new();
+    let mut seen_edges: HashSet<(T, T)> = HashSet::new();
+    let mut edges: Vec<(T, T)> = vec![];
+
+    for (vertex, neighbors) in entries {
+        vertices.extend([vertex].into_iter().chain(neighbors).cloned());
+
+        for neighbor in neighbors {
+            if !seen_edges.contains(&(neighbor.clone(), vertex.clone()))
+                && seen_edges.insert((vertex.clone(), neighbor.clone()))
+            {
+                edges.push((vertex.clone(), neighbor.clone()));
+            }
+        }
+    }
+    let vertices: Vec<T> = vertices.into_iter().collect();
+
+    let domains = vertices
+        .iter()
+        .map(|vertex| (vertex.clone(), colors.clone()))
+        .collect();
+
+    let mut csp = CSP::new(vertices, domains);
+
+    for (vertex1, vertex2) in edges {
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![vertex1, vertex2])));
+    }
+
+    csp
+}
+
+/// Colors the graph given by `adjacency` with `colors`, or returns `None` if
+/// there are too few of them. The same graph always gets the same coloring,
+/// see `graph_coloring_csp`.
+pub fn color_graph<T, C>(adjacency: &HashMap<T, Vec<T>>, colors: Vec<C>) -> Option<HashMap<T, C>>
+where
+    T: Eq + Hash + Ord + Clone + ThreadSafe + 'static,
+    C: Clone + PartialEq,
+{
+    graph_coloring_csp(adjacency, colors).backtracking_search(HashMap::new())
+}
+
+/// The fewest colors needed for the graph given by `adjacency` (its
+/// chromatic number), with a coloring using colors `0..k`.
+///
+/// Tries one color, then two, and so on, building a fresh CSP each time.
+///
+/// # Panics
+///
+/// Panics if a vertex is listed as its own neighbor, as no number of colors
+/// can satisfy that edge.
+pub fn min_colors<T>(adjacency: &HashMap<T, Vec<T>>) -> (usize, HashMap<T, usize>)
+where
+    T: Eq + Hash + Ord + Clone + ThreadSafe + 'static,
+{
+    let vertices = graph_coloring_csp(adjacency, vec![0]).variables().len();
+    if vertices == 0 {
+        return (0, HashMap::new());
+    }
+
+    // A graph without self-loops can always use a color per vertex.
+    for k in 1..=vertices {
+        if let Some(coloring) = color_graph(adjacency, (0..k).collect()) {
+            return (k, coloring);
+        }
+    }
+
+    panic!("a vertex adjacent to itself can't be colored");
+}
+
+/// Renders a colored graph in Graphviz's DOT language, each vertex filled
+/// with its color from `coloring` (left blank if it has none) and each edge
+/// of `adjacency` drawn once.
+///
+/// Vertices are named by their `Display` form, and the lines are sorted so
+/// that the output doesn't depend on the maps' iteration order.
+pub fn to_dot<T>(adjacency: &HashMap<T, Vec<T>>, coloring: &HashMap<T, String>) -> String
+where
+    T: Eq + Hash + Display,
+{
+    let quote = |name: String| format!("\"{}\"", name.replace('"', "\\\""));
+    let mut nodes: BTreeSet<String> = BTreeSet::new();
+    let mut edges: BTreeSet<(String, String)> = BTreeSet::new();
+
+    for (vertex, neighbors) in adjacency {
+        for vertex in [vertex].into_iter().chain(neighbors) {
+            let node = quote(vertex.to_string());
+            nodes.insert(match coloring.get(vertex) {
+                Some(color) => format!("{} [fillcolor={}];", node, quote(color.clone())),
+                None => format!("{};", node),
+            });
+        }
+
+        for neighbor in neighbors {
+            let (name1, name2) = (quote(vertex.to_string()), quote(neighbor.to_string()));
+            edges.insert(if name1 <= name2 {
+                (name1, name2)
+            } else {
+                (name2, name1)
+            });
+        }
+    }
+
+    let mut dot = String::from("graph {\n    node [style=filled];\n");
+    for node in nodes {
+        dot.push_str(&format!("    {}\n", node));
+    }
+    for (name1, name2) in edges {
+        dot.push_str(&format!("    {} -- {};\n", name1, name2));
+    }
+    dot.push_str("}\n");
+
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A hub joined to every vertex of a five-vertex rim, listing every edge
+    /// from both ends.
+    fn wheel() -> HashMap<u32, Vec<u32>> {
+        let mut adjacency: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        for rim in 1..=5 {
+            let next = rim % 5 + 1;
+
+            for (vertex1, vertex2) in [(0, rim), (rim, next)] {
+                adjacency.entry(vertex1).or_default().push(vertex2);
+                adjacency.entry(vertex2).or_default().push(vertex1);
+            }
+        }
+
+        adjacency
+    }
+
+    #[test]
+    fn test_color_graph_colors_wheel() {
+        let adjacency = wheel();
+
+        let coloring = color_graph(&adjacency, vec!["red", "green", "blue", "yellow"]).unwrap();
+
+        assert_eq!(coloring.len(), 6);
+        for (vertex, neighbors) in &adjacency {
+            for neighbor in neighbors {
+                assert_ne!(coloring[vertex], coloring[neighbor]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_odd_wheel_needs_four_colors() {
+        assert_eq!(color_graph(&wheel(), vec!["red", "green", "blue"]), None);
+    }
+
+    #[test]
+    fn test_edges_listed_twice_yield_one_constraint() {
+        let csp = graph_coloring_csp(&wheel(), vec![0, 1, 2, 3]);
+
+        assert_eq!(csp.constraints_for(&0).len(), 5);
+        assert_eq!(csp.constraints_for(&1).len(), 3);
+    }
+
+    #[test]
+    fn test_coloring_does_not_depend_on_iteration_order() {
+        let coloring = color_graph(&wheel(), vec!["red", "green", "blue", "yellow"]);
+
+        // Each map is hashed with fresh keys, so they iterate differently.
+        for _ in 0..10 {
+            let adjacency: HashMap<u32, Vec<u32>> = wheel().into_iter().collect();
+
+            assert_eq!(
+                graph_coloring_csp(&adjacency, vec![0]).variables(),
+                &[0, 1, 2, 3, 4, 5]
+            );
+            assert_eq!(
+                color_graph(&adjacency, vec!["red", "green", "blue", "yellow"]),
+                coloring
+            );
+        }
+    }
+
+    #[test]
+    fn test_neighbors_without_their_own_entry_are_colored() {
+        let adjacency: HashMap<&str, Vec<&str>> = [("a", vec!["b"])].into_iter().collect();
+
+        let coloring = color_graph(&adjacency, vec![1, 2]).unwrap();
+
+        assert_eq!(coloring.len(), 2);
+        assert_ne!(coloring["a"], coloring["b"]);
+    }
+
+    fn undirected(edges: &[(u32, u32)]) -> HashMap<u32, Vec<u32>> {
+        let mut adjacency: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        for &(vertex1, vertex2) in edges {
+            adjacency.entry(vertex1).or_default().push(vertex2);
+            adjacency.entry(vertex2).or_default().push(vertex1);
+        }
+
+        adjacency
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let adjacency: HashMap<&str, Vec<&str>> = [("b", vec!["a", "c"]), ("a", vec!["b"])]
+            .into_iter()
+            .collect();
+        let coloring: HashMap<&str, String> = [("a", "red"), ("b", "green"), ("c", "red")]
+            .into_iter()
+            .map(|(vertex, color)| (vertex, color.to_string()))
+            .collect();
+
+        assert_eq!(
+            to_dot(&adjacency, &coloring),
+            "graph {\n    \
+                 node [style=filled];\n    \
+                 \"a\" [fillcolor=\"red\"];\n    \
+                 \"b\" [fillcolor=\"green\"];\n    \
+                 \"c\" [fillcolor=\"red\"];\n    \
+                 \"a\" -- \"b\";\n    \
+                 \"b\" -- \"c\";\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_escapes_quotes() {
+        let adjacency: HashMap<&str, Vec<&str>> = [("say \"hi\"", vec![])].into_iter().collect();
+
+        assert!(to_dot(&adjacency, &HashMap::new()).contains("    \"say \\\"hi\\\"\";\n"));
+    }
+
+    #[test]
+    fn test_min_colors_of_triangle() {
+        let adjacency = undirected(&[(0, 1), (1, 2), (2, 0)]);
+
+        let (k, coloring) = min_colors(&adjacency);
+
+        assert_eq!(k, 3);
+        assert_eq!(coloring.len(), 3);
+        assert!(coloring.values().all(|color| *color < 3));
+    }
+
+    #[test]
+    fn test_min_colors_of_bipartite_graph() {
+        // Every vertex of { 0, 1, 2 } is joined to every vertex of { 3, 4 }.
+        let adjacency = undirected(&[(0, 3), (0, 4), (1, 3), (1, 4), (2, 3), (2, 4)]);
+
+        let (k, coloring) = min_colors(&adjacency);
+
+        assert_eq!(k, 2);
+        for (vertex, neighbors) in &adjacency {
+            for neighbor in neighbors {
+                assert_ne!(coloring[vertex], coloring[neighbor]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_colors_of_edgeless_graph() {
+        let adjacency: HashMap<u32, Vec<u32>> = [(0, vec![]), (1, vec![])].into_iter().collect();
+
+        assert_eq!(min_colors(&adjacency).0, 1);
+        assert_eq!(
+            min_colors(&HashMap::<u32, Vec<u32>>::new()),
+            (0, HashMap::new())
+        );
+    }
+}
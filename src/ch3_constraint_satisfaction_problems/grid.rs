@@ -0,0 +1,112 @@
+use std::{collections::HashMap, fmt::Display};
+
+/// A solved square puzzle, indexed by row and then column.
+pub type Grid = Vec<Vec<u32>>;
+
+/// A cell of a grid, as `(row, column)`.
+pub type Cell = (usize, usize);
+
+/// What `format_grid` prints for a cell missing from the solution.
+const UNASSIGNED: &str = ".";
+
+/// The cells of a `rows`×`cols` grid, row by row, to use as the variables
+/// of a grid CSP.
+pub fn grid_variables(rows: usize, cols: usize) -> Vec<Cell> {
+    (0..rows)
+        .flat_map(|row| (0..cols).map(move |column| (row, column)))
+        .collect()
+}
+
+/// Lays out a (possibly partial) solution of a `rows`×`cols` grid CSP row
+/// by row, with `None` for unassigned cells.
+pub fn solution_to_vec2d<D: Clone>(
+    solution: &HashMap<Cell, D>,
+    rows: usize,
+    cols: usize,
+) -> Vec<Vec<Option<D>>> {
+    (0..rows)
+        .map(|row| {
+            (0..cols)
+                .map(|column| solution.get(&(row, column)).cloned())
+                .collect()
+        })
+        .collect()
+}
+
+/// Lays out the solution of an `n`×`n` grid CSP row by row.
+pub(crate) fn to_grid(n: usize, solution: &HashMap<Cell, u32>) -> Grid {
+    (0..n)
+        .map(|row| (0..n).map(|column| solution[&(row, column)]).collect())
+        .collect()
+}
+
+/// Renders a (possibly partial) solution of a grid CSP as text, one line per
+/// row, with the values right-aligned in columns separated by a space and
+/// `.` for unassigned cells.
+pub fn format_grid<D: Display>(solution: &HashMap<Cell, D>, rows: usize, cols: usize) -> String {
+    let cells: Vec<Vec<String>> = (0..rows)
+        .map(|row| {
+            (0..cols)
+                .map(|column| match solution.get(&(row, column)) {
+                    Some(value) => value.to_string(),
+                    None => UNASSIGNED.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    let width = cells.iter().flatten().map(String::len).max().unwrap_or(0);
+
+    let mut text = String::new();
+    for row in cells {
+        let line: Vec<String> = row.iter().map(|cell| format!("{cell:>width$}")).collect();
+        text.push_str(&line.join(" "));
+        text.push('\n');
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_variables_go_row_by_row() {
+        assert_eq!(
+            grid_variables(2, 3),
+            vec![(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2)]
+        );
+        assert!(grid_variables(0, 3).is_empty());
+    }
+
+    #[test]
+    fn test_solution_to_vec2d_lays_out_rows() {
+        let solution: HashMap<Cell, char> = [((0, 0), 'a'), ((0, 2), 'b'), ((1, 1), 'c')]
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            solution_to_vec2d(&solution, 2, 3),
+            vec![
+                vec![Some('a'), None, Some('b')],
+                vec![None, Some('c'), None],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_grid_aligns_values() {
+        let solution: HashMap<Cell, u32> = [((0, 0), 1), ((0, 1), 10), ((1, 0), 7), ((1, 1), 2)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(format_grid(&solution, 2, 2), " 1 10\n 7  2\n");
+    }
+
+    #[test]
+    fn test_format_grid_marks_unassigned_cells() {
+        let solution: HashMap<Cell, char> = [((0, 0), 'Q'), ((1, 2), 'Q')].into_iter().collect();
+
+        assert_eq!(format_grid(&solution, 2, 3), "Q . .\n. . Q\n");
+    }
+}
@@ -0,0 +1,223 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{Constraint, Propagation, SearchOptions, VariableOrdering, CSP},
+    grid::{Cell, Grid},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// The numbers of the cells of a row or column that are kept (`true`) must
+/// all differ.
+#[derive(Clone, Debug)]
+pub struct UniqueKeptConstraint {
+    cells: Vec<Cell>,
+    numbers: Vec<u32>,
+}
+
+impl UniqueKeptConstraint {
+    pub fn new(cells: Vec<Cell>, numbers: Vec<u32>) -> Self {
+        Self { cells, numbers }
+    }
+}
+
+impl Constraint<Cell, bool> for UniqueKeptConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, bool>) -> bool {
+        let mut seen = HashSet::new();
+
+        self.cells
+            .iter()
+            .zip(&self.numbers)
+            .filter(|(cell, _)| assignment.get(cell) == Some(&true))
+            .all(|(_, number)| seen.insert(number))
+    }
+}
+
+/// Two neighboring cells must not both be blacked out.
+#[derive(Clone, Debug)]
+pub struct AdjacentBlackConstraint {
+    cells: Vec<Cell>,
+}
+
+impl AdjacentBlackConstraint {
+    pub fn new(cell1: Cell, cell2: Cell) -> Self {
+        Self {
+            cells: vec![cell1, cell2],
+        }
+    }
+}
+
+impl Constraint<Cell, bool> for AdjacentBlackConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, bool>) -> bool {
+        self.cells
+            .iter()
+            .any(|cell| assignment.get(cell) != Some(&false))
+    }
+}
+
+/// The kept cells must form one orthogonally connected area. This is only
+/// judged once every cell is decided, by flooding from a kept cell.
+#[derive(Clone, Debug)]
+pub struct ConnectivityConstraint {
+    cells: Vec<Cell>,
+}
+
+impl ConnectivityConstraint {
+    pub fn new(cells: Vec<Cell>) -> Self {
+        Self { cells }
+    }
+}
+
+impl Constraint<Cell, bool> for ConnectivityConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, bool>) -> bool {
+        if self.cells.iter().any(|cell| !assignment.contains_key(cell)) {
+            return true;
+        }
+
+        let kept: HashSet<Cell> = self
+            .cells
+            .iter()
+            .copied()
+            .filter(|cell| assignment[cell])
+            .collect();
+        let Some(&start) = kept.iter().next() else {
+            return true;
+        };
+
+        let mut reached = HashSet::from([start]);
+        let mut frontier = vec![start];
+        while let Some((row, column)) = frontier.pop() {
+            let neighbors = [
+                (row.wrapping_sub(1), column),
+                (row + 1, column),
+                (row, column.wrapping_sub(1)),
+                (row, column + 1),
+            ];
+
+            for neighbor in neighbors {
+                if kept.contains(&neighbor) && reached.insert(neighbor) {
+                    frontier.push(neighbor);
+                }
+            }
+        }
+
+        reached.len() == kept.len()
+    }
+}
+
+/// Solves a Hitori puzzle: blacks out cells of `grid` so that no number is
+/// kept twice in a row or column, no two blacked out cells touch and the
+/// kept cells stay connected. Returns, per cell, whether it is kept.
+pub fn solve(grid: &Grid) -> Option<Vec<Vec<bool>>> {
+    let rows: Vec<Vec<Cell>> = (0..grid.len())
+        .map(|row| (0..grid[row].len()).map(|column| (row, column)).collect())
+        .collect();
+    let columns: Vec<Vec<Cell>> = (0..rows.first().map_or(0, Vec::len))
+        .map(|column| (0..grid.len()).map(|row| (row, column)).collect())
+        .collect();
+    let number = |&(row, column): &Cell| grid[row][column];
+
+    // A number no other cell of its row or column repeats never has to go.
+    let repeated = |line: &[Cell], cell: &Cell| {
+        line.iter()
+            .any(|other| other != cell && number(other) == number(cell))
+    };
+    let cells: Vec<Cell> = rows.iter().flatten().copied().collect();
+    let domains = cells
+        .iter()
+        .map(|cell| {
+            if repeated(&rows[cell.0], cell) || repeated(&columns[cell.1], cell) {
+                (*cell, vec![true, false])
+            } else {
+                (*cell, vec![true])
+            }
+        })
+        .collect();
+
+    let mut csp = CSP::new(cells.clone(), domains);
+
+    for line in rows.iter().chain(&columns) {
+        csp.add_constraint(Arc::new(UniqueKeptConstraint::new(
+            line.clone(),
+            line.iter().map(number).collect(),
+        )));
+        for pair in line.windows(2) {
+            csp.add_constraint(Arc::new(AdjacentBlackConstraint::new(pair[0], pair[1])));
+        }
+    }
+    csp.add_constraint(Arc::new(ConnectivityConstraint::new(cells)));
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    Some(
+        rows.iter()
+            .map(|row| row.iter().map(|cell| solution[cell]).collect())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_by_four_hitori() {
+        let grid = vec![
+            vec![2, 2, 3, 4],
+            vec![3, 4, 4, 2],
+            vec![4, 3, 2, 3],
+            vec![2, 3, 4, 2],
+        ];
+
+        let kept = solve(&grid).unwrap();
+
+        assert_eq!(
+            kept,
+            vec![
+                vec![false, true, true, true],
+                vec![true, true, false, true],
+                vec![true, false, true, true],
+                vec![true, true, true, false],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unavoidable_split_is_unsolvable() {
+        // Whichever 1 of the middle column goes, the others that must go
+        // with it either wall in the top right corner or run corner to
+        // corner and cut the grid in two.
+        let grid = vec![vec![1, 1, 2], vec![2, 1, 1], vec![3, 2, 1]];
+
+        assert_eq!(solve(&grid), None);
+    }
+
+    #[test]
+    fn test_connectivity_constraint() {
+        let cells = vec![(0, 0), (0, 1), (1, 0), (1, 1)];
+        let constraint = ConnectivityConstraint::new(cells.clone());
+        let assignment =
+            |kept: [bool; 4]| -> HashMap<Cell, bool> { cells.iter().copied().zip(kept).collect() };
+
+        assert!(constraint.satisfied(&assignment([true, false, true, true])));
+        assert!(!constraint.satisfied(&assignment([true, false, false, true])));
+        assert!(constraint.satisfied(&HashMap::from([((0, 0), true), ((1, 1), true)])));
+    }
+}
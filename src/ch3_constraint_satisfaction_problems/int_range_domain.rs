@@ -0,0 +1,345 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{Constraint, CspError, LinearConstraint};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::Arc,
+};
+
+/// The integers from `min` to `max`, both included, as a domain too large to
+/// list value by value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntRangeDomain {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl IntRangeDomain {
+    /// # Panics
+    ///
+    /// Panics if `min > max`, as the range would be empty.
+    pub fn new(min: i64, max: i64) -> Self {
+        assert!(min <= max, "an integer range needs min <= max");
+
+        Self { min, max }
+    }
+
+    /// The number of values in the range, as a `u128` since the full range
+    /// of `i64` holds one more value than a `u64` can count.
+    pub fn size(&self) -> u128 {
+        u128::from(self.min.abs_diff(self.max)) + 1
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    /// The one value of the range, if that is all it holds.
+    pub fn value(&self) -> Option<i64> {
+        (self.min == self.max).then_some(self.min)
+    }
+
+    /// The lower and upper halves of the range, the lower one taking the
+    /// middle value when there is one, or `None` for a single value.
+    pub fn split(&self) -> Option<(IntRangeDomain, IntRangeDomain)> {
+        if self.min == self.max {
+            return None;
+        }
+
+        // Rounded down, and in `i128` so distant bounds can't overflow.
+        let middle = (i128::from(self.min) + i128::from(self.max)).div_euclid(2) as i64;
+
+        Some((
+            IntRangeDomain::new(self.min, middle),
+            IntRangeDomain::new(middle + 1, self.max),
+        ))
+    }
+}
+
+/// A constraint over integer variables that can tell, from the ranges the
+/// variables are still known to lie in, whether it might hold.
+///
+/// `feasible` may answer `true` too often while some ranges hold several
+/// values, but has to be exact once each holds just one, as that is how
+/// `RangeCSP::bisecting_search` checks its solutions.
+pub trait IntervalConstraint<V: Eq + Hash> {
+    fn get_variables(&self) -> &Vec<V>;
+    fn feasible(&self, domains: &HashMap<V, IntRangeDomain>) -> bool;
+}
+
+impl<V> IntervalConstraint<V> for LinearConstraint<V>
+where
+    V: Eq + PartialEq + Hash + Clone,
+{
+    fn get_variables(&self) -> &Vec<V> {
+        Constraint::<V, i64>::get_variables(self)
+    }
+
+    fn feasible(&self, domains: &HashMap<V, IntRangeDomain>) -> bool {
+        self.feasible_within(|variable| {
+            let domain = domains[variable];
+            (domain.min, domain.max)
+        })
+    }
+}
+
+/// A CSP over integer variables each ranging over an `IntRangeDomain`,
+/// solved by splitting ranges in half rather than trying every value.
+pub struct RangeCSP<V: Eq + Hash + Clone> {
+    variables: Vec<V>,
+    domains: HashMap<V, IntRangeDomain>,
+    constraints: Vec<Arc<dyn IntervalConstraint<V>>>,
+}
+
+impl<V: Eq + Hash + Clone> RangeCSP<V> {
+    /// Panics where `try_new` would fail.
+    pub fn new(variables: Vec<V>, domains: HashMap<V, IntRangeDomain>) -> Self {
+        Self::try_new(variables, domains).unwrap_or_else(|error| match error {
+            CspError::DuplicateVariable(_) => panic!("Every variable should be listed once."),
+            _ => panic!("Every variable should have a domain assigned to it."),
+        })
+    }
+
+    /// Fails with `CspError::MissingDomain` if a variable has no domain and
+    /// with `CspError::DuplicateVariable` if a variable is listed twice.
+    pub fn try_new(
+        variables: Vec<V>,
+        domains: HashMap<V, IntRangeDomain>,
+    ) -> Result<Self, CspError<V>> {
+        let mut seen: HashSet<&V> = HashSet::new();
+
+        for variable in &variables {
+            if !domains.contains_key(variable) {
+                return Err(CspError::MissingDomain(variable.clone()));
+            }
+
+            if !seen.insert(variable) {
+                return Err(CspError::DuplicateVariable(variable.clone()));
+            }
+        }
+
+        Ok(Self {
+            variables,
+            domains,
+            constraints: vec![],
+        })
+    }
+
+    pub fn add_constraint(&mut self, constraint: Arc<dyn IntervalConstraint<V>>) {
+        self.try_add_constraint(constraint)
+            .unwrap_or_else(|_| panic!("Variable in constraint not in CSP"))
+    }
+
+    /// Fails with `CspError::UnknownVariable` if the constraint refers to a
+    /// variable that isn't part of the problem.
+    pub fn try_add_constraint(
+        &mut self,
+        constraint: Arc<dyn IntervalConstraint<V>>,
+    ) -> Result<(), CspError<V>> {
+        if let Some(unknown) = constraint
+            .get_variables()
+            .iter()
+            .find(|variable| !self.variables.contains(variable))
+        {
+            return Err(CspError::UnknownVariable(unknown.clone()));
+        }
+
+        self.constraints.push(constraint);
+
+        Ok(())
+    }
+
+    /// Finds a value for every variable satisfying all the constraints.
+    ///
+    /// Rather than branching on each value, the search halves the widest
+    /// range left, tries the lower half and then the upper one, and drops
+    /// any half that some constraint finds infeasible. Ranges of any width
+    /// thus cost a number of splits that grows with their logarithm, as long
+    /// as the constraints prune well.
+    pub fn bisecting_search(&self) -> Option<HashMap<V, i64>> {
+        self.bisect(&mut self.domains.clone())
+    }
+
+    fn bisect(&self, domains: &mut HashMap<V, IntRangeDomain>) -> Option<HashMap<V, i64>> {
+        if !self
+            .constraints
+            .iter()
+            .all(|constraint| constraint.feasible(domains))
+        {
+            return None;
+        }
+
+        // Ties go to the first variable declared.
+        let widest = self
+            .variables
+            .iter()
+            .enumerate()
+            .filter(|(_, variable)| domains[*variable].value().is_none())
+            .max_by_key(|(position, variable)| (domains[*variable].size(), Reverse(*position)));
+
+        let Some((_, variable)) = widest else {
+            return Some(
+                domains
+                    .iter()
+                    .map(|(variable, domain)| (variable.clone(), domain.min))
+                    .collect(),
+            );
+        };
+
+        let domain = domains[variable];
+        let (lower, upper) = domain.split().unwrap();
+
+        for half in [lower, upper] {
+            domains.insert(variable.clone(), half);
+            if let Some(solution) = self.bisect(domains) {
+                return Some(solution);
+            }
+        }
+        domains.insert(variable.clone(), domain);
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch3_constraint_satisfaction_problems::csp::Relation;
+
+    fn range_csp(variables: &[&'static str], min: i64, max: i64) -> RangeCSP<&'static str> {
+        let domains = variables
+            .iter()
+            .map(|variable| (*variable, IntRangeDomain::new(min, max)))
+            .collect();
+
+        RangeCSP::new(variables.to_vec(), domains)
+    }
+
+    #[test]
+    fn test_split() {
+        let domain = IntRangeDomain::new(0, 1000);
+
+        assert_eq!(
+            domain.split(),
+            Some((IntRangeDomain::new(0, 500), IntRangeDomain::new(501, 1000)))
+        );
+        assert_eq!(
+            IntRangeDomain::new(-3, -2).split().unwrap().0.value(),
+            Some(-3)
+        );
+        assert_eq!(IntRangeDomain::new(7, 7).split(), None);
+        assert_eq!(domain.size(), 1001);
+    }
+
+    #[test]
+    fn test_full_range() {
+        let domain = IntRangeDomain::new(i64::MIN, i64::MAX);
+
+        assert_eq!(domain.size(), 1 << 64);
+        assert_eq!(
+            domain.split(),
+            Some((
+                IntRangeDomain::new(i64::MIN, -1),
+                IntRangeDomain::new(0, i64::MAX)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_bisecting_search_over_full_range() {
+        let mut csp = range_csp(&["x", "y"], i64::MIN, i64::MAX);
+        csp.add_constraint(Arc::new(LinearConstraint::new(
+            vec![(1, "x"), (-1, "y")],
+            Relation::Equal,
+            i64::MAX,
+            i64::MIN..=i64::MAX,
+        )));
+
+        let solution = csp.bisecting_search().unwrap();
+
+        assert_eq!(
+            i128::from(solution["x"]) - i128::from(solution["y"]),
+            i128::from(i64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_bisecting_search_on_sum() {
+        let mut csp = range_csp(&["x", "y"], 0, 1000);
+        csp.add_constraint(Arc::new(LinearConstraint::new(
+            vec![(1, "x"), (1, "y")],
+            Relation::Equal,
+            100,
+            0..=1000,
+        )));
+
+        let solution = csp.bisecting_search().unwrap();
+
+        assert_eq!(solution["x"] + solution["y"], 100);
+        assert!(IntRangeDomain::new(0, 1000).contains(solution["x"]));
+        assert!(IntRangeDomain::new(0, 1000).contains(solution["y"]));
+    }
+
+    #[test]
+    fn test_bisecting_search_on_huge_domains() {
+        let mut csp = range_csp(&["x", "y"], 0, 1 << 40);
+        for (terms, relation, target) in [
+            (vec![(1, "x"), (-1, "y")], Relation::Equal, 12_345),
+            (vec![(1, "x"), (1, "y")], Relation::GreaterOrEqual, 1 << 39),
+        ] {
+            csp.add_constraint(Arc::new(LinearConstraint::new(
+                terms,
+                relation,
+                target,
+                0..=1 << 40,
+            )));
+        }
+
+        let solution = csp.bisecting_search().unwrap();
+
+        assert_eq!(solution["x"] - solution["y"], 12_345);
+        assert!(solution["x"] + solution["y"] >= 1 << 39);
+    }
+
+    #[test]
+    fn test_bisecting_search_without_integer_solution() {
+        let mut csp = range_csp(&["x"], 0, 1000);
+        csp.add_constraint(Arc::new(LinearConstraint::new(
+            vec![(2, "x")],
+            Relation::Equal,
+            101,
+            0..=1000,
+        )));
+
+        assert_eq!(csp.bisecting_search(), None);
+    }
+
+    #[test]
+    fn test_try_add_constraint_rejects_unknown_variable() {
+        let mut csp = range_csp(&["x"], 0, 10);
+
+        assert_eq!(
+            csp.try_add_constraint(Arc::new(LinearConstraint::new(
+                vec![(1, "z")],
+                Relation::LessOrEqual,
+                3,
+                0..=10,
+            ))),
+            Err(CspError::UnknownVariable("z"))
+        );
+    }
+
+    #[test]
+    fn test_try_new_rejects_missing_domain() {
+        let domains = HashMap::from([("x", IntRangeDomain::new(0, 10))]);
+
+        assert_eq!(
+            RangeCSP::try_new(vec!["x", "y"], domains.clone()).err(),
+            Some(CspError::MissingDomain("y"))
+        );
+        assert_eq!(
+            RangeCSP::try_new(vec!["x", "x"], domains).err(),
+            Some(CspError::DuplicateVariable("x"))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::Constraint,
+    grid::{to_grid, Cell, Grid},
+    latin_square::latin_square_csp,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// How the numbers of a cage combine into its target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    /// The largest number minus all the others, so the two cells of the
+    /// usual subtraction cage can come in either order.
+    Subtract,
+    Multiply,
+    /// The largest number divided by all the others, leaving no remainder.
+    Divide,
+}
+
+/// The numbers in a group of cells must combine to `target` under
+/// `operation`. Partial assignments are always accepted: the cage is only
+/// worked out once all its cells are filled in.
+#[derive(Clone, Debug)]
+pub struct CageConstraint {
+    cells: Vec<Cell>,
+    operation: Operation,
+    target: u32,
+}
+
+impl CageConstraint {
+    pub fn new(cells: Vec<Cell>, operation: Operation, target: u32) -> Self {
+        Self {
+            cells,
+            operation,
+            target,
+        }
+    }
+}
+
+impl Constraint<Cell, u32> for CageConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.cells
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, u32>) -> bool {
+        let Some(mut values) = self
+            .cells
+            .iter()
+            .map(|cell| assignment.get(cell).copied())
+            .collect::<Option<Vec<u32>>>()
+        else {
+            return true;
+        };
+
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        let (largest, rest) = (values[0], &values[1..]);
+
+        match self.operation {
+            Operation::Add => values.iter().sum::<u32>() == self.target,
+            Operation::Multiply => values.iter().product::<u32>() == self.target,
+            Operation::Subtract => largest.checked_sub(rest.iter().sum()) == Some(self.target),
+            Operation::Divide => {
+                let divisor: u32 = rest.iter().product();
+                largest % divisor == 0 && largest / divisor == self.target
+            }
+        }
+    }
+}
+
+/// Solves an `n`×`n` KenKen: a Latin square with the numbers 1 to `n`
+/// whose cells are split into `cages`.
+pub fn solve(n: usize, cages: &[CageConstraint]) -> Option<Grid> {
+    let mut csp = latin_square_csp(n, &[]);
+
+    for cage in cages {
+        csp.add_constraint(Arc::new(cage.clone()));
+    }
+
+    csp.backtracking_search(HashMap::new())
+        .map(|solution| to_grid(n, &solution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_by_four_kenken() {
+        // These cages only admit one solution.
+        let cages = [
+            CageConstraint::new(vec![(0, 0), (1, 0)], Operation::Subtract, 2),
+            CageConstraint::new(vec![(0, 1), (0, 2)], Operation::Multiply, 6),
+            CageConstraint::new(vec![(0, 3), (1, 3)], Operation::Divide, 2),
+            CageConstraint::new(vec![(1, 1), (1, 2)], Operation::Subtract, 3),
+            CageConstraint::new(vec![(2, 0), (3, 0)], Operation::Divide, 2),
+            CageConstraint::new(vec![(2, 1), (3, 1)], Operation::Subtract, 2),
+            CageConstraint::new(vec![(2, 2), (2, 3)], Operation::Divide, 2),
+            CageConstraint::new(vec![(3, 2), (3, 3)], Operation::Multiply, 12),
+        ];
+
+        assert_eq!(
+            solve(4, &cages),
+            Some(vec![
+                vec![1, 2, 3, 4],
+                vec![3, 4, 1, 2],
+                vec![4, 3, 2, 1],
+                vec![2, 1, 4, 3],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_two_cell_cages_accept_either_order() {
+        let subtract = CageConstraint::new(vec![(0, 0), (0, 1)], Operation::Subtract, 2);
+        let divide = CageConstraint::new(vec![(0, 0), (0, 1)], Operation::Divide, 2);
+        let cells = |a, b| -> HashMap<Cell, u32> { HashMap::from([((0, 0), a), ((0, 1), b)]) };
+
+        assert!(subtract.satisfied(&cells(1, 3)));
+        assert!(subtract.satisfied(&cells(3, 1)));
+        assert!(!subtract.satisfied(&cells(2, 3)));
+        assert!(divide.satisfied(&cells(2, 4)));
+        assert!(divide.satisfied(&cells(4, 2)));
+        assert!(!divide.satisfied(&cells(3, 4)));
+        assert!(divide.satisfied(&HashMap::from([((0, 0), 3)])));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{AllDifferentConstraint, CSP},
+    grid::{grid_variables, to_grid, Cell, Grid},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// The CSP filling an `n`×`n` grid with the numbers 1 to `n` so that each
+/// appears once per row and once per column. Each `(row, column, value)` of
+/// `givens` fixes a cell.
+pub fn latin_square_csp(n: usize, givens: &[(usize, usize, u32)]) -> CSP<Cell, u32> {
+    let cells = grid_variables(n, n);
+    let mut domains: HashMap<Cell, Vec<u32>> = cells
+        .iter()
+        .map(|cell| (*cell, (1..=n as u32).collect()))
+        .collect();
+
+    for &(row, column, value) in givens {
+        domains.insert((row, column), vec![value]);
+    }
+
+    let mut csp = CSP::new(cells, domains);
+
+    for i in 0..n {
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(
+            (0..n).map(|column| (i, column)).collect(),
+        )));
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(
+            (0..n).map(|row| (row, i)).collect(),
+        )));
+    }
+
+    csp
+}
+
+/// Completes an `n`×`n` Latin square from `givens`, each a
+/// `(row, column, value)` triple.
+pub fn solve(n: usize, givens: &[(usize, usize, u32)]) -> Option<Grid> {
+    latin_square_csp(n, givens)
+        .backtracking_search(HashMap::new())
+        .map(|solution| to_grid(n, &solution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_permutations(lines: impl Iterator<Item = Vec<u32>>, n: u32) {
+        for mut line in lines {
+            line.sort();
+            assert_eq!(line, (1..=n).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn test_four_by_four_latin_square() {
+        let grid = solve(4, &[(0, 0, 3), (2, 1, 1)]).unwrap();
+
+        assert_eq!(grid[0][0], 3);
+        assert_eq!(grid[2][1], 1);
+        assert_permutations(grid.iter().cloned(), 4);
+        assert_permutations(
+            (0..4).map(|column| grid.iter().map(|row| row[column]).collect()),
+            4,
+        );
+    }
+
+    #[test]
+    fn test_conflicting_givens_have_no_solution() {
+        assert_eq!(solve(3, &[(0, 0, 1), (0, 2, 1)]), None);
+    }
+
+    #[test]
+    fn test_latin_squares_of_order_three() {
+        assert_eq!(latin_square_csp(3, &[]).count_solutions(), 12);
+    }
+}
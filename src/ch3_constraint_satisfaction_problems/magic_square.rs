@@ -0,0 +1,65 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{AllDifferentConstraint, SumConstraint, CSP},
+    grid::{grid_variables, to_grid, Cell, Grid},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// What every row, column and diagonal of an `n`×`n` magic square adds up
+/// to.
+pub fn magic_constant(n: usize) -> u32 {
+    (n * (n * n + 1) / 2) as u32
+}
+
+/// Fills an `n`×`n` grid with the distinct numbers 1 to `n²` so that every
+/// row, every column and both diagonals add up to `magic_constant(n)`.
+pub fn solve(n: usize) -> Option<Grid> {
+    let cells = grid_variables(n, n);
+    let domains = cells
+        .iter()
+        .map(|cell| (*cell, (1..=(n * n) as u32).collect()))
+        .collect();
+
+    let mut csp = CSP::new(cells.clone(), domains);
+    csp.add_constraint(Arc::new(AllDifferentConstraint::new(cells)));
+
+    let mut lines: Vec<Vec<Cell>> = vec![];
+    for i in 0..n {
+        lines.push((0..n).map(|column| (i, column)).collect());
+        lines.push((0..n).map(|row| (row, i)).collect());
+    }
+    lines.push((0..n).map(|i| (i, i)).collect());
+    lines.push((0..n).map(|i| (i, n - 1 - i)).collect());
+
+    for line in lines {
+        csp.add_constraint(Arc::new(SumConstraint::new(line, magic_constant(n))));
+    }
+
+    csp.backtracking_search(HashMap::new())
+        .map(|solution| to_grid(n, &solution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_three_by_three_magic_square() {
+        let grid = solve(3).unwrap();
+
+        let mut numbers: Vec<u32> = grid.iter().flatten().copied().collect();
+        numbers.sort();
+        assert_eq!(numbers, (1..=9).collect::<Vec<u32>>());
+
+        for i in 0..3 {
+            assert_eq!(grid[i].iter().sum::<u32>(), 15);
+            assert_eq!(grid.iter().map(|row| row[i]).sum::<u32>(), 15);
+        }
+        assert_eq!((0..3).map(|i| grid[i][i]).sum::<u32>(), 15);
+        assert_eq!((0..3).map(|i| grid[i][2 - i]).sum::<u32>(), 15);
+    }
+
+    #[test]
+    fn test_two_by_two_magic_square_does_not_exist() {
+        assert_eq!(solve(2), None);
+    }
+}
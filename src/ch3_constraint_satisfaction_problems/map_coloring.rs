@@ -1,39 +1,235 @@
-use crate::ch3_constraint_satisfaction_problems::csp::{Constraint, CSP};
-use std::{collections::HashMap, sync::Arc};
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{Constraint, NotEqualConstraint, CSP},
+    graph_coloring::{color_graph, min_colors},
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    sync::Arc,
+};
 
+/// Two neighboring places must not share a color.
 #[derive(Clone)]
 pub struct MapColoringConstraint<'a> {
-    variables: Vec<&'a str>,
+    places: NotEqualConstraint<&'a str>,
 }
 
 impl<'a> MapColoringConstraint<'a> {
-    fn new(place1: &'a str, place2: &'a str) -> Self {
+    pub fn new(place1: &'a str, place2: &'a str) -> Self {
         Self {
-            variables: vec![place1, place2],
+            places: NotEqualConstraint::new(vec![place1, place2]),
         }
     }
 }
 
 impl<'a> Constraint<&'a str, &'a str> for MapColoringConstraint<'a> {
     fn get_variables(&self) -> &Vec<&'a str> {
-        &self.variables
+        Constraint::<&'a str, &'a str>::get_variables(&self.places)
     }
 
-    fn satisfied(&self, assignment: &HashMap<&str, &str>) -> bool {
-        if let (Some(place1_color), Some(place2_color)) = (
-            assignment.get(self.variables.first().unwrap()),
-            assignment.get(self.variables.last().unwrap()),
-        ) {
-            place1_color != place2_color
-        } else {
-            true
+    fn satisfied(&self, assignment: &HashMap<&'a str, &'a str>) -> bool {
+        self.places.satisfied(assignment)
+    }
+}
+
+/// The states and territories of Australia, each to be colored red, green
+/// or blue differently from its neighbors.
+pub fn australia_csp() -> CSP<&'static str, &'static str> {
+    let regions = vec![
+        "Western Australia",
+        "Northern Territory",
+        "South Australia",
+        "Queensland",
+        "New South Wales",
+        "Victoria",
+        "Tasmania",
+    ];
+    let domains = regions
+        .iter()
+        .map(|region| (*region, vec!["red", "green", "blue"]))
+        .collect();
+
+    let mut csp = CSP::new(regions, domains);
+
+    for (place1, place2) in [
+        ("Western Australia", "Northern Territory"),
+        ("Western Australia", "South Australia"),
+        ("Northern Territory", "South Australia"),
+        ("Northern Territory", "Queensland"),
+        ("South Australia", "Queensland"),
+        ("South Australia", "New South Wales"),
+        ("South Australia", "Victoria"),
+        ("Queensland", "New South Wales"),
+        ("New South Wales", "Victoria"),
+        ("Victoria", "Tasmania"),
+    ] {
+        csp.add_constraint(Arc::new(MapColoringConstraint::new(place1, place2)));
+    }
+
+    csp
+}
+
+/// Reads the borders of a map from a file with one `region: neighbor1,
+/// neighbor2` line per region.
+///
+/// Blank lines and lines starting with `#` are skipped. A border only needs
+/// to be listed from one side, and a region may have no neighbors at all
+/// (`Tasmania:`). Lines without a `:` or without a region name, and regions
+/// listed as their own neighbor, are rejected with `ErrorKind::InvalidData`.
+pub fn load_adjacency(path: &Path) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut adjacency: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |reason| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("line {}: {}", number + 1, reason),
+            )
+        };
+
+        let (region, neighbors) = line.split_once(':').ok_or_else(|| invalid("missing ':'"))?;
+        let region = region.trim();
+        if region.is_empty() {
+            return Err(invalid("missing region name"));
+        }
+
+        let neighbors: Vec<String> = neighbors
+            .split(',')
+            .map(str::trim)
+            .filter(|neighbor| !neighbor.is_empty())
+            .map(str::to_string)
+            .collect();
+        if neighbors.iter().any(|neighbor| neighbor == region) {
+            return Err(invalid("region borders itself"));
+        }
+
+        adjacency
+            .entry(region.to_string())
+            .or_default()
+            .extend(neighbors);
+    }
+
+    Ok(adjacency)
+}
+
+/// Colors the map described by the file at `path` (see `load_adjacency`)
+/// with `colors`, or returns `Ok(None)` if there are too few of them.
+pub fn color_map_from_file<C: Clone + PartialEq>(
+    path: &Path,
+    colors: Vec<C>,
+) -> io::Result<Option<HashMap<String, C>>> {
+    Ok(color_graph(&load_adjacency(path)?, colors))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColoringError {
+    /// The palette has fewer colors than the map needs.
+    Infeasible { min_colors_needed: usize },
+    /// The region is listed as its own neighbor, so no palette will do.
+    SelfAdjacent(String),
+}
+
+impl Display for ColoringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColoringError::Infeasible { min_colors_needed } => {
+                write!(f, "the map needs at least {} colors", min_colors_needed)
+            }
+            ColoringError::SelfAdjacent(region) => write!(f, "{} borders itself", region),
         }
     }
 }
 
+impl Error for ColoringError {}
+
+/// Colors the map with borders `adjacency` (as read by `load_adjacency`)
+/// using the names in `palette`.
+///
+/// If the palette is too small, the chromatic number is searched for so the
+/// error can say how many colors would do. A region bordering itself fails
+/// with `ColoringError::SelfAdjacent`, the first such region by name.
+pub fn color_map(
+    adjacency: &HashMap<String, Vec<String>>,
+    palette: &[&str],
+) -> Result<HashMap<String, String>, ColoringError> {
+    if let Some(region) = adjacency
+        .iter()
+        .filter(|(region, neighbors)| neighbors.contains(region))
+        .map(|(region, _)| region)
+        .min()
+    {
+        return Err(ColoringError::SelfAdjacent(region.clone()));
+    }
+
+    let colors = palette.iter().map(|color| color.to_string()).collect();
+
+    color_graph(adjacency, colors).ok_or_else(|| ColoringError::Infeasible {
+        min_colors_needed: min_colors(adjacency).0,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_supports_matches_satisfied() {
+        let constraint = MapColoringConstraint::new("Victoria", "Tasmania");
+        let colors = ["red", "green"];
+
+        for place1 in ["Victoria", "Tasmania", "Queensland"] {
+            for place2 in ["Victoria", "Tasmania"] {
+                for color1 in colors {
+                    for color2 in colors {
+                        let assignment: HashMap<&str, &str> =
+                            [(place1, color1), (place2, color2)].into_iter().collect();
+
+                        assert_eq!(
+                            constraint.supports(&place1, &color1, &place2, &color2),
+                            constraint.satisfied(&assignment)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// A file in the temporary directory that is removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+            fs::write(&path, contents).unwrap();
+
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_australia_csp_has_twelve_colorings() {
+        let csp = australia_csp();
+
+        // Once South Australia has a color, the regions around it alternate
+        // between the other two, and Tasmania avoids Victoria's: 3 * 2 * 2.
+        assert_eq!(csp.count_solutions(), 12);
+    }
 
     #[test]
     fn test_australia_map_coloring() {
@@ -48,7 +244,7 @@ mod tests {
             "Tasmania",
         ];
 
-        let domains: HashMap<&str, Vec<&str>> = vec![
+        let domains: HashMap<&str, Vec<&str>> = [
             ("Western Australia", vec!["red", "green", "blue"]),
             ("Northern Territory", vec!["red", "green", "blue"]),
             ("South Australia", vec!["red", "green", "blue"]),
@@ -129,4 +325,107 @@ mod tests {
 
         assert!(solution.get("Victoria").ne(&solution.get("Tasmania")));
     }
+
+    #[test]
+    fn test_color_map_from_file() {
+        let file = TempFile::new(
+            "australia.txt",
+            "# Mainland states and territories\n\
+             Western Australia: Northern Territory, South Australia\n\
+             Northern Territory: South Australia, Queensland\n\
+             South Australia: Queensland, New South Wales, Victoria\n\
+             \n\
+             Queensland: New South Wales\n\
+             New South Wales: Victoria\n\
+             Victoria: Tasmania\n\
+             Tasmania:\n",
+        );
+
+        let adjacency = load_adjacency(&file.0).unwrap();
+        let coloring = color_map_from_file(&file.0, vec!["red", "green", "blue"])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(adjacency.len(), 7);
+        assert_eq!(adjacency["Tasmania"], Vec::<String>::new());
+        assert_eq!(coloring.len(), 7);
+        for (region, neighbors) in &adjacency {
+            for neighbor in neighbors {
+                assert_ne!(coloring[region], coloring[neighbor]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_color_map_reports_colors_needed() {
+        let regions = ["A", "B", "C", "D"];
+        let adjacency: HashMap<String, Vec<String>> = regions
+            .iter()
+            .map(|region| {
+                let neighbors = regions
+                    .iter()
+                    .filter(|neighbor| neighbor != &region)
+                    .map(|neighbor| neighbor.to_string())
+                    .collect();
+
+                (region.to_string(), neighbors)
+            })
+            .collect();
+
+        let error = color_map(&adjacency, &["red", "green", "blue"]).unwrap_err();
+        let coloring = color_map(&adjacency, &["red", "green", "blue", "yellow"]).unwrap();
+
+        assert_eq!(
+            error,
+            ColoringError::Infeasible {
+                min_colors_needed: 4
+            }
+        );
+        assert_eq!(error.to_string(), "the map needs at least 4 colors");
+        assert_eq!(coloring.len(), 4);
+        for (region, neighbors) in &adjacency {
+            for neighbor in neighbors {
+                assert_ne!(coloring[region], coloring[neighbor]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_color_map_rejects_region_bordering_itself() {
+        let adjacency: HashMap<String, Vec<String>> = [
+            ("A".to_string(), vec!["B".to_string()]),
+            ("C".to_string(), vec!["C".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+
+        let error = color_map(&adjacency, &["red"]).unwrap_err();
+
+        assert_eq!(error, ColoringError::SelfAdjacent("C".to_string()));
+        assert_eq!(error.to_string(), "C borders itself");
+        assert_eq!(
+            color_map(&adjacency, &["red", "green"]),
+            Err(ColoringError::SelfAdjacent("C".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_load_adjacency_rejects_lines_without_colon() {
+        let file = TempFile::new("invalid.txt", "Victoria: Tasmania\nQueensland\n");
+
+        let error = load_adjacency(&file.0).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "line 2: missing ':'");
+    }
+
+    #[test]
+    fn test_load_adjacency_rejects_region_bordering_itself() {
+        let file = TempFile::new("self_adjacent.txt", "A: B, A\n");
+
+        let error = load_adjacency(&file.0).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "line 1: region borders itself");
+    }
 }
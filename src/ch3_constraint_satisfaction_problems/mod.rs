@@ -1,2 +1,31 @@
+pub mod binairo;
+pub mod builder;
+pub mod chess_placement;
+pub mod circuit_board;
+pub mod crossword;
 pub mod csp;
+pub mod definition;
+pub mod einstein;
+pub mod finite_domain;
+pub mod frequency_assignment;
+pub mod futoshiki;
+pub mod fx_hash;
+pub mod graph_coloring;
+pub mod grid;
+pub mod hitori;
+pub mod int_range_domain;
+pub mod kenken;
+pub mod latin_square;
+pub mod magic_square;
 pub mod map_coloring;
+pub mod nonogram;
+pub mod nurse_rostering;
+pub mod queens;
+pub mod round_robin;
+pub mod scheduling;
+pub mod seating;
+pub mod send_more_money;
+pub mod skyscraper;
+pub mod sudoku;
+pub mod timetabling;
+pub mod word_search;
@@ -0,0 +1,173 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{Constraint, CSP};
+use std::{collections::HashMap, sync::Arc};
+
+/// The lengths of the runs of filled cells in a line, in order. Zeros are
+/// ignored, so an empty line can be given as `[]` or `[0]`.
+pub type Clue = Vec<usize>;
+
+/// Every way of filling a line of `length` cells whose runs match `clue`.
+fn line_patterns(clue: &[usize], length: usize) -> Vec<Vec<bool>> {
+    let runs: Vec<usize> = clue.iter().copied().filter(|run| *run > 0).collect();
+    let mut patterns = vec![];
+
+    fill(&runs, length, &mut vec![], &mut patterns);
+
+    patterns
+}
+
+fn fill(runs: &[usize], length: usize, line: &mut Vec<bool>, patterns: &mut Vec<Vec<bool>>) {
+    let Some((run, rest)) = runs.split_first() else {
+        let mut pattern = line.clone();
+        pattern.resize(length, false);
+        patterns.push(pattern);
+        return;
+    };
+
+    // The rest of the runs each need their cells and a gap before them.
+    let needed: usize = rest.iter().map(|run| run + 1).sum();
+    let start = line.len();
+    let Some(slack) = (length - start).checked_sub(run + needed) else {
+        return;
+    };
+
+    for offset in 0..=slack {
+        line.truncate(start);
+        line.resize(start + offset, false);
+        line.resize(start + offset + run, true);
+        if !rest.is_empty() {
+            line.push(false);
+        }
+
+        fill(rest, length, line, patterns);
+    }
+
+    line.truncate(start);
+}
+
+/// The runs of filled cells in `cells`.
+fn runs(cells: &[bool]) -> Vec<usize> {
+    cells
+        .split(|filled| !filled)
+        .map(<[bool]>::len)
+        .filter(|run| *run > 0)
+        .collect()
+}
+
+/// A column of the picture must match its clue. The variables are the rows,
+/// whose values are their fillings.
+///
+/// While only some rows are filled in, the cells above the first missing row
+/// are checked against the start of the clue, which prunes the search as the
+/// rows are filled from the top.
+#[derive(Clone, Debug)]
+pub struct ColumnConstraint {
+    rows: Vec<usize>,
+    column: usize,
+    clue: Clue,
+}
+
+impl ColumnConstraint {
+    pub fn new(rows: usize, column: usize, clue: Clue) -> Self {
+        Self {
+            rows: (0..rows).collect(),
+            column,
+            clue: clue.into_iter().filter(|run| *run > 0).collect(),
+        }
+    }
+}
+
+impl Constraint<usize, Vec<bool>> for ColumnConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.rows
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, Vec<bool>>) -> bool {
+        let cells: Vec<bool> = self
+            .rows
+            .iter()
+            .map_while(|row| assignment.get(row).map(|filling| filling[self.column]))
+            .collect();
+
+        let runs = runs(&cells);
+        if cells.len() == self.rows.len() {
+            return runs == self.clue;
+        }
+
+        // The last run may still grow, unless a blank cell has closed it.
+        let Some((last, finished)) = runs.split_last() else {
+            return true;
+        };
+        let open = cells.last() == Some(&true);
+
+        finished.len() < self.clue.len()
+            && finished == &self.clue[..finished.len()]
+            && if open {
+                *last <= self.clue[finished.len()]
+            } else {
+                *last == self.clue[finished.len()]
+            }
+    }
+}
+
+/// Reconstructs the picture described by the clues of its rows and columns,
+/// as rows of filled (`true`) and blank cells.
+pub fn solve(row_clues: &[Clue], column_clues: &[Clue]) -> Option<Vec<Vec<bool>>> {
+    let (rows, columns) = (row_clues.len(), column_clues.len());
+    let variables: Vec<usize> = (0..rows).collect();
+    let domains = variables
+        .iter()
+        .map(|row| (*row, line_patterns(&row_clues[*row], columns)))
+        .collect();
+
+    let mut csp = CSP::new(variables, domains);
+
+    for (column, clue) in column_clues.iter().enumerate() {
+        csp.add_constraint(Arc::new(ColumnConstraint::new(rows, column, clue.clone())));
+    }
+
+    let solution = csp.backtracking_search(HashMap::new())?;
+
+    Some((0..rows).map(|row| solution[&row].clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picture(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter()
+            .map(|row| row.chars().map(|cell| cell == '#').collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_plus_sign() {
+        let clues = vec![vec![1], vec![1], vec![5], vec![1], vec![1]];
+
+        assert_eq!(
+            solve(&clues, &clues),
+            Some(picture(&["..#..", "..#..", "#####", "..#..", "..#.."]))
+        );
+    }
+
+    #[test]
+    fn test_line_patterns() {
+        assert_eq!(
+            line_patterns(&[2, 1], 5),
+            picture(&["##.#.", "##..#", ".##.#"])
+        );
+        assert_eq!(line_patterns(&[0], 2), picture(&[".."]));
+        assert_eq!(line_patterns(&[3], 2), Vec::<Vec<bool>>::new());
+    }
+
+    #[test]
+    fn test_letter_with_several_runs() {
+        let rows = vec![vec![3], vec![1, 1], vec![3], vec![1, 1], vec![1, 1]];
+        let columns = vec![vec![5], vec![1, 1], vec![5]];
+
+        assert_eq!(
+            solve(&rows, &columns),
+            Some(picture(&["###", "#.#", "###", "#.#", "#.#"]))
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Constraint, WeightedConstraint, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+pub type Nurse<'a> = &'a str;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ShiftKind {
+    Day,
+    Night,
+}
+
+/// The day or night shift of day `day`, which one nurse has to work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Shift {
+    pub day: usize,
+    pub kind: ShiftKind,
+}
+
+/// Rules every roster has to follow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HardRule {
+    /// No nurse works two shifts of the same day.
+    OneShiftPerDay,
+    /// No nurse works the day shift right after a night shift.
+    NoDayAfterNight,
+}
+
+/// `nurse` would rather not work on `day`; rostering them anyway costs
+/// `penalty`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DayOff<'a> {
+    pub nurse: Nurse<'a>,
+    pub day: usize,
+    pub penalty: f64,
+}
+
+/// A nurse works none of the given shifts. The variables are the shifts,
+/// whose values are the nurses.
+#[derive(Clone, Debug)]
+pub struct DayOffConstraint {
+    shifts: Vec<Shift>,
+    nurse: usize,
+}
+
+impl DayOffConstraint {
+    pub fn new(shifts: Vec<Shift>, nurse: usize) -> Self {
+        Self { shifts, nurse }
+    }
+}
+
+impl Constraint<Shift, usize> for DayOffConstraint {
+    fn get_variables(&self) -> &Vec<Shift> {
+        &self.shifts
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Shift, usize>) -> bool {
+        self.shifts
+            .iter()
+            .all(|shift| assignment.get(shift) != Some(&self.nurse))
+    }
+}
+
+/// Puts one of `nurses` on each of `shifts` following `hard_rules`, at the
+/// lowest summed penalty of the `soft_prefs` it can't honor, and returns
+/// that roster with its penalty.
+///
+/// # Panics
+///
+/// Panics if a preference names someone who isn't among `nurses`.
+pub fn roster<'a>(
+    nurses: &[Nurse<'a>],
+    shifts: &[Shift],
+    hard_rules: &[HardRule],
+    soft_prefs: &[DayOff<'a>],
+) -> Option<(HashMap<Shift, Nurse<'a>>, f64)> {
+    // The CSP works on indices into `nurses`, as its values must be
+    // `'static` for the constraints to hold them.
+    let index = |nurse: Nurse| {
+        nurses
+            .iter()
+            .position(|known| *known == nurse)
+            .unwrap_or_else(|| panic!("{} is not a nurse", nurse))
+    };
+    let shifts_on = |day: usize| -> Vec<Shift> {
+        shifts
+            .iter()
+            .filter(|shift| shift.day == day)
+            .copied()
+            .collect()
+    };
+    let domains = shifts
+        .iter()
+        .map(|shift| (*shift, (0..nurses.len()).collect()))
+        .collect();
+
+    let mut csp = CSP::new(shifts.to_vec(), domains);
+
+    for rule in hard_rules {
+        match rule {
+            HardRule::OneShiftPerDay => {
+                let mut days: Vec<usize> = shifts.iter().map(|shift| shift.day).collect();
+                days.sort_unstable();
+                days.dedup();
+
+                for day in days {
+                    csp.add_constraint(Arc::new(AllDifferentConstraint::new(shifts_on(day))));
+                }
+            }
+            HardRule::NoDayAfterNight => {
+                for night in shifts.iter().filter(|shift| shift.kind == ShiftKind::Night) {
+                    let next_day = Shift {
+                        day: night.day + 1,
+                        kind: ShiftKind::Day,
+                    };
+
+                    if shifts.contains(&next_day) {
+                        csp.add_constraint(Arc::new(AllDifferentConstraint::new(vec![
+                            *night, next_day,
+                        ])));
+                    }
+                }
+            }
+        }
+    }
+    for preference in soft_prefs {
+        csp.add_soft_constraint(WeightedConstraint::new(
+            Arc::new(DayOffConstraint::new(
+                shifts_on(preference.day),
+                index(preference.nurse),
+            )),
+            preference.penalty,
+        ));
+    }
+
+    let (solution, penalty) = csp.branch_and_bound(HashMap::new())?;
+
+    Some((
+        solution
+            .into_iter()
+            .map(|(shift, nurse)| (shift, nurses[nurse]))
+            .collect(),
+        penalty,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn shift(day: usize, kind: ShiftKind) -> Shift {
+        Shift { day, kind }
+    }
+
+    #[test]
+    fn test_roster_minimizes_penalty_of_unhonored_day_offs() {
+        let shifts = [shift(0, ShiftKind::Day), shift(0, ShiftKind::Night)];
+        let prefs = [
+            DayOff {
+                nurse: "Ann",
+                day: 0,
+                penalty: 3.0,
+            },
+            DayOff {
+                nurse: "Ben",
+                day: 0,
+                penalty: 1.0,
+            },
+        ];
+
+        // Two of the three nurses have to work, so one of the two wishing
+        // for the day off can't have it: the cheaper one is overruled.
+        let (roster, penalty) = roster(
+            &["Ann", "Ben", "Cat"],
+            &shifts,
+            &[HardRule::OneShiftPerDay],
+            &prefs,
+        )
+        .unwrap();
+
+        assert_eq!(penalty, 1.0);
+        assert_eq!(roster.len(), 2);
+        assert!(roster.values().all(|nurse| *nurse != "Ann"));
+        assert_ne!(roster[&shifts[0]], roster[&shifts[1]]);
+    }
+
+    #[test]
+    fn test_roster_honors_every_preference_it_can() {
+        let shifts = [shift(0, ShiftKind::Night), shift(1, ShiftKind::Day)];
+        let prefs = [DayOff {
+            nurse: "Ann",
+            day: 0,
+            penalty: 1.0,
+        }];
+
+        let (roster, penalty) = roster(
+            &["Ann", "Ben"],
+            &shifts,
+            &[HardRule::NoDayAfterNight],
+            &prefs,
+        )
+        .unwrap();
+
+        assert_eq!(penalty, 0.0);
+        assert_eq!(roster[&shifts[0]], "Ben");
+        assert_eq!(roster[&shifts[1]], "Ann");
+    }
+
+    #[test]
+    fn test_roster_fails_when_hard_rules_cannot_hold() {
+        let shifts = [shift(0, ShiftKind::Night), shift(1, ShiftKind::Day)];
+
+        assert_eq!(
+            roster(&["Ann"], &shifts, &[HardRule::NoDayAfterNight], &[]),
+            None
+        );
+    }
+}
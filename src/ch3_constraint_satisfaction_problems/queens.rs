@@ -0,0 +1,100 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{Constraint, CSP};
+use std::{collections::HashMap, sync::Arc};
+
+/// No two queens may share a row or a diagonal. The variables are the
+/// columns of the board, each holding one queen, and their values the rows
+/// the queens stand on, so sharing a column is ruled out by construction.
+#[derive(Clone, Debug)]
+pub struct QueensConstraint {
+    columns: Vec<usize>,
+}
+
+impl QueensConstraint {
+    pub fn new(columns: Vec<usize>) -> Self {
+        Self { columns }
+    }
+}
+
+impl Constraint<usize, usize> for QueensConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.columns
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, usize>) -> bool {
+        let queens: Vec<(usize, usize)> = self
+            .columns
+            .iter()
+            .filter_map(|column| assignment.get(column).map(|row| (*column, *row)))
+            .collect();
+
+        queens.iter().enumerate().all(|(i, (column1, row1))| {
+            queens[i + 1..].iter().all(|(column2, row2)| {
+                row1 != row2 && row1.abs_diff(*row2) != column1.abs_diff(*column2)
+            })
+        })
+    }
+}
+
+/// The CSP placing `n` queens on an `n`×`n` board, with one column per
+/// variable and a `QueensConstraint` per pair of columns, so that
+/// propagation can tell which pair rules a row out.
+pub fn queens_csp(n: usize) -> CSP<usize, usize> {
+    let columns: Vec<usize> = (0..n).collect();
+    let domains = columns
+        .iter()
+        .map(|column| (*column, (0..n).collect()))
+        .collect();
+
+    let mut csp = CSP::new(columns, domains);
+
+    for column1 in 0..n {
+        for column2 in column1 + 1..n {
+            csp.add_constraint(Arc::new(QueensConstraint::new(vec![column1, column2])));
+        }
+    }
+
+    csp
+}
+
+/// Places `n` queens on an `n`×`n` board so that none attacks another,
+/// returning the row of the queen in each column.
+pub fn solve_n_queens(n: usize) -> Option<HashMap<usize, usize>> {
+    queens_csp(n).backtracking_search(HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_no_attacks(solution: &HashMap<usize, usize>, n: usize) {
+        assert_eq!(solution.len(), n);
+
+        for column1 in 0..n {
+            for column2 in column1 + 1..n {
+                let (row1, row2) = (solution[&column1], solution[&column2]);
+
+                assert_ne!(row1, row2);
+                assert_ne!(row1.abs_diff(row2), column1.abs_diff(column2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_four_queens() {
+        let solution = solve_n_queens(4).unwrap();
+
+        assert_no_attacks(&solution, 4);
+    }
+
+    #[test]
+    fn test_eight_queens() {
+        let solution = solve_n_queens(8).unwrap();
+
+        assert_no_attacks(&solution, 8);
+    }
+
+    #[test]
+    fn test_three_queens_has_no_solution() {
+        assert_eq!(solve_n_queens(3), None);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Propagation, SearchOptions, VariableOrdering, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// The matches played at the same time, each as the two teams' numbers,
+/// and with an odd number of teams the one team sitting the round out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round {
+    pub matches: Vec<(usize, usize)>,
+    pub bye: Option<usize>,
+}
+
+pub type Schedule = Vec<Round>;
+
+/// Draws up a round robin for the teams numbered `0..teams`: every pair of
+/// teams meets exactly once, and every team plays once per round.
+///
+/// Each match is a variable whose value is its round, and the matches of a
+/// team must all be in different rounds. An odd number of teams is paired
+/// with a phantom team, whose opponent in a round has a bye.
+pub fn schedule(teams: usize) -> Option<Schedule> {
+    let players = teams + teams % 2;
+    let rounds = players.saturating_sub(1);
+    let matches: Vec<(usize, usize)> = (0..players)
+        .flat_map(|team1| (team1 + 1..players).map(move |team2| (team1, team2)))
+        .collect();
+    let domains = matches
+        .iter()
+        .map(|&(team1, team2)| {
+            // The rounds are interchangeable, so team 0 can be made to meet
+            // the others in order.
+            let rounds = if team1 == 0 {
+                vec![team2 - 1]
+            } else {
+                (0..rounds).collect()
+            };
+            ((team1, team2), rounds)
+        })
+        .collect();
+
+    let mut csp = CSP::new(matches.clone(), domains);
+
+    for team in 0..players {
+        let played = matches
+            .iter()
+            .copied()
+            .filter(|&(team1, team2)| team1 == team || team2 == team)
+            .collect();
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(played)));
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    let mut schedule: Schedule = (0..rounds)
+        .map(|_| Round {
+            matches: vec![],
+            bye: None,
+        })
+        .collect();
+    for &(team1, team2) in &matches {
+        let round = &mut schedule[solution[&(team1, team2)]];
+
+        if team2 == teams {
+            round.bye = Some(team1);
+        } else {
+            round.matches.push((team1, team2));
+        }
+    }
+
+    Some(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_valid_round_robin(schedule: &Schedule, teams: usize) {
+        let mut met: Vec<(usize, usize)> = vec![];
+
+        assert_eq!(schedule.len(), teams + teams % 2 - 1);
+        for round in schedule {
+            let mut playing: Vec<usize> = round
+                .matches
+                .iter()
+                .flat_map(|&(team1, team2)| [team1, team2])
+                .chain(round.bye)
+                .collect();
+            playing.sort();
+            assert_eq!(playing, (0..teams).collect::<Vec<usize>>());
+            assert_eq!(round.bye.is_some(), teams % 2 == 1);
+
+            met.extend(&round.matches);
+        }
+
+        met.sort();
+        let pairs: Vec<(usize, usize)> = (0..teams)
+            .flat_map(|team1| (team1 + 1..teams).map(move |team2| (team1, team2)))
+            .collect();
+        assert_eq!(met, pairs);
+    }
+
+    #[test]
+    fn test_schedule_four_teams() {
+        assert_valid_round_robin(&schedule(4).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_schedule_six_teams() {
+        assert_valid_round_robin(&schedule(6).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_schedule_odd_teams_gives_each_one_bye() {
+        let schedule = schedule(5).unwrap();
+
+        assert_valid_round_robin(&schedule, 5);
+        let mut byes: Vec<usize> = schedule.iter().filter_map(|round| round.bye).collect();
+        byes.sort();
+        assert_eq!(byes, vec![0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, AtMostKConstraint, Propagation, SearchOptions, VariableOrdering, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// A shift to staff: the `slot`-th shift (morning, evening, ...) of `day`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Shift {
+    pub day: usize,
+    pub slot: usize,
+}
+
+pub type Employee<'a> = &'a str;
+
+/// Assigns one of `employees` to each of `shifts`, so that nobody works two
+/// shifts of the same day or more than `max_per_employee` shifts in all.
+///
+/// `availability` lists the shifts each employee can work; an employee
+/// without an entry can work any of them.
+pub fn schedule<'a>(
+    shifts: &[Shift],
+    employees: &[Employee<'a>],
+    availability: &HashMap<Employee<'a>, Vec<Shift>>,
+    max_per_employee: usize,
+) -> Option<HashMap<Shift, Employee<'a>>> {
+    // The CSP works on indices into `employees`, as its values must be
+    // `'static` for the constraints to hold them.
+    let domains = shifts
+        .iter()
+        .map(|shift| {
+            let available = (0..employees.len())
+                .filter(|employee| {
+                    availability
+                        .get(employees[*employee])
+                        .is_none_or(|available| available.contains(shift))
+                })
+                .collect();
+            (*shift, available)
+        })
+        .collect();
+
+    let mut csp = CSP::new(shifts.to_vec(), domains);
+
+    let mut days: HashMap<usize, Vec<Shift>> = HashMap::new();
+    for shift in shifts {
+        days.entry(shift.day).or_default().push(*shift);
+    }
+    for same_day in days.into_values() {
+        if same_day.len() > 1 {
+            csp.add_constraint(Arc::new(AllDifferentConstraint::new(same_day)));
+        }
+    }
+
+    for employee in 0..employees.len() {
+        csp.add_constraint(Arc::new(AtMostKConstraint::new(
+            shifts.to_vec(),
+            employee,
+            max_per_employee,
+        )));
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    Some(
+        solution
+            .into_iter()
+            .map(|(shift, employee)| (shift, employees[employee]))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Morning and evening shifts over three days.
+    fn shifts() -> Vec<Shift> {
+        (0..3)
+            .flat_map(|day| (0..2).map(move |slot| Shift { day, slot }))
+            .collect()
+    }
+
+    #[test]
+    fn test_schedule_respects_limits_and_availability() {
+        let shifts = shifts();
+        let employees = ["Ana", "Ben", "Cho"];
+        // Ben only works evenings; Cho can't come on day 0.
+        let availability: HashMap<Employee, Vec<Shift>> = [
+            (
+                "Ben",
+                shifts
+                    .iter()
+                    .copied()
+                    .filter(|shift| shift.slot == 1)
+                    .collect(),
+            ),
+            (
+                "Cho",
+                shifts
+                    .iter()
+                    .copied()
+                    .filter(|shift| shift.day != 0)
+                    .collect(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let roster = schedule(&shifts, &employees, &availability, 2).unwrap();
+
+        assert_eq!(roster.len(), shifts.len());
+        for employee in employees {
+            let worked: Vec<&Shift> = roster
+                .iter()
+                .filter(|(_, assigned)| **assigned == employee)
+                .map(|(shift, _)| shift)
+                .collect();
+
+            assert!(worked.len() <= 2);
+            for shift in &worked {
+                assert!(availability
+                    .get(employee)
+                    .is_none_or(|available| available.contains(shift)));
+                assert_eq!(
+                    worked.iter().filter(|other| other.day == shift.day).count(),
+                    1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_schedule_fails_when_too_few_shifts_allowed() {
+        let shifts = shifts();
+
+        assert_eq!(schedule(&shifts, &["Ana", "Ben"], &HashMap::new(), 2), None);
+        assert!(schedule(&shifts, &["Ana", "Ben"], &HashMap::new(), 3).is_some());
+    }
+}
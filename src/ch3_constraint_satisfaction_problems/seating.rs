@@ -0,0 +1,203 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Constraint, Propagation, SearchOptions, VariableOrdering, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+pub type Guest<'a> = &'a str;
+
+/// Who must or must not sit beside whom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule<'a> {
+    Together(Guest<'a>, Guest<'a>),
+    Apart(Guest<'a>, Guest<'a>),
+}
+
+/// Whether two seats of a round table with `seats` seats are side by side,
+/// the last seat being next to the first.
+fn neighbors(seats: usize, seat1: usize, seat2: usize) -> bool {
+    (seat1 + 1) % seats == seat2 || (seat2 + 1) % seats == seat1
+}
+
+/// The seats of two guests, as far as the assignment of guests to seats
+/// goes.
+fn seats_of(
+    assignment: &HashMap<usize, usize>,
+    guests: (usize, usize),
+) -> (Option<usize>, Option<usize>) {
+    let seat_of = |guest| {
+        assignment
+            .iter()
+            .find(|(_, seated)| **seated == guest)
+            .map(|(seat, _)| *seat)
+    };
+
+    (seat_of(guests.0), seat_of(guests.1))
+}
+
+/// Two guests must sit side by side at a round table. The variables are
+/// the seats, whose values are the guests; a guest left without a seat
+/// once all seats are taken fails the constraint.
+#[derive(Clone, Debug)]
+pub struct NextToConstraint {
+    seats: Vec<usize>,
+    guests: (usize, usize),
+}
+
+impl NextToConstraint {
+    pub fn new(seats: usize, guest1: usize, guest2: usize) -> Self {
+        Self {
+            seats: (0..seats).collect(),
+            guests: (guest1, guest2),
+        }
+    }
+}
+
+impl Constraint<usize, usize> for NextToConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.seats
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, usize>) -> bool {
+        match seats_of(assignment, self.guests) {
+            (Some(seat1), Some(seat2)) => neighbors(self.seats.len(), seat1, seat2),
+            _ => self.seats.iter().any(|seat| !assignment.contains_key(seat)),
+        }
+    }
+}
+
+/// Two guests must not sit side by side at a round table, with the seats
+/// as variables and the guests as values.
+#[derive(Clone, Debug)]
+pub struct NotNextToConstraint {
+    seats: Vec<usize>,
+    guests: (usize, usize),
+}
+
+impl NotNextToConstraint {
+    pub fn new(seats: usize, guest1: usize, guest2: usize) -> Self {
+        Self {
+            seats: (0..seats).collect(),
+            guests: (guest1, guest2),
+        }
+    }
+}
+
+impl Constraint<usize, usize> for NotNextToConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.seats
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, usize>) -> bool {
+        match seats_of(assignment, self.guests) {
+            (Some(seat1), Some(seat2)) => !neighbors(self.seats.len(), seat1, seat2),
+            _ => true,
+        }
+    }
+}
+
+/// Seats `guests` around a round table of `seats` seats, one per seat,
+/// following `rules`. With more guests than seats, some are left out.
+///
+/// # Panics
+///
+/// Panics if a rule names someone who isn't among `guests`.
+pub fn arrange<'a>(
+    seats: usize,
+    guests: &[Guest<'a>],
+    rules: &[Rule<'a>],
+) -> Option<HashMap<usize, Guest<'a>>> {
+    // The CSP works on indices into `guests`, as its values must be
+    // `'static` for the constraints to hold them.
+    let index = |guest: Guest| {
+        guests
+            .iter()
+            .position(|known| *known == guest)
+            .unwrap_or_else(|| panic!("{} is not a guest", guest))
+    };
+    let variables: Vec<usize> = (0..seats).collect();
+    let domains = variables
+        .iter()
+        .map(|seat| (*seat, (0..guests.len()).collect()))
+        .collect();
+
+    let mut csp = CSP::new(variables.clone(), domains);
+
+    csp.add_constraint(Arc::new(AllDifferentConstraint::new(variables)));
+    for rule in rules {
+        match *rule {
+            Rule::Together(guest1, guest2) => csp.add_constraint(Arc::new(NextToConstraint::new(
+                seats,
+                index(guest1),
+                index(guest2),
+            ))),
+            Rule::Apart(guest1, guest2) => csp.add_constraint(Arc::new(NotNextToConstraint::new(
+                seats,
+                index(guest1),
+                index(guest2),
+            ))),
+        }
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    Some(
+        solution
+            .into_iter()
+            .map(|(seat, guest)| (seat, guests[guest]))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seat_of(arrangement: &HashMap<usize, Guest>, guest: Guest) -> usize {
+        arrangement
+            .iter()
+            .find(|(_, seated)| **seated == guest)
+            .map(|(seat, _)| *seat)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_arrange_keeps_rivals_apart() {
+        let guests = ["Ada", "Bo", "Cy", "Di", "Ed"];
+        let rules = [
+            Rule::Together("Ada", "Bo"),
+            Rule::Together("Bo", "Cy"),
+            Rule::Apart("Ada", "Di"),
+            Rule::Apart("Cy", "Ed"),
+        ];
+
+        let arrangement = arrange(5, &guests, &rules).unwrap();
+
+        assert_eq!(arrangement.len(), 5);
+        let seat = |guest| seat_of(&arrangement, guest);
+        assert!(neighbors(5, seat("Ada"), seat("Bo")));
+        assert!(neighbors(5, seat("Bo"), seat("Cy")));
+        assert!(!neighbors(5, seat("Ada"), seat("Di")));
+        assert!(!neighbors(5, seat("Cy"), seat("Ed")));
+    }
+
+    #[test]
+    fn test_arrange_fails_when_everyone_is_apart() {
+        let guests = ["Ada", "Bo", "Cy"];
+        let rules = [Rule::Apart("Ada", "Bo")];
+
+        // Around a table of three, everyone sits next to everyone.
+        assert_eq!(arrange(3, &guests, &rules), None);
+    }
+
+    #[test]
+    fn test_first_and_last_seats_are_neighbors() {
+        assert!(neighbors(6, 0, 5));
+        assert!(neighbors(6, 5, 0));
+        assert!(!neighbors(6, 0, 4));
+    }
+}
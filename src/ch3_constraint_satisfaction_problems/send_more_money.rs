@@ -0,0 +1,120 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Constraint, FunctionConstraint, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+const ADDENDS: [&str; 2] = ["SEND", "MORE"];
+const SUM: &str = "MONEY";
+
+/// The letters in the order they first appear from the rightmost column, so
+/// that each column can be checked as soon as the search reaches it.
+const LETTERS: [char; 8] = ['D', 'E', 'Y', 'N', 'R', 'O', 'S', 'M'];
+
+/// The last `column + 1` digits of SEND + MORE must match those of MONEY,
+/// counting columns from the right. Partial assignments are always accepted:
+/// the sum is only worked out once every letter of those columns has a
+/// digit.
+#[derive(Clone, Debug)]
+pub struct ColumnSumConstraint {
+    column: usize,
+    letters: Vec<char>,
+}
+
+impl ColumnSumConstraint {
+    pub fn new(column: usize) -> Self {
+        let mut letters: Vec<char> = vec![];
+
+        for word in ADDENDS.iter().chain([&SUM]) {
+            for letter in word.chars().rev().take(column + 1) {
+                if !letters.contains(&letter) {
+                    letters.push(letter);
+                }
+            }
+        }
+
+        Self { column, letters }
+    }
+
+    /// The number spelled by the last `column + 1` letters of `word`.
+    fn tail(&self, word: &str, assignment: &HashMap<char, u32>) -> u32 {
+        let letters: Vec<char> = word.chars().rev().take(self.column + 1).collect();
+
+        letters
+            .iter()
+            .rev()
+            .fold(0, |number, letter| number * 10 + assignment[letter])
+    }
+}
+
+impl Constraint<char, u32> for ColumnSumConstraint {
+    fn get_variables(&self) -> &Vec<char> {
+        &self.letters
+    }
+
+    fn satisfied(&self, assignment: &HashMap<char, u32>) -> bool {
+        if !self
+            .letters
+            .iter()
+            .all(|letter| assignment.contains_key(letter))
+        {
+            return true;
+        }
+
+        let modulus = 10u32.pow(self.column as u32 + 1);
+        let addends: u32 = ADDENDS.iter().map(|word| self.tail(word, assignment)).sum();
+
+        addends % modulus == self.tail(SUM, assignment)
+    }
+}
+
+/// Solves the cryptarithm SEND + MORE = MONEY, where every letter is a
+/// distinct digit and no word starts with a zero.
+pub fn solve() -> Option<HashMap<char, u32>> {
+    let domains = LETTERS
+        .iter()
+        .map(|letter| (*letter, (0..=9).collect()))
+        .collect();
+
+    let mut csp = CSP::new(LETTERS.to_vec(), domains);
+
+    csp.add_constraint(Arc::new(AllDifferentConstraint::new(LETTERS.to_vec())));
+    for leading in ['S', 'M'] {
+        csp.add_constraint(Arc::new(FunctionConstraint::new(
+            vec![leading],
+            move |assignment: &HashMap<char, u32>| assignment.get(&leading) != Some(&0),
+        )));
+    }
+    for column in 0..SUM.len() {
+        csp.add_constraint(Arc::new(ColumnSumConstraint::new(column)));
+    }
+
+    csp.backtracking_search(HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_more_money() {
+        let solution = solve().unwrap();
+
+        let number = |word: &str| {
+            word.chars()
+                .fold(0, |number, letter| number * 10 + solution[&letter])
+        };
+
+        assert_eq!(number("SEND"), 9567);
+        assert_eq!(number("MORE"), 1085);
+        assert_eq!(number("MONEY"), 10652);
+    }
+
+    #[test]
+    fn test_column_sum_waits_for_its_letters() {
+        let units = ColumnSumConstraint::new(0);
+        let assignment: HashMap<char, u32> = [('D', 7), ('E', 5)].into_iter().collect();
+
+        assert_eq!(units.get_variables(), &vec!['D', 'E', 'Y']);
+        assert!(units.satisfied(&assignment));
+    }
+}
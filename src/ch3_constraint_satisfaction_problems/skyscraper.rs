@@ -0,0 +1,156 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{Constraint, Propagation, SearchOptions, VariableOrdering},
+    grid::{to_grid, Cell, Grid},
+    latin_square::latin_square_csp,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// The clues around a Skyscraper grid: how many buildings can be seen
+/// looking into each column from the top and bottom, and into each row from
+/// the left and right. `0` means the side has no clue there.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Clues {
+    pub top: Vec<u32>,
+    pub bottom: Vec<u32>,
+    pub left: Vec<u32>,
+    pub right: Vec<u32>,
+}
+
+/// How many of `heights`, seen in order, are taller than every one before
+/// them and so not hidden.
+fn visible(heights: impl Iterator<Item = u32>) -> u32 {
+    let mut tallest = 0;
+    let mut count = 0;
+
+    for height in heights {
+        if height > tallest {
+            tallest = height;
+            count += 1;
+        }
+    }
+
+    count
+}
+
+/// Looking along `line`, from its first cell, exactly `clue` buildings are
+/// visible. Only judged once the whole line is assigned.
+#[derive(Clone, Debug)]
+pub struct VisibilityConstraint {
+    line: Vec<Cell>,
+    clue: u32,
+}
+
+impl VisibilityConstraint {
+    pub fn new(line: Vec<Cell>, clue: u32) -> Self {
+        Self { line, clue }
+    }
+}
+
+impl Constraint<Cell, u32> for VisibilityConstraint {
+    fn get_variables(&self) -> &Vec<Cell> {
+        &self.line
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, u32>) -> bool {
+        let heights: Option<Vec<u32>> = self
+            .line
+            .iter()
+            .map(|cell| assignment.get(cell).copied())
+            .collect();
+
+        heights.is_none_or(|heights| visible(heights.into_iter()) == self.clue)
+    }
+}
+
+/// Solves an `n`×`n` Skyscraper puzzle: a Latin square of building heights
+/// 1 to `n` matching the visibility `clues`.
+pub fn solve(n: usize, clues: &Clues) -> Option<Grid> {
+    let mut csp = latin_square_csp(n, &[]);
+
+    let column = |column: usize| -> Vec<Cell> { (0..n).map(|row| (row, column)).collect() };
+    let row = |row: usize| -> Vec<Cell> { (0..n).map(|column| (row, column)).collect() };
+    let reversed = |mut line: Vec<Cell>| {
+        line.reverse();
+        line
+    };
+
+    for i in 0..n {
+        let lines = [
+            (clues.top.get(i), column(i)),
+            (clues.bottom.get(i), reversed(column(i))),
+            (clues.left.get(i), row(i)),
+            (clues.right.get(i), reversed(row(i))),
+        ];
+
+        for (clue, line) in lines {
+            if let Some(&clue) = clue.filter(|clue| **clue != 0) {
+                csp.add_constraint(Arc::new(VisibilityConstraint::new(line, clue)));
+            }
+        }
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    csp.backtracking_search_with_options(HashMap::new(), options)
+        .map(|solution| to_grid(n, &solution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_by_four_skyscraper() {
+        // These clues only admit one solution.
+        let clues = Clues {
+            top: vec![3, 2, 1, 2],
+            bottom: vec![2, 3, 2, 1],
+            left: vec![2, 2, 1, 4],
+            right: vec![2, 2, 4, 1],
+        };
+
+        assert_eq!(
+            solve(4, &clues),
+            Some(vec![
+                vec![2, 1, 4, 3],
+                vec![3, 4, 1, 2],
+                vec![4, 3, 2, 1],
+                vec![1, 2, 3, 4],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_contradictory_clues_have_no_solution() {
+        // A row can't show all four buildings from both ends.
+        let clues = Clues {
+            left: vec![4],
+            right: vec![4],
+            ..Clues::default()
+        };
+
+        assert_eq!(solve(4, &clues), None);
+    }
+
+    #[test]
+    fn test_visibility_constraint() {
+        let line = vec![(0, 0), (0, 1), (0, 2)];
+        let constraint = VisibilityConstraint::new(line, 2);
+        let assignment = |heights: [u32; 3]| -> HashMap<Cell, u32> {
+            [
+                ((0, 0), heights[0]),
+                ((0, 1), heights[1]),
+                ((0, 2), heights[2]),
+            ]
+            .into()
+        };
+
+        assert!(constraint.satisfied(&assignment([2, 1, 3])));
+        assert!(!constraint.satisfied(&assignment([1, 2, 3])));
+        assert!(!constraint.satisfied(&assignment([3, 1, 2])));
+        assert!(constraint.satisfied(&HashMap::from([((0, 0), 3)])));
+    }
+}
@@ -0,0 +1,338 @@
+use crate::ch3_constraint_satisfaction_problems::{
+    csp::{
+        AllDifferentConstraint, Propagation, SearchOptions, SumConstraint, VariableOrdering, CSP,
+    },
+    grid::{grid_variables, Cell},
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// The 81 cells of a Sudoku board as `(row, column)`, with `givens` fixing
+/// the filled ones (`0` for empty) and each row, column and box all
+/// different.
+pub fn sudoku_csp(givens: [[u32; 9]; 9]) -> CSP<Cell, u32> {
+    let cells = grid_variables(9, 9);
+    let domains = cells
+        .iter()
+        .map(|&(row, column)| match givens[row][column] {
+            0 => ((row, column), (1..=9).collect()),
+            given => ((row, column), vec![given]),
+        })
+        .collect();
+
+    let mut csp = CSP::new(cells, domains);
+
+    for i in 0..9 {
+        let row = (0..9).map(|column| (i, column)).collect();
+        let column = (0..9).map(|row| (row, i)).collect();
+        let square = (0..9)
+            .map(|cell| (i / 3 * 3 + cell / 3, i % 3 * 3 + cell % 3))
+            .collect();
+
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(row)));
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(column)));
+        csp.add_constraint(Arc::new(AllDifferentConstraint::new(square)));
+    }
+
+    csp
+}
+
+/// Cells of a killer Sudoku that have to add up to `sum`, with no digit
+/// repeated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cage {
+    pub cells: Vec<Cell>,
+    pub sum: u32,
+}
+
+/// Rules some Sudoku puzzles add to the classic ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SudokuVariant {
+    /// Sudoku-X: both main diagonals hold every digit once as well.
+    Diagonal,
+    /// Killer Sudoku: the digits of each cage add up to its sum.
+    Killer(Vec<Cage>),
+}
+
+/// The CSP of `sudoku_csp` with the extra rules of `variant`.
+pub fn sudoku_variant_csp(givens: [[u32; 9]; 9], variant: &SudokuVariant) -> CSP<Cell, u32> {
+    let mut csp = sudoku_csp(givens);
+
+    match variant {
+        SudokuVariant::Diagonal => {
+            let diagonal = (0..9).map(|i| (i, i)).collect();
+            let anti_diagonal = (0..9).map(|i| (i, 8 - i)).collect();
+
+            csp.add_constraint(Arc::new(AllDifferentConstraint::new(diagonal)));
+            csp.add_constraint(Arc::new(AllDifferentConstraint::new(anti_diagonal)));
+        }
+        SudokuVariant::Killer(cages) => {
+            for cage in cages {
+                csp.add_constraint(Arc::new(AllDifferentConstraint::new(cage.cells.clone())));
+                csp.add_constraint(Arc::new(SumConstraint::new(cage.cells.clone(), cage.sum)));
+            }
+        }
+    }
+
+    csp
+}
+
+/// Solves `grid` under the extra rules of `variant`.
+pub fn solve_variant(grid: &Sudoku, variant: &SudokuVariant) -> Option<Sudoku> {
+    solve_csp(&sudoku_variant_csp(grid.cells, variant))
+}
+
+fn solve_csp(csp: &CSP<Cell, u32>) -> Option<Sudoku> {
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+    let solution = csp.backtracking_search_with_options(HashMap::new(), options)?;
+
+    let mut cells = [[0; 9]; 9];
+    for ((row, column), value) in solution {
+        cells[row][column] = value;
+    }
+
+    Some(Sudoku { cells })
+}
+
+/// A Sudoku board, `0` standing for an empty cell.
+///
+/// Parses from the usual text form, 81 characters read row by row with `.`
+/// or `0` for an empty cell, which may be broken into lines; whitespace is
+/// ignored. Displays back as nine lines of digits and dots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sudoku {
+    pub cells: [[u32; 9]; 9],
+}
+
+impl Sudoku {
+    pub fn csp(&self) -> CSP<Cell, u32> {
+        sudoku_csp(self.cells)
+    }
+
+    /// The board with every cell filled, or `None` if the givens allow no
+    /// solution.
+    pub fn solve(&self) -> Option<Sudoku> {
+        solve_csp(&self.csp())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SudokuParseError {
+    /// The board doesn't have 81 cells; holds how many it has.
+    WrongLength(usize),
+    /// A cell is neither a digit nor `.`.
+    InvalidCharacter(char),
+}
+
+impl Display for SudokuParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SudokuParseError::WrongLength(length) => {
+                write!(f, "a Sudoku has 81 cells, not {}", length)
+            }
+            SudokuParseError::InvalidCharacter(character) => {
+                write!(f, "{:?} is not a digit or '.'", character)
+            }
+        }
+    }
+}
+
+impl Error for SudokuParseError {}
+
+impl FromStr for Sudoku {
+    type Err = SudokuParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .chars()
+            .filter(|character| !character.is_whitespace())
+            .map(|character| match character {
+                '.' => Ok(0),
+                _ => character
+                    .to_digit(10)
+                    .ok_or(SudokuParseError::InvalidCharacter(character)),
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        if values.len() != 81 {
+            return Err(SudokuParseError::WrongLength(values.len()));
+        }
+
+        let mut cells = [[0; 9]; 9];
+        for (i, value) in values.into_iter().enumerate() {
+            cells[i / 9][i % 9] = value;
+        }
+
+        Ok(Sudoku { cells })
+    }
+}
+
+impl Display for Sudoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.cells.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            for value in row {
+                match value {
+                    0 => write!(f, ".")?,
+                    _ => write!(f, "{}", value)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    #[test]
+    fn test_sudoku_csp_keeps_givens_and_solves() {
+        let mut givens = [[0; 9]; 9];
+        givens[0] = [5, 3, 0, 0, 7, 0, 0, 0, 0];
+        givens[8] = [0, 0, 0, 0, 8, 0, 0, 7, 9];
+        let csp = sudoku_csp(givens);
+        let options = SearchOptions {
+            variable_ordering: VariableOrdering::MRV,
+            propagation: Propagation::ForwardChecking,
+            ..SearchOptions::default()
+        };
+
+        let solution = csp
+            .backtracking_search_with_options(HashMap::new(), options)
+            .unwrap();
+
+        assert_eq!(csp.domain(&(0, 0)), Some(&[5][..]));
+        assert_eq!(csp.domain(&(0, 2)).map(<[u32]>::len), Some(9));
+        assert_eq!(solution[&(8, 8)], 9);
+        assert!(csp.is_satisfied(&solution));
+    }
+
+    #[test]
+    fn test_parse_solve_and_display_round_trip() {
+        let sudoku: Sudoku = PUZZLE.parse().unwrap();
+
+        assert_eq!(sudoku.cells[0], [5, 3, 0, 0, 7, 0, 0, 0, 0]);
+        assert_eq!(sudoku.to_string().parse(), Ok(sudoku));
+        assert_eq!(
+            sudoku.solve().unwrap().to_string(),
+            "534678912\n\
+             672195348\n\
+             198342567\n\
+             859761423\n\
+             426853791\n\
+             713924856\n\
+             961537284\n\
+             287419635\n\
+             345286179"
+        );
+    }
+
+    #[test]
+    fn test_parse_accepts_lines_and_zeros() {
+        let lines = PUZZLE
+            .replace('.', "0")
+            .as_bytes()
+            .chunks(9)
+            .map(|row| String::from_utf8(row.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(lines.parse::<Sudoku>(), PUZZLE.parse());
+    }
+
+    fn assert_all_different(digits: impl Iterator<Item = u32>) {
+        let mut digits: Vec<u32> = digits.collect();
+        digits.sort_unstable();
+
+        assert_eq!(digits, (1..=9).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_solve_diagonal_variant() {
+        let mut grid = Sudoku { cells: [[0; 9]; 9] };
+        grid.cells[0][0] = 9;
+        grid.cells[4][4] = 1;
+
+        let solution = solve_variant(&grid, &SudokuVariant::Diagonal).unwrap();
+
+        assert_eq!(solution.cells[0][0], 9);
+        assert_eq!(solution.cells[4][4], 1);
+        assert_all_different((0..9).map(|i| solution.cells[i][i]));
+        assert_all_different((0..9).map(|i| solution.cells[i][8 - i]));
+        for row in solution.cells {
+            assert_all_different(row.into_iter());
+        }
+    }
+
+    #[test]
+    fn test_solve_killer_variant() {
+        let grid = Sudoku { cells: [[0; 9]; 9] };
+        let cages = vec![
+            Cage {
+                cells: vec![(0, 0), (0, 1)],
+                sum: 3,
+            },
+            Cage {
+                cells: vec![(1, 0), (2, 0), (2, 1)],
+                sum: 24,
+            },
+        ];
+
+        let solution = solve_variant(&grid, &SudokuVariant::Killer(cages)).unwrap();
+        let cells = solution.cells;
+
+        assert_eq!(cells[0][0] + cells[0][1], 3);
+        assert_eq!(cells[1][0] + cells[2][0] + cells[2][1], 24);
+        assert_ne!(cells[2][0], cells[2][1]);
+        for column in 0..9 {
+            assert_all_different(cells.iter().map(|row| row[column]));
+        }
+    }
+
+    #[test]
+    fn test_killer_cages_rule_out_the_only_solution() {
+        let grid: Sudoku = PUZZLE.parse().unwrap();
+        let cage = |sum| Cage {
+            cells: vec![(0, 2), (0, 3)],
+            sum,
+        };
+
+        // The puzzle's one solution has 4 and 6 in these cells.
+        assert_eq!(
+            solve_variant(&grid, &SudokuVariant::Killer(vec![cage(10)])),
+            grid.solve()
+        );
+        assert_eq!(
+            solve_variant(&grid, &SudokuVariant::Killer(vec![cage(11)])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_boards() {
+        assert_eq!(
+            PUZZLE[1..].parse::<Sudoku>(),
+            Err(SudokuParseError::WrongLength(80))
+        );
+        assert_eq!(
+            PUZZLE.replacen('.', "x", 1).parse::<Sudoku>(),
+            Err(SudokuParseError::InvalidCharacter('x'))
+        );
+    }
+}
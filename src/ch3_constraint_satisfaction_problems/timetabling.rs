@@ -0,0 +1,153 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{
+    AllDifferentConstraint, Constraint, Propagation, SearchOptions, VariableOrdering, CSP,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// An exam sat by `students` students.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Exam {
+    pub id: usize,
+    pub students: usize,
+}
+
+/// A room seating up to `capacity` students.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Room {
+    pub id: usize,
+    pub capacity: usize,
+}
+
+pub type Slot = usize;
+
+/// Two exams sharing a student must be held in different timeslots, in
+/// whichever rooms.
+#[derive(Clone, Debug)]
+pub struct ConflictConstraint {
+    exams: Vec<Exam>,
+}
+
+impl ConflictConstraint {
+    pub fn new(exam1: Exam, exam2: Exam) -> Self {
+        Self {
+            exams: vec![exam1, exam2],
+        }
+    }
+}
+
+impl Constraint<Exam, (Room, Slot)> for ConflictConstraint {
+    fn get_variables(&self) -> &Vec<Exam> {
+        &self.exams
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Exam, (Room, Slot)>) -> bool {
+        match (
+            assignment.get(&self.exams[0]),
+            assignment.get(&self.exams[1]),
+        ) {
+            (Some((_, slot1)), Some((_, slot2))) => slot1 != slot2,
+            _ => true,
+        }
+    }
+}
+
+/// Gives each of `exams` a room large enough for it and a timeslot, so that
+/// no room holds two exams at once and the two exams of each pair of
+/// `conflicts` (indices into `exams`) are held at different times.
+pub fn timetable(
+    exams: &[Exam],
+    conflicts: &[(usize, usize)],
+    slots: &[Slot],
+    rooms: &[Room],
+) -> Option<HashMap<Exam, (Room, Slot)>> {
+    let domains = exams
+        .iter()
+        .map(|exam| {
+            let places = rooms
+                .iter()
+                .filter(|room| room.capacity >= exam.students)
+                .flat_map(|room| slots.iter().map(|slot| (*room, *slot)))
+                .collect();
+            (*exam, places)
+        })
+        .collect();
+
+    let mut csp = CSP::new(exams.to_vec(), domains);
+
+    csp.add_constraint(Arc::new(AllDifferentConstraint::new(exams.to_vec())));
+    for &(exam1, exam2) in conflicts {
+        csp.add_constraint(Arc::new(ConflictConstraint::new(
+            exams[exam1],
+            exams[exam2],
+        )));
+    }
+
+    let options = SearchOptions {
+        variable_ordering: VariableOrdering::MRV,
+        propagation: Propagation::ForwardChecking,
+        ..SearchOptions::default()
+    };
+
+    csp.backtracking_search_with_options(HashMap::new(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGEBRA: Exam = Exam {
+        id: 0,
+        students: 80,
+    };
+    const BIOLOGY: Exam = Exam {
+        id: 1,
+        students: 30,
+    };
+    const CHEMISTRY: Exam = Exam {
+        id: 2,
+        students: 25,
+    };
+
+    const HALL: Room = Room {
+        id: 0,
+        capacity: 100,
+    };
+    const LAB: Room = Room {
+        id: 1,
+        capacity: 40,
+    };
+
+    #[test]
+    fn test_conflicting_exams_get_different_slots() {
+        let exams = [ALGEBRA, BIOLOGY, CHEMISTRY];
+
+        let timetable = timetable(&exams, &[(0, 1)], &[0, 1], &[HALL, LAB]).unwrap();
+
+        assert_eq!(timetable.len(), 3);
+        assert_eq!(timetable[&ALGEBRA].0, HALL);
+        assert_ne!(timetable[&ALGEBRA].1, timetable[&BIOLOGY].1);
+        for (exam, (room, _)) in &timetable {
+            assert!(room.capacity >= exam.students);
+        }
+        for exam1 in exams {
+            for exam2 in exams {
+                assert!(exam1 == exam2 || timetable[&exam1] != timetable[&exam2]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_timetable_fails_without_a_large_enough_room() {
+        assert_eq!(timetable(&[ALGEBRA], &[], &[0, 1], &[LAB]), None);
+    }
+
+    #[test]
+    fn test_timetable_fails_when_all_exams_clash() {
+        let exams = [ALGEBRA, BIOLOGY, CHEMISTRY];
+
+        // Three pairwise conflicting exams can't share two slots.
+        assert_eq!(
+            timetable(&exams, &[(0, 1), (1, 2), (0, 2)], &[0, 1], &[HALL, LAB]),
+            None
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::ch3_constraint_satisfaction_problems::csp::{Constraint, CSP};
+use std::{collections::HashMap, sync::Arc};
+
+/// The direction a word is read in from its first letter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Right,
+    Down,
+    DownRight,
+    DownLeft,
+}
+
+impl Orientation {
+    const ALL: [Orientation; 4] = [
+        Orientation::Right,
+        Orientation::Down,
+        Orientation::DownRight,
+        Orientation::DownLeft,
+    ];
+
+    /// How far apart consecutive letters are, in rows and columns.
+    fn step(self) -> (isize, isize) {
+        match self {
+            Orientation::Right => (0, 1),
+            Orientation::Down => (1, 0),
+            Orientation::DownRight => (1, 1),
+            Orientation::DownLeft => (1, -1),
+        }
+    }
+}
+
+/// Where a word goes on the grid: the cell of its first letter and the
+/// direction of the rest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub row: usize,
+    pub column: usize,
+    pub orientation: Orientation,
+}
+
+impl Placement {
+    /// The cells covered by a word of `length` letters, in reading order.
+    /// Only meaningful for placements that keep the word on the grid.
+    pub fn cells(&self, length: usize) -> impl Iterator<Item = (usize, usize)> {
+        let Placement { row, column, .. } = *self;
+        let (row_step, column_step) = self.orientation.step();
+
+        (0..length as isize).map(move |i| {
+            (
+                row.wrapping_add_signed(i * row_step),
+                column.wrapping_add_signed(i * column_step),
+            )
+        })
+    }
+
+    /// Every placement of a word of `length` letters on a `rows`×`columns`
+    /// grid.
+    fn all(rows: usize, columns: usize, length: usize) -> Vec<Placement> {
+        let mut placements = vec![];
+
+        for row in 0..rows {
+            for column in 0..columns {
+                for orientation in Orientation::ALL {
+                    let placement = Placement {
+                        row,
+                        column,
+                        orientation,
+                    };
+
+                    if placement
+                        .cells(length)
+                        .all(|(row, column)| row < rows && column < columns)
+                    {
+                        placements.push(placement);
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+}
+
+/// Placed words may only share a cell if they have the same letter there,
+/// which lets them cross. The variables are indices into `words`.
+#[derive(Clone, Debug)]
+pub struct WordSearchConstraint {
+    words: Vec<Vec<char>>,
+    variables: Vec<usize>,
+}
+
+impl WordSearchConstraint {
+    pub fn new(words: &[&str]) -> Self {
+        Self {
+            words: words.iter().map(|word| word.chars().collect()).collect(),
+            variables: (0..words.len()).collect(),
+        }
+    }
+}
+
+impl Constraint<usize, Placement> for WordSearchConstraint {
+    fn get_variables(&self) -> &Vec<usize> {
+        &self.variables
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, Placement>) -> bool {
+        let mut letters: HashMap<(usize, usize), char> = HashMap::new();
+
+        assignment.iter().all(|(word, placement)| {
+            let word = &self.words[*word];
+
+            placement
+                .cells(word.len())
+                .zip(word)
+                .all(|(cell, letter)| *letters.entry(cell).or_insert(*letter) == *letter)
+        })
+    }
+}
+
+/// Places every one of `words` on a grid of `(rows, columns)` cells, letting
+/// them cross wherever they agree on the letter. A word given twice is only
+/// placed once.
+pub fn place_words<'a>(
+    grid_size: (usize, usize),
+    words: &[&'a str],
+) -> Option<HashMap<&'a str, Placement>> {
+    let (rows, columns) = grid_size;
+    let variables: Vec<usize> = (0..words.len()).collect();
+    let domains = variables
+        .iter()
+        .map(|word| {
+            let length = words[*word].chars().count();
+            (*word, Placement::all(rows, columns, length))
+        })
+        .collect();
+
+    let mut csp = CSP::new(variables, domains);
+    csp.add_constraint(Arc::new(WordSearchConstraint::new(words)));
+
+    let solution = csp.backtracking_search(HashMap::new())?;
+
+    Some(
+        solution
+            .into_iter()
+            .map(|(word, placement)| (words[word], placement))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_three_words_on_five_by_five_grid() {
+        let words = ["JOE", "MARY", "SARAH"];
+
+        let placements = place_words((5, 5), &words).unwrap();
+        let mut grid: HashMap<(usize, usize), char> = HashMap::new();
+
+        assert_eq!(placements.len(), 3);
+        for (word, placement) in &placements {
+            for (cell, letter) in placement.cells(word.len()).zip(word.chars()) {
+                assert!(cell.0 < 5 && cell.1 < 5);
+                assert_eq!(*grid.entry(cell).or_insert(letter), letter);
+            }
+        }
+    }
+
+    #[test]
+    fn test_words_may_only_cross_on_a_shared_letter() {
+        let constraint = WordSearchConstraint::new(&["CAT", "ART", "OWL"]);
+        let across = |row, column| Placement {
+            row,
+            column,
+            orientation: Orientation::Right,
+        };
+        let down = |row, column| Placement {
+            row,
+            column,
+            orientation: Orientation::Down,
+        };
+
+        // CAT and ART share the A at (0, 1); CAT and OWL clash there.
+        let crossing: HashMap<usize, Placement> = [(0, across(0, 0)), (1, down(0, 1))].into();
+        let clashing: HashMap<usize, Placement> = [(0, across(0, 0)), (2, down(0, 1))].into();
+
+        assert!(constraint.satisfied(&crossing));
+        assert!(!constraint.satisfied(&clashing));
+    }
+
+    #[test]
+    fn test_words_longer_than_the_grid_cannot_be_placed() {
+        assert_eq!(place_words((3, 3), &["LONG"]), None);
+    }
+}
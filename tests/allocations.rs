@@ -0,0 +1,66 @@
+//! Counts the heap allocations made by the solver. Installing a counting
+//! global allocator affects the whole test binary, hence a file of its own.
+
+use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::csp::{
+    NotEqualConstraint, CSP,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_backtracking_search_does_not_allocate_per_node() {
+    // A ring of 30 regions, 3-colored.
+    let variables: Vec<usize> = (0..30).collect();
+    let domains = variables
+        .iter()
+        .map(|variable| (*variable, vec!["red", "green", "blue"]))
+        .collect();
+
+    let mut csp = CSP::new(variables, domains);
+
+    for region in 0..30 {
+        csp.add_constraint(Arc::new(NotEqualConstraint::new(vec![
+            region,
+            (region + 1) % 30,
+        ])));
+    }
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let (solution, stats) = csp.backtracking_search_with_stats(HashMap::new());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(solution.unwrap().len(), 30);
+
+    // Cloning the assignment at every node would cost at least one
+    // allocation per node; growing a single map costs a handful in total.
+    assert!(
+        allocations < stats.nodes_visited / 2,
+        "{} allocations for {} nodes",
+        allocations,
+        stats.nodes_visited
+    );
+}
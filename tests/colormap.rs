@@ -0,0 +1,106 @@
+//! Colors the map in `fixtures/australia.txt`, through the library and
+//! through the `colormap` binary.
+
+use classic_computer_science_propblems_in_rust::ch3_constraint_satisfaction_problems::map_coloring::{
+    color_map, load_adjacency, ColoringError,
+};
+use std::{path::PathBuf, process::Command};
+
+fn fixture() -> PathBuf {
+    fixture_named("australia.txt")
+}
+
+fn fixture_named(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn test_color_map_from_fixture() {
+    let adjacency = load_adjacency(&fixture()).unwrap();
+
+    let coloring = color_map(&adjacency, &["red", "green", "blue"]).unwrap();
+
+    assert_eq!(coloring.len(), 7);
+    for (region, neighbors) in &adjacency {
+        for neighbor in neighbors {
+            assert_ne!(coloring[region], coloring[neighbor]);
+        }
+    }
+    assert_eq!(
+        color_map(&adjacency, &["red", "green"]),
+        Err(ColoringError::Infeasible {
+            min_colors_needed: 3
+        })
+    );
+}
+
+#[test]
+fn test_colormap_binary_prints_one_line_per_region() {
+    let output = Command::new(env!("CARGO_BIN_EXE_colormap"))
+        .args(["--colors", "red,green,blue"])
+        .arg(fixture())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let regions: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split_once(": ").unwrap().0)
+        .collect();
+    assert_eq!(
+        regions,
+        [
+            "New South Wales",
+            "Northern Territory",
+            "Queensland",
+            "South Australia",
+            "Tasmania",
+            "Victoria",
+            "Western Australia"
+        ]
+    );
+}
+
+#[test]
+fn test_colormap_binary_reports_colors_needed() {
+    let output = Command::new(env!("CARGO_BIN_EXE_colormap"))
+        .args(["--colors", "red,green"])
+        .arg(fixture())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "the map needs at least 3 colors\n"
+    );
+}
+
+#[test]
+fn test_colormap_binary_rejects_missing_palette() {
+    let output = Command::new(env!("CARGO_BIN_EXE_colormap"))
+        .arg(fixture())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_colormap_binary_rejects_region_bordering_itself() {
+    let path = fixture_named("self_adjacent.txt");
+    let output = Command::new(env!("CARGO_BIN_EXE_colormap"))
+        .args(["--colors", "red"])
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        format!("{}: line 2: region borders itself\n", path.display())
+    );
+}